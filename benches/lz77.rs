@@ -0,0 +1,62 @@
+//! Throughput of the two LZ77 encoders, the exhaustive [lz77_encode] and the
+//! hash chain based [lz77_encode_fast], on the same inputs.
+//!
+//! Run with `cargo bench --bench lz77`.
+
+use generic_compression::{
+    lz::lz77::{lz77_decode, lz77_encode, lz77_encode_fast},
+    test_util::gen_data,
+};
+
+use std::{hint::black_box, time::Instant};
+
+/// The input sizes benchmarked, in bytes
+const SIZES: [usize; 2] = [16 * 1024, 64 * 1024];
+const MAX_OFFSET: usize = 1024;
+const MAX_LENGTH: usize = 64;
+const MAX_CHAIN: usize = 16;
+const ITERATIONS: u32 = 5;
+/// The seed and redundancy of the generated input, which the lz77 tests
+/// round trip at the same sizes
+const SEED: u64 = 12;
+const REDUNDANCY: f64 = 0.7;
+
+/// Runs the encoder a few times, and prints its throughput.
+fn bench(name: &str, input: &[u8], encode: impl Fn(&[u8]) -> usize) {
+    let start = Instant::now();
+    let mut entries = 0;
+    for _ in 0..ITERATIONS {
+        entries = encode(black_box(input));
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    let throughput = input.len() as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+    println!(
+        "{:<18} {:>7} B  {:>10.2?}  {:>8.2} MiB/s  {:>6} entries",
+        name,
+        input.len(),
+        elapsed,
+        throughput,
+        entries
+    );
+}
+
+fn main() {
+    for size in SIZES {
+        let input = gen_data(SEED, size, REDUNDANCY);
+        // both must produce decodable output before being timed
+        assert_eq!(
+            lz77_decode(&lz77_encode(&input, MAX_OFFSET, MAX_LENGTH)).unwrap(),
+            input
+        );
+        assert_eq!(
+            lz77_decode(&lz77_encode_fast(&input, MAX_OFFSET, MAX_LENGTH, MAX_CHAIN)).unwrap(),
+            input
+        );
+        bench("lz77_encode", &input, |input| {
+            lz77_encode(input, MAX_OFFSET, MAX_LENGTH).len()
+        });
+        bench("lz77_encode_fast", &input, |input| {
+            lz77_encode_fast(input, MAX_OFFSET, MAX_LENGTH, MAX_CHAIN).len()
+        });
+    }
+}
@@ -1,82 +1,37 @@
 use generic_compression::{
-    lz::{lz77::*, lz78::*, lzw::*},
-    transform::{bwt::*, mtf::*},
+    container::{Algorithm, compress, compress_into, decompress},
+    lz::{DEFAULT_DICTIONARY_SIZE, DEFAULT_LOOKAHEAD, DEFAULT_WINDOW_SIZE},
 };
 
 use std::{
+    error,
     fs::{File, read},
-    io::{Read, Write},
-    path::PathBuf,
+    io::Write,
+    path::{Path, PathBuf},
 };
 
-/// Module providing a simple serialization and deserialization interface, optimized for output size.
-mod io;
-use io::{
-    deserializer::{deserialize_lz77, deserialize_lz78, deserialize_lzw},
-    serializer::{serialize_lz77, serialize_lz78, serialize_lzw},
-};
+/// Module recommending an algorithm based on cheap statistics of the input.
+mod recommend;
+use recommend::recommend_algorithm;
 
 use clap::{Parser, Subcommand};
 
-const HEADER_SIZE: usize = 3;
-const LZ77_HEADER: &[u8; HEADER_SIZE] = b"l77";
-const LZ78_HEADER: &[u8; HEADER_SIZE] = b"l78";
-const LZW_HEADER: &[u8; HEADER_SIZE] = b"lzw";
-const STACK_HEADER: &[u8; HEADER_SIZE] = b"stk";
-
-const LZW_DICIONARY: &[u8; 256] = &{
-    let mut array = [0u8; 256];
-    let mut i = 0;
-    while i < 256 {
-        array[i] = i as u8;
-        i += 1;
-    }
-    array
-};
-
-#[derive(Subcommand)]
-enum Algorithm {
-    /// LZ77 compression algorithm
-    LZ77 {
-        /// The maximum offset to search for matches
-        #[arg(short, long, default_value = "255")]
-        window_size: usize,
-        /// The maximum length of matches
-        #[arg(short, long, default_value = "255")]
-        lookahead_buffer_size: usize,
-    },
-    /// LZ78 compression algorithm
-    LZ78 {
-        /// The maximum offset to search for matches
-        #[arg(short, long, default_value = "255")]
-        lookahead_max: usize,
-        /// The size of the dictionary
-        #[arg(short, long, default_value = "255")]
-        dictionary_size: usize,
-    },
-    /// LZW compression algorithm
-    LZW {
-        /// The maximum offset to search for matches
-        #[arg(short, long, default_value = "255")]
-        lookahead_max: usize,
-    },
-    /// LZW compression algorithm with move-to-front and Burrows-Wheeler transform
-    STACK {
-        /// The maximum offset to search for matches
-        #[arg(short, long, default_value = "255")]
-        lookahead_max: usize,
-    },
-}
-
 #[derive(Subcommand)]
 enum Command {
     /// Compress the input file
+    // an algorithm negates the requirement, so one of the two must be given
+    #[command(subcommand_negates_reqs = true)]
     Compress {
+        /// Report the size every algorithm would produce, without writing the output
+        #[arg(long, required = true)]
+        dry_run: bool,
         #[command(subcommand)]
-        algorithm: Algorithm,
+        algorithm: Option<Algorithm>,
     },
     /// Decompress the input file
     Decompress,
+    /// Recommend a compression algorithm for the input file
+    Recommend,
 }
 
 #[derive(Parser)]
@@ -89,11 +44,109 @@ struct Args {
     #[arg(short, long, default_value = "compressed.out")]
     output: PathBuf,
 
+    /// Overwrite the output file if it already exists. The input file is
+    /// always kept, as with `gzip --keep`
+    #[arg(short, long)]
+    force: bool,
+
     /// The compression algorithm to use (lz77 or lz78)
     #[command(subcommand)]
     command: Command,
 }
 
+/// The algorithms compared by a dry run, with their default parameters.
+fn default_algorithms() -> Vec<Algorithm> {
+    vec![
+        Algorithm::LZ77 {
+            window_size: DEFAULT_WINDOW_SIZE,
+            lookahead_buffer_size: DEFAULT_LOOKAHEAD,
+        },
+        Algorithm::LZ78 {
+            lookahead_max: DEFAULT_LOOKAHEAD,
+            dictionary_size: DEFAULT_DICTIONARY_SIZE,
+        },
+        Algorithm::LZW {
+            lookahead_max: DEFAULT_LOOKAHEAD,
+        },
+        Algorithm::STACK {
+            lookahead_max: DEFAULT_LOOKAHEAD,
+            min_gain: 0,
+        },
+    ]
+}
+
+/// Compresses the data with each of the algorithms into memory, to find out
+/// the size of the output without writing it anywhere.
+///
+/// ## Arguments
+/// - `algorithms` - The algorithms to compare, with their parameters.
+/// - `input_data` - The data to be compressed.
+///
+/// ## Returns
+/// - `Result<Vec<(Algorithm, usize)>, Box<dyn error::Error>>` - The size of
+///   the compressed data, including the header, for each algorithm, or an error.
+fn compressed_sizes(
+    algorithms: Vec<Algorithm>,
+    input_data: &[u8],
+) -> Result<Vec<(Algorithm, usize)>, Box<dyn error::Error>> {
+    let mut buffer = Vec::new();
+    let mut sizes = Vec::with_capacity(algorithms.len());
+    for algorithm in algorithms {
+        compress_into(&algorithm, input_data, &mut buffer)?;
+        sizes.push((algorithm, buffer.len()));
+    }
+    Ok(sizes)
+}
+
+/// Formats the sizes found by [compressed_sizes] as a table, with the
+/// compression ratio of each algorithm.
+///
+/// ## Arguments
+/// - `input_len` - The size of the uncompressed data.
+/// - `sizes` - The algorithms and their compressed sizes.
+///
+/// ## Returns
+/// - `String` - The table, one line per algorithm after a heading.
+fn format_sizes(input_len: usize, sizes: &[(Algorithm, usize)]) -> String {
+    let mut table = format!("{:<8} {:>12} {:>8}\n", "ALGO", "SIZE", "RATIO");
+    for (algorithm, size) in sizes {
+        let name = match algorithm {
+            Algorithm::LZ77 { .. } => "LZ77",
+            Algorithm::LZ78 { .. } => "LZ78",
+            Algorithm::LZW { .. } => "LZW",
+            Algorithm::STACK { .. } => "STACK",
+            Algorithm::STORE => "STORE",
+        };
+        let ratio = input_len as f64 / (*size).max(1) as f64;
+        table.push_str(&format!("{:<8} {:>12} {:>8.3}\n", name, size, ratio));
+    }
+    table
+}
+
+/// Creates the output file. An existing file is only overwritten if `force`
+/// is given, so a mistyped output path can't destroy data.
+///
+/// ## Arguments
+/// - `path` - The path of the output file.
+/// - `force` - Whether to overwrite an existing file.
+///
+/// ## Returns
+/// - `Result<File, Box<dyn error::Error>>` - The created file, or an error if
+///   it exists and `force` isn't given.
+fn create_output(path: &Path, force: bool) -> Result<File, Box<dyn error::Error>> {
+    if force {
+        return Ok(File::create(path)?);
+    }
+    File::create_new(path).map_err(|error| match error.kind() {
+        std::io::ErrorKind::AlreadyExists => format!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        )
+        .into(),
+        _ => error.into(),
+    })
+}
+
 fn main() {
     let args = Args::parse();
 
@@ -101,102 +154,118 @@ fn main() {
     let input_data = read(&args.input).expect("Failed to read input file");
 
     match args.command {
-        Command::Compress { algorithm } => {
-            let mut file = File::create(&args.output).expect("Failed to create output file");
-            match algorithm {
-                Algorithm::LZ77 {
-                    window_size,
-                    lookahead_buffer_size,
-                } => {
-                    file.write(LZ77_HEADER).unwrap();
-                    serialize_lz77(
-                        lz77_encode(&input_data, window_size, lookahead_buffer_size),
-                        window_size,
-                        lookahead_buffer_size,
-                        &mut file,
-                    )
-                }
-                Algorithm::LZ78 {
-                    lookahead_max,
-                    dictionary_size,
-                } => {
-                    file.write(LZ78_HEADER).unwrap();
-                    file.write_all(&dictionary_size.to_le_bytes()).unwrap();
-                    serialize_lz78(
-                        lz78_encode(&input_data, lookahead_max, dictionary_size),
-                        dictionary_size,
-                        &mut file,
-                    )
-                }
-                Algorithm::LZW { lookahead_max } => {
-                    file.write(LZW_HEADER).unwrap();
-                    serialize_lzw(
-                        lzw_encode(&input_data, LZW_DICIONARY, lookahead_max),
-                        &mut file,
-                    )
-                }
-                Algorithm::STACK { lookahead_max } => {
-                    file.write(STACK_HEADER).unwrap();
-                    let (bwt, index) = encode_bwt(&input_data);
-                    file.write_all(&index.to_le_bytes()).unwrap();
-                    let mut ordering = LZW_DICIONARY.to_vec();
-                    let mtf = encode_move_to_front(&bwt, &mut ordering);
-                    let mtf = mtf.into_iter().map(|x| x as u8).collect::<Vec<_>>();
-                    serialize_lzw(
-                        lzw_encode(mtf.as_slice(), LZW_DICIONARY, lookahead_max),
-                        &mut file,
-                    )
-                }
-            }
-            .unwrap();
+        Command::Compress {
+            dry_run: true,
+            algorithm,
+        } => {
+            let algorithms = match algorithm {
+                Some(algorithm) => vec![algorithm],
+                None => default_algorithms(),
+            };
+            let sizes =
+                compressed_sizes(algorithms, &input_data).expect("Failed to compress input file");
+            print!("{}", format_sizes(input_data.len(), &sizes));
         }
+        Command::Compress {
+            dry_run: false,
+            algorithm: Some(algorithm),
+        } => {
+            let mut file =
+                create_output(&args.output, args.force).expect("Failed to create output file");
+            compress(&algorithm, &input_data, &mut file).expect("Failed to compress input file");
+        }
+        Command::Compress {
+            dry_run: false,
+            algorithm: None,
+        } => unreachable!("Parsing requires an algorithm, unless --dry-run is given"),
         Command::Decompress => {
-            let mut file = File::open(&args.input).expect("Failed to open input file");
-            let mut header = [0; HEADER_SIZE];
-            file.read_exact(&mut header)
-                .expect("Failed to read header from input file");
-            let data = match &header {
-                LZ77_HEADER => {
-                    let data: Vec<LZ77entry<u8>> =
-                        deserialize_lz77(&mut file).expect("Failed to decode LZ77 data");
-                    lz77_decode(&data)
-                }
-                LZ78_HEADER => {
-                    let mut dictionary_size_buf = [0; 8];
-                    file.read_exact(&mut dictionary_size_buf)
-                        .expect("Failed to read dictionary_size from input file");
-                    let dictionary_size = usize::from_le_bytes(dictionary_size_buf);
-
-                    let data: Vec<LZ78entry<u8>> =
-                        deserialize_lz78(&mut file).expect("Failed to decode LZ78 data");
-                    lz78_decode(&data, dictionary_size)
-                }
-                LZW_HEADER => {
-                    let data: Vec<usize> =
-                        deserialize_lzw(&mut file).expect("Failed to decode LZW data");
-                    lzw_decode(&data, LZW_DICIONARY)
-                }
-                STACK_HEADER => {
-                    let mut index_buf = [0; 8];
-                    file.read_exact(&mut index_buf)
-                        .expect("Failed to read index from input file");
-                    let index = usize::from_le_bytes(index_buf);
-                    let data: Vec<usize> =
-                        deserialize_lzw(&mut file).expect("Failed to decode LZW data");
-                    let mut ordering = LZW_DICIONARY.to_vec();
-                    let mtf = lzw_decode(&data, &ordering);
-                    let mtf = mtf.into_iter().map(|x| x as usize).collect::<Vec<_>>();
-                    let bwt = decode_move_to_front(mtf.as_slice(), &mut ordering);
-                    let bwt = bwt.into_iter().map(|x| x as u8).collect::<Vec<_>>();
-                    decode_bwt(bwt.as_slice(), index)
-                }
-                header => panic!("Unknown compression algorithm: {:?}", header),
-            };
-            let mut output_file = File::create(&args.output).expect("Failed to create output file");
+            let data =
+                decompress(&mut input_data.as_slice()).expect("Failed to decompress input file");
+            let mut output_file =
+                create_output(&args.output, args.force).expect("Failed to create output file");
             output_file
                 .write_all(&data)
                 .expect("Failed to write decompressed data");
             output_file.flush().expect("Failed to flush output file");
         }
+        Command::Recommend => {
+            let (algorithm, confidence) = recommend_algorithm(&input_data);
+            println!("{:?} (confidence {:.2})", algorithm, confidence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_dry_run_sizes() {
+        let input = b"RATABARBARATABARBARAT, TAMTARAMTAMTAMRAMTAT".repeat(16);
+        let sizes = compressed_sizes(default_algorithms(), &input).unwrap();
+        let table = format_sizes(input.len(), &sizes);
+        let lines: Vec<&str> = table.lines().skip(1).collect();
+        assert_eq!(lines.len(), 4);
+        for (line, algorithm) in lines.iter().zip(default_algorithms()) {
+            let mut file = Cursor::new(Vec::new());
+            compress(&algorithm, &input, &mut file).unwrap();
+            let size: usize = line.split_whitespace().nth(1).unwrap().parse().unwrap();
+            assert_eq!(size, file.into_inner().len());
+        }
+    }
+
+    #[test]
+    fn test_default_parameters() {
+        assert_eq!(
+            (
+                DEFAULT_WINDOW_SIZE,
+                DEFAULT_LOOKAHEAD,
+                DEFAULT_DICTIONARY_SIZE
+            ),
+            (255, 255, 255)
+        );
+        // the arguments left out on the command line are the defaults of a dry run
+        for (name, default) in ["lz77", "lz78", "lzw", "stack"]
+            .into_iter()
+            .zip(default_algorithms())
+        {
+            let args = Args::try_parse_from(["cli", "input", "compress", name]).unwrap();
+            let Command::Compress { algorithm, .. } = args.command else {
+                panic!("Parsed as another command");
+            };
+            assert_eq!(algorithm, Some(default));
+        }
+    }
+
+    #[test]
+    fn test_compress_requires_algorithm() {
+        let err = Args::try_parse_from(["cli", "input", "compress"])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+        for args in [
+            &["cli", "input", "compress", "--dry-run"][..],
+            &["cli", "input", "compress", "lzw"],
+            &["cli", "input", "compress", "--dry-run", "lzw"],
+        ] {
+            assert!(Args::try_parse_from(args).is_ok(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_create_output_force() {
+        let path = std::env::temp_dir().join(format!("create_output_{}", std::process::id()));
+        std::fs::write(&path, b"precious").unwrap();
+        assert!(create_output(&path, false).is_err());
+        assert_eq!(read(&path).unwrap(), b"precious");
+        create_output(&path, true)
+            .unwrap()
+            .write_all(b"replaced")
+            .unwrap();
+        assert_eq!(read(&path).unwrap(), b"replaced");
+        std::fs::remove_file(&path).unwrap();
+        create_output(&path, false).unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -1,5 +0,0 @@
-/// Provides serialization routines for the `io` module.
-pub mod serializer;
-
-/// Provides deserialization routines for the `io` module.
-pub mod deserializer;
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use generic_compression::{
+    analysis::{SAMPLE_SIZE, entropy},
+    lz::{DEFAULT_LOOKAHEAD, DEFAULT_WINDOW_SIZE},
+};
+
+use crate::Algorithm;
+
+/// The length of substrings looked up when estimating match density.
+const MATCH_LENGTH: usize = 4;
+
+/// Cheap statistics of a sample of the input.
+struct Statistics {
+    /// Shannon entropy of the bytes, in bits per byte
+    entropy: f64,
+    /// Fraction of bytes equal to the byte before them
+    runs: f64,
+    /// Fraction of positions starting a substring that already occurred
+    matches: f64,
+}
+
+impl Statistics {
+    fn new(sample: &[u8]) -> Self {
+        let len = sample.len() as f64;
+        let entropy = entropy(sample);
+        let runs = sample.windows(2).filter(|w| w[0] == w[1]).count() as f64 / len;
+        let mut seen = HashSet::new();
+        let matches = sample
+            .windows(MATCH_LENGTH)
+            .filter(|window| !seen.insert(*window))
+            .count() as f64
+            / len;
+        Statistics {
+            entropy,
+            runs,
+            matches,
+        }
+    }
+}
+
+/// Recommends a compression algorithm for the input, based on statistics of
+/// its beginning, without actually compressing anything.
+///
+/// ## Arguments
+/// - `input` - The data to be compressed.
+///
+/// ## Returns
+/// - `(Algorithm, f64)` - The recommended algorithm, with default parameters,
+///   and the confidence of the recommendation, between 0 and 1.
+pub fn recommend_algorithm(input: &[u8]) -> (Algorithm, f64) {
+    let sample = &input[..input.len().min(SAMPLE_SIZE)];
+    if sample.len() < MATCH_LENGTH {
+        // too little data to compress, or to judge
+        return (Algorithm::STORE, 1.0);
+    }
+    let stats = Statistics::new(sample);
+    if stats.entropy > 7.5 && stats.matches < 0.05 {
+        // looks like noise, or already compressed data
+        let confidence = ((stats.entropy - 7.5) * 2.0).min(1.0) * (1.0 - stats.matches * 20.0);
+        (Algorithm::STORE, confidence)
+    } else if stats.runs > 0.5 {
+        // long runs are matches at an offset of one
+        (
+            Algorithm::LZ77 {
+                window_size: DEFAULT_WINDOW_SIZE,
+                lookahead_buffer_size: DEFAULT_LOOKAHEAD,
+            },
+            stats.runs,
+        )
+    } else if stats.matches > 0.3 {
+        (
+            Algorithm::LZ77 {
+                window_size: DEFAULT_WINDOW_SIZE,
+                lookahead_buffer_size: DEFAULT_LOOKAHEAD,
+            },
+            stats.matches,
+        )
+    } else {
+        // few long matches, but a skewed distribution of bytes
+        (
+            Algorithm::STACK {
+                lookahead_max: DEFAULT_LOOKAHEAD,
+                min_gain: 0,
+            },
+            1.0 - stats.entropy / 8.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generic_compression::test_util::gen_data;
+
+    #[test]
+    fn test_recommend_repetitive() {
+        let input = [b"a".repeat(1000), b"b".repeat(1000), b"c".repeat(1000)].concat();
+        let (algorithm, confidence) = recommend_algorithm(&input);
+        assert!(matches!(algorithm, Algorithm::LZ77 { .. }));
+        assert!(confidence > 0.9);
+    }
+
+    #[test]
+    fn test_recommend_random() {
+        let input = gen_data(1, 10000, 0.0);
+        let (algorithm, confidence) = recommend_algorithm(&input);
+        assert!(matches!(algorithm, Algorithm::STORE));
+        assert!(confidence > 0.5);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// How many bytes from the start of the input [is_likely_compressible] examines.
+pub const SAMPLE_SIZE: usize = 1 << 16;
+
+/// The entropy, in bits per byte, above which data is considered noise, or
+/// already compressed.
+const MAX_COMPRESSIBLE_ENTROPY: f64 = 7.5;
+
+/// Computes the order-0 Shannon entropy of the bytes.
+///
+/// ## Arguments
+///
+/// - `data`: The bytes to examine.
+///
+/// ## Returns
+///
+/// The entropy, in bits per byte, between 0 and 8.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::analysis::entropy;
+/// assert_eq!(entropy(b"aaaa"), 0.0);
+/// assert_eq!(entropy(b"abab"), 1.0);
+/// ```
+pub fn entropy(data: &[u8]) -> f64 {
+    let mut histogram = [0usize; 256];
+    for &byte in data {
+        histogram[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Quickly estimates whether compressing the data is worth the time, from
+/// the entropy of its first [SAMPLE_SIZE] bytes. Data that is already
+/// compressed, like JPEG or ZIP files, uses nearly all 8 bits of every byte,
+/// and is rejected. Only the distribution of the bytes is considered, not
+/// their order, so repeats of random data are rejected too. Short samples can't reach a high entropy, so very short
+/// inputs are always considered compressible.
+///
+/// ## Arguments
+///
+/// - `input`: The data to be compressed.
+///
+/// ## Returns
+///
+/// `false` if the data looks like noise, or already compressed data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::analysis::is_likely_compressible;
+/// assert!(is_likely_compressible(&b"to be or not to be".repeat(100)));
+/// ```
+pub fn is_likely_compressible(input: &[u8]) -> bool {
+    let sample = &input[..input.len().min(SAMPLE_SIZE)];
+    entropy(sample) <= MAX_COMPRESSIBLE_ENTROPY
+}
+
+/// Counts how many times every symbol occurs, as needed to build a Huffman
+/// or arithmetic coding model. Symbols are listed in the order they first
+/// occur.
+///
+/// ## Arguments
+///
+/// - `input`: The symbols to count.
+///
+/// ## Returns
+///
+/// Every distinct symbol, with its count.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::analysis::count_frequencies;
+/// assert_eq!(count_frequencies(b"abca"), vec![(b'a', 2), (b'b', 1), (b'c', 1)]);
+/// ```
+pub fn count_frequencies<T: Hash + Eq + Clone>(input: &[T]) -> Vec<(T, u64)> {
+    merge(input.iter().map(|symbol| (symbol, 1)))
+}
+
+/// Merges frequency tables counted separately, for example over chunks of a
+/// large input, or in parallel, into a single table. Symbols are listed in
+/// the order they first occur in the tables, so merging the tables of
+/// consecutive chunks gives the same table as counting the whole input with
+/// [count_frequencies].
+///
+/// ## Arguments
+///
+/// - `tables`: The frequency tables to merge.
+///
+/// ## Returns
+///
+/// Every distinct symbol, with the sum of its counts.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::analysis::merge_frequencies;
+/// let merged = merge_frequencies(&[vec![(b'a', 2), (b'b', 1)], vec![(b'c', 4), (b'a', 1)]]);
+/// assert_eq!(merged, vec![(b'a', 3), (b'b', 1), (b'c', 4)]);
+/// ```
+pub fn merge_frequencies<T: Hash + Eq + Clone>(tables: &[Vec<(T, u64)>]) -> Vec<(T, u64)> {
+    merge(
+        tables
+            .iter()
+            .flatten()
+            .map(|(symbol, count)| (symbol, *count)),
+    )
+}
+
+fn merge<'a, T: Hash + Eq + Clone + 'a>(
+    counts: impl Iterator<Item = (&'a T, u64)>,
+) -> Vec<(T, u64)> {
+    let mut indices: HashMap<&T, usize> = HashMap::new();
+    let mut table: Vec<(T, u64)> = Vec::new();
+    for (symbol, count) in counts {
+        let index = *indices.entry(symbol).or_insert_with(|| {
+            table.push((symbol.clone(), 0));
+            table.len() - 1
+        });
+        table[index].1 += count;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_data;
+
+    #[test]
+    fn test_is_likely_compressible() {
+        let text = b"she sells sea shells by the sea shore, ".repeat(500);
+        assert!(is_likely_compressible(&text));
+
+        let random = gen_data(1, 100000, 0.0);
+        assert!(entropy(&random) > 7.9);
+        assert!(!is_likely_compressible(&random));
+    }
+
+    #[test]
+    fn test_merge_frequencies() {
+        let input = gen_data(2, 10000, 0.5);
+        let (first, second) = input.split_at(3000);
+        let merged = merge_frequencies(&[count_frequencies(first), count_frequencies(second)]);
+        assert_eq!(merged, count_frequencies(&input));
+        assert_eq!(
+            merged.iter().map(|(_, count)| count).sum::<u64>(),
+            input.len() as u64
+        );
+        assert!(merge_frequencies::<u8>(&[]).is_empty());
+    }
+}
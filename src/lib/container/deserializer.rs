@@ -0,0 +1,396 @@
+use crate::{
+    encoding::elias::gamma_decode,
+    lz::{
+        lz77::LZ77entry,
+        lz78::{BoundedLZ78tuple, LZ78entry},
+    },
+    transform::bwt::column_counts,
+};
+use bits_io::prelude::{BitRead, BitSlice};
+use num_traits::FromBytes;
+
+use super::serializer::NIBBLE_MAX;
+
+use std::{
+    error, fmt,
+    io::{self, Read},
+};
+
+/// Error returned when a stream ends before all of the entries its length
+/// prefix declares were read.
+#[derive(Debug, PartialEq)]
+pub struct TruncatedError {
+    /// The number of entries read before the stream ended, which is also the
+    /// index of the entry that couldn't be read
+    pub entries_read: usize,
+    /// The number of entries the stream declared
+    pub declared: usize,
+}
+
+impl fmt::Display for TruncatedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Stream ended while reading entry {} of {}",
+            self.entries_read, self.declared
+        )
+    }
+}
+
+impl error::Error for TruncatedError {}
+
+/// Replaces an unexpected end of the stream with a [TruncatedError], leaving
+/// other errors as they are.
+///
+/// ## Arguments
+/// - `entries_read` - The number of entries read so far.
+/// - `declared` - The number of entries the stream declared.
+///
+/// ## Returns
+/// - `impl FnOnce(Box<dyn error::Error>) -> Box<dyn error::Error>` - The mapping, for `map_err`.
+fn truncated(
+    entries_read: usize,
+    declared: usize,
+) -> impl FnOnce(Box<dyn error::Error>) -> Box<dyn error::Error> {
+    move |err| match err.downcast_ref::<io::Error>() {
+        Some(io_err) if io_err.kind() == io::ErrorKind::UnexpectedEof => Box::new(TruncatedError {
+            entries_read,
+            declared,
+        }),
+        _ => err,
+    }
+}
+
+/// Deserializes a `usize` value from a specified number of bytes.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+/// - `num_bytes` - The number of bytes to deserialize the value from.
+///
+/// ## Returns
+/// - `Result<usize, Box<dyn error::Error>>` - The deserialized `usize` value or an error.
+pub(crate) fn deserialize_usize<R: Read>(
+    state: &mut R,
+    num_bytes: u8,
+) -> Result<usize, Box<dyn error::Error>> {
+    let mut buffer = vec![0; num_bytes as usize];
+    state.read_exact(&mut buffer)?;
+    Ok(match num_bytes {
+        1 => buffer[0] as usize,
+        2 => u16::from_le_bytes(buffer.as_slice().try_into()?) as usize,
+        4 => u32::from_le_bytes(buffer.as_slice().try_into()?) as usize,
+        8 => u64::from_le_bytes(buffer.as_slice().try_into()?) as usize,
+        _ => return Err(format!("Unsupported integer width: {}", num_bytes).into()),
+    })
+}
+
+/// Deserializes a single byte from the input stream.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the byte from.
+///
+/// ## Returns
+/// - `Result<u8, Box<dyn error::Error>>` - The deserialized byte value or an error.
+fn deserialize_byte<R: Read>(state: &mut R) -> Result<u8, Box<dyn error::Error>> {
+    let mut buffer = [0; 1];
+    state.read_exact(&mut buffer)?;
+    Ok(buffer[0])
+}
+
+/// Deserializes a vector of `LZ77entry` values from the input stream.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+///
+/// ## Returns
+/// - `Result<Vec<LZ77entry<T>>, Box<dyn error::Error>>` - The deserialized vector of `LZ77entry` values or an error.
+pub fn deserialize_lz77<R: Read, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+) -> Result<Vec<LZ77entry<T>>, Box<dyn error::Error>> {
+    let len = deserialize_usize(state, 8)?;
+    let mut result = Vec::with_capacity(len);
+    let window_size = deserialize_byte(state)?;
+    let lookahead_size = deserialize_byte(state)?;
+    for i in 0..len {
+        let entry = deserialize_lz77_entry(state, window_size, lookahead_size)
+            .map_err(truncated(i, len))?;
+        result.push(entry);
+    }
+    return Ok(result);
+}
+
+/// Deserializes a single `LZ77entry` value from the input stream.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+/// - `window_size` - The width of the offset, in bytes.
+/// - `lookahead_size` - The width of the length, in bytes.
+///
+/// ## Returns
+/// - `Result<LZ77entry<T>, Box<dyn error::Error>>` - The deserialized entry or an error.
+fn deserialize_lz77_entry<R: Read, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+    window_size: u8,
+    lookahead_size: u8,
+) -> Result<LZ77entry<T>, Box<dyn error::Error>> {
+    let offset = deserialize_usize(state, window_size)?;
+    let length = deserialize_usize(state, lookahead_size)?;
+    let mut buffer = [0; N];
+    state.read_exact(&mut buffer)?;
+    let value = T::from_le_bytes(&buffer);
+    Ok(LZ77entry::from((offset, length, value)))
+}
+
+/// Deserializes a vector of `LZ77entry` values written by
+/// `serialize_lz77_packed`.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+///
+/// ## Returns
+/// - `Result<Vec<LZ77entry<T>>, Box<dyn error::Error>>` - The deserialized vector of `LZ77entry` values or an error.
+pub fn deserialize_lz77_packed<R: Read, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+) -> Result<Vec<LZ77entry<T>>, Box<dyn error::Error>> {
+    let len = deserialize_usize(state, 8)?;
+    let window_size = deserialize_byte(state)?;
+    let mut result = Vec::with_capacity(len);
+    while result.len() < len {
+        let entries_read = result.len();
+        deserialize_lz77_sequence(state, window_size, &mut result)
+            .map_err(truncated(entries_read, len))?;
+    }
+    if result.len() > len {
+        return Err(format!(
+            "Stream declared {} entries, but contains {}",
+            len,
+            result.len()
+        )
+        .into());
+    }
+    Ok(result)
+}
+
+/// Deserializes a single sequence of a packed LZ77 stream, a match followed
+/// by literals, appending its entries to the result.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+/// - `window_size` - The width of the offset, in bytes.
+/// - `result` - The entries deserialized so far.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn error::Error>>` - Indicates success or failure of the operation.
+fn deserialize_lz77_sequence<R: Read, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+    window_size: u8,
+    result: &mut Vec<LZ77entry<T>>,
+) -> Result<(), Box<dyn error::Error>> {
+    let token = deserialize_byte(state)?;
+    let length = deserialize_nibble(token & 0xF, state)?;
+    let offset = if length > 0 {
+        deserialize_usize(state, window_size)?
+    } else {
+        0
+    };
+    let literals = deserialize_nibble(token >> 4, state)?;
+    if literals == 0 {
+        return Err("Sequence without a literal".into());
+    }
+    for i in 0..literals {
+        let mut buffer = [0; N];
+        state.read_exact(&mut buffer)?;
+        let value = T::from_le_bytes(&buffer);
+        // only the first literal belongs to the match
+        result.push(if i == 0 {
+            LZ77entry::from((offset, length, value))
+        } else {
+            LZ77entry::from((0, 0, value))
+        });
+    }
+    Ok(())
+}
+
+/// Deserializes a value stored in a token nibble, followed by extension bytes
+/// if the nibble is full.
+///
+/// ## Arguments
+/// - `nibble` - The nibble of the token.
+/// - `state` - The input stream to read the extension bytes from.
+///
+/// ## Returns
+/// - `Result<usize, Box<dyn error::Error>>` - The deserialized value or an error.
+fn deserialize_nibble<R: Read>(nibble: u8, state: &mut R) -> Result<usize, Box<dyn error::Error>> {
+    let mut value = nibble as usize;
+    if value == NIBBLE_MAX {
+        loop {
+            let byte = deserialize_byte(state)?;
+            value += byte as usize;
+            if byte < u8::MAX {
+                break;
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// Deserializes a vector of `LZ78entry` values from the input stream.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+/// - `dictionary_size` - The size of the dictionary, used to validate the indices.
+///
+/// ## Returns
+/// - `Result<Vec<LZ78entry<T>>, Box<dyn error::Error>>` - The deserialized vector of `LZ78entry` values or an error.
+pub fn deserialize_lz78<R: Read, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+    dictionary_size: usize,
+) -> Result<Vec<LZ78entry<T>>, Box<dyn error::Error>> {
+    let len = deserialize_usize(state, 8)?;
+    let mut result = Vec::with_capacity(len);
+    let dict_width = deserialize_byte(state)?;
+    for i in 0..len {
+        let index = deserialize_usize(state, dict_width).map_err(truncated(i, len))?;
+        let index = if index == 0 { None } else { Some(index - 1) };
+        let mut buffer = [0; N];
+        state
+            .read_exact(&mut buffer)
+            .map_err(|err| truncated(i, len)(err.into()))?;
+        let value = T::from_le_bytes(&buffer);
+        result.push(LZ78entry::try_from(BoundedLZ78tuple::new(
+            (index, value),
+            dictionary_size,
+        ))?);
+    }
+    return Ok(result);
+}
+
+/// LZ78 entries, along with the lengths of the phrases they decode to.
+pub type LZ78withLengths<T> = (Vec<LZ78entry<T>>, Vec<usize>);
+
+/// Deserializes a vector of `LZ78entry` values along with their phrase lengths.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+/// - `dictionary_size` - The size of the dictionary, used to validate the indices.
+///
+/// ## Returns
+/// - `Result<LZ78withLengths<T>, Box<dyn error::Error>>` - The deserialized entries and their phrase lengths or an error.
+pub fn deserialize_lz78_with_lengths<R: Read, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+    dictionary_size: usize,
+) -> Result<LZ78withLengths<T>, Box<dyn error::Error>> {
+    let entries = deserialize_lz78(state, dictionary_size)?;
+    let byte_len = deserialize_usize(state, 8)?;
+    let mut bytes = vec![0; byte_len];
+    state.read_exact(&mut bytes)?;
+    let mut bits = BitSlice::from_slice(&bytes);
+    let mut lengths = Vec::with_capacity(entries.len());
+    for _ in 0..entries.len() {
+        let length: u64 = gamma_decode(&mut bits)?;
+        lengths.push(length as usize);
+    }
+    Ok((entries, lengths))
+}
+
+/// Deserializes a vector of `LZ78entry` values from gamma coded bits.
+///
+/// ## Arguments
+/// - `state` - The input bit stream to read the serialized data from.
+/// - `dictionary_size` - The size of the dictionary, used to validate the indices.
+///
+/// ## Returns
+/// - `Result<Vec<LZ78entry<T>>, Box<dyn error::Error>>` - The deserialized vector of `LZ78entry` values or an error.
+pub fn deserialize_lz78_gamma<R: BitRead, const N: usize, T: FromBytes<Bytes = [u8; N]>>(
+    state: &mut R,
+    dictionary_size: usize,
+) -> Result<Vec<LZ78entry<T>>, Box<dyn error::Error>> {
+    let len: u64 = gamma_decode(state)?;
+    let mut result = Vec::new();
+    for _ in 1..len {
+        let index: u64 = gamma_decode(state)?;
+        let index = if index == 1 {
+            None
+        } else {
+            Some(index as usize - 2)
+        };
+        let mut buffer = [0; N];
+        state.read_bits_exact(BitSlice::from_slice_mut(&mut buffer))?;
+        let value = T::from_le_bytes(&buffer);
+        result.push(LZ78entry::try_from(BoundedLZ78tuple::new(
+            (index, value),
+            dictionary_size,
+        ))?);
+    }
+    Ok(result)
+}
+
+/// Deserializes a vector of `usize` values from the input stream.
+///
+/// ## Arguments
+/// - `state` - The input stream to read the serialized data from.
+///
+/// ## Returns
+/// - `Result<Vec<usize>, Box<dyn error::Error>>` - The deserialized vector of `usize` values or an error.
+pub fn deserialize_lzw<R: Read>(state: &mut R) -> Result<Vec<usize>, Box<dyn error::Error>> {
+    let len = deserialize_usize(state, 8)?;
+    let mut result = Vec::with_capacity(len);
+    let width = deserialize_byte(state)?;
+    for i in 0..len {
+        let value = deserialize_usize(state, width).map_err(truncated(i, len))?;
+        result.push(value);
+    }
+    return Ok(result);
+}
+
+/// A block of Burrows-Wheeler Transform output: the transformed data, the
+/// index of the original data and, if they were stored, the symbol counts.
+pub type BwtBlock = (Vec<u8>, usize, Option<Vec<(u8, usize)>>);
+
+/// Deserializes a block of Burrows-Wheeler Transform output written by
+/// `serialize_bwt_block`.
+///
+/// ## Arguments
+/// - `state` - The input bit stream to read the serialized data from.
+///
+/// ## Returns
+/// - `Result<BwtBlock, Box<dyn error::Error>>` - The deserialized block or an error.
+pub fn deserialize_bwt_block<R: BitRead>(state: &mut R) -> Result<BwtBlock, Box<dyn error::Error>> {
+    let index: u64 = gamma_decode(state)?;
+    let flag: u64 = gamma_decode(state)?;
+    let (len, counts) = match flag {
+        1 => {
+            let len: u64 = gamma_decode(state)?;
+            (len as usize - 1, None)
+        }
+        2 => {
+            let distinct: u64 = gamma_decode(state)?;
+            let mut counts = Vec::new();
+            for _ in 1..distinct {
+                let mut byte = [0u8];
+                state.read_bits_exact(BitSlice::from_slice_mut(&mut byte))?;
+                let count: u64 = gamma_decode(state)?;
+                counts.push((byte[0], count as usize));
+            }
+            let len = counts
+                .iter()
+                .try_fold(0usize, |len, (_, count)| len.checked_add(*count))
+                .ok_or("The counts overflow the block length")?;
+            (len, Some(counts))
+        }
+        _ => return Err(format!("Invalid counts flag: {}", flag).into()),
+    };
+    let mut block = vec![0; len];
+    state.read_bits_exact(BitSlice::from_slice_mut(&mut block))?;
+    let index = index as usize - 1;
+    if index >= len.max(1) {
+        return Err(format!("Index {} out of bounds for a block of {}", index, len).into());
+    }
+    // the decoder trusts the counts to be those of the block
+    if let Some(counts) = &counts
+        && *counts != column_counts(&block)
+    {
+        return Err("The counts don't match the block".into());
+    }
+    Ok((block, index, counts))
+}
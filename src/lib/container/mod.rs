@@ -0,0 +1,862 @@
+use bits_io::{bit_types::BitVec, prelude::BitSlice};
+
+use crate::{
+    analysis::entropy,
+    lz::{
+        DEFAULT_DICTIONARY_SIZE, DEFAULT_LOOKAHEAD, DEFAULT_WINDOW_SIZE, lz77::*, lz78::*, lzw::*,
+    },
+    pipeline::stack_encode_stages,
+    transform::{bwt::*, mtf::*},
+};
+
+use std::{
+    borrow::Cow,
+    error,
+    io::{Cursor, Read, Seek, Write},
+};
+
+use clap::Subcommand;
+
+/// Module providing the serialization of the encoders' output, optimized for output size.
+pub mod serializer;
+use serializer::{Lz77Writer, serialize_lz78, serialize_lzw};
+
+/// Module providing the deserialization of the data written by the [serializer].
+pub mod deserializer;
+use deserializer::{deserialize_lz77, deserialize_lz78, deserialize_lzw};
+
+/// Written at the start of every compressed file, so that it can be told apart
+/// from other data before the algorithm is even looked at.
+const MAGIC: &[u8; 4] = b"GZC1";
+const HEADER_SIZE: usize = 3;
+const LZ77_HEADER: &[u8; HEADER_SIZE] = b"l77";
+const LZ78_HEADER: &[u8; HEADER_SIZE] = b"l78";
+const LZW_HEADER: &[u8; HEADER_SIZE] = b"lzw";
+const STACK_HEADER: &[u8; HEADER_SIZE] = b"stk";
+const STORE_HEADER: &[u8; HEADER_SIZE] = b"sto";
+/// The version of the serialization format, written right after the header.
+/// Must be bumped whenever the layout of the compressed data changes.
+const FORMAT_VERSION: u8 = 3;
+
+// Tags of the parameters, stored in the parameter block after the version
+const WINDOW_SIZE_TAG: u8 = 1;
+const LOOKAHEAD_BUFFER_SIZE_TAG: u8 = 2;
+const LOOKAHEAD_MAX_TAG: u8 = 3;
+const DICTIONARY_SIZE_TAG: u8 = 4;
+const MIN_GAIN_TAG: u8 = 5;
+
+const LZW_DICIONARY: &[u8; 256] = &{
+    let mut array = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        array[i] = i as u8;
+        i += 1;
+    }
+    array
+};
+
+/// The compression algorithms of the container format, with their parameters.
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Algorithm {
+    /// LZ77 compression algorithm
+    LZ77 {
+        /// The maximum offset to search for matches
+        #[arg(short, long, default_value_t = DEFAULT_WINDOW_SIZE)]
+        window_size: usize,
+        /// The maximum length of matches
+        #[arg(short, long, default_value_t = DEFAULT_LOOKAHEAD)]
+        lookahead_buffer_size: usize,
+    },
+    /// LZ78 compression algorithm
+    LZ78 {
+        /// The maximum offset to search for matches
+        #[arg(short, long, default_value_t = DEFAULT_LOOKAHEAD)]
+        lookahead_max: usize,
+        /// The size of the dictionary
+        #[arg(short, long, default_value_t = DEFAULT_DICTIONARY_SIZE)]
+        dictionary_size: usize,
+    },
+    /// LZW compression algorithm
+    LZW {
+        /// The maximum offset to search for matches
+        #[arg(short, long, default_value_t = DEFAULT_LOOKAHEAD)]
+        lookahead_max: usize,
+    },
+    /// LZW compression algorithm with move-to-front and Burrows-Wheeler transform
+    STACK {
+        /// The maximum offset to search for matches
+        #[arg(short, long, default_value_t = DEFAULT_LOOKAHEAD)]
+        lookahead_max: usize,
+        /// The minimum reduction of the estimated size by the transforms, in
+        /// percent, below which plain LZW is used instead
+        #[arg(short, long, default_value = "0")]
+        min_gain: usize,
+    },
+    /// No compression, the data is stored as is
+    STORE,
+}
+
+/// Writes the header identifying the algorithm, the format version and the
+/// parameter block.
+///
+/// ## Format
+/// - The four [MAGIC] bytes.
+/// - Three bytes identifying the algorithm.
+/// - A byte with the format version.
+/// - A byte with the number of parameters, followed by the parameters, each
+///   consisting of a tag byte, a byte with the length of the value, and the
+///   value in little endian.
+///
+/// ## Arguments
+/// - `algorithm` - The compression algorithm, with its parameters.
+/// - `file` - The output stream to write the header to.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn error::Error>>` - Indicates success or failure of the operation.
+fn write_header<W: Write>(
+    algorithm: &Algorithm,
+    file: &mut W,
+) -> Result<(), Box<dyn error::Error>> {
+    let (header, parameters) = match *algorithm {
+        Algorithm::LZ77 {
+            window_size,
+            lookahead_buffer_size,
+        } => (
+            LZ77_HEADER,
+            vec![
+                (WINDOW_SIZE_TAG, window_size),
+                (LOOKAHEAD_BUFFER_SIZE_TAG, lookahead_buffer_size),
+            ],
+        ),
+        Algorithm::LZ78 {
+            lookahead_max,
+            dictionary_size,
+        } => (
+            LZ78_HEADER,
+            vec![
+                (LOOKAHEAD_MAX_TAG, lookahead_max),
+                (DICTIONARY_SIZE_TAG, dictionary_size),
+            ],
+        ),
+        Algorithm::LZW { lookahead_max } => (LZW_HEADER, vec![(LOOKAHEAD_MAX_TAG, lookahead_max)]),
+        Algorithm::STACK {
+            lookahead_max,
+            min_gain,
+        } => (
+            STACK_HEADER,
+            vec![(LOOKAHEAD_MAX_TAG, lookahead_max), (MIN_GAIN_TAG, min_gain)],
+        ),
+        Algorithm::STORE => (STORE_HEADER, vec![]),
+    };
+    file.write_all(MAGIC)?;
+    file.write_all(header)?;
+    file.write_all(&[FORMAT_VERSION, parameters.len() as u8])?;
+    for (tag, value) in parameters {
+        let bytes = value.to_le_bytes();
+        // trailing zero bytes are implied
+        let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        file.write_all(&[tag, len as u8])?;
+        file.write_all(&bytes[..len])?;
+    }
+    Ok(())
+}
+
+/// Reads the header written by [compress].
+///
+/// ## Arguments
+/// - `file` - The input stream to read the header from.
+///
+/// ## Returns
+/// - `Result<Algorithm, Box<dyn error::Error>>` - The algorithm the data was
+///   compressed with, along with its parameters, or an error.
+pub fn read_header<R: Read>(file: &mut R) -> Result<Algorithm, Box<dyn error::Error>> {
+    let mut magic = [0; MAGIC.len()];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(format!(
+            "Not a compressed file: expected it to start with {:?}, found {:?}",
+            MAGIC, magic
+        )
+        .into());
+    }
+    let mut header = [0; HEADER_SIZE];
+    file.read_exact(&mut header)?;
+    let mut version = [0; 1];
+    file.read_exact(&mut version)?;
+    if version[0] != FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported format version {}, only version {} is supported",
+            version[0], FORMAT_VERSION
+        )
+        .into());
+    }
+    let mut count = [0; 1];
+    file.read_exact(&mut count)?;
+    let mut parameters = Vec::with_capacity(count[0] as usize);
+    for _ in 0..count[0] {
+        let mut tag_len = [0; 2];
+        file.read_exact(&mut tag_len)?;
+        let [tag, len] = tag_len;
+        if len as usize > size_of::<usize>() {
+            return Err(format!("Parameter {} is too long: {} bytes", tag, len).into());
+        }
+        let mut bytes = [0; size_of::<usize>()];
+        file.read_exact(&mut bytes[..len as usize])?;
+        parameters.push((tag, usize::from_le_bytes(bytes)));
+    }
+    let parameter = |tag: u8| {
+        parameters
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| format!("Missing parameter {}", tag))
+    };
+    Ok(match &header {
+        LZ77_HEADER => Algorithm::LZ77 {
+            window_size: parameter(WINDOW_SIZE_TAG)?,
+            lookahead_buffer_size: parameter(LOOKAHEAD_BUFFER_SIZE_TAG)?,
+        },
+        LZ78_HEADER => Algorithm::LZ78 {
+            lookahead_max: parameter(LOOKAHEAD_MAX_TAG)?,
+            dictionary_size: parameter(DICTIONARY_SIZE_TAG)?,
+        },
+        LZW_HEADER => Algorithm::LZW {
+            lookahead_max: parameter(LOOKAHEAD_MAX_TAG)?,
+        },
+        STACK_HEADER => Algorithm::STACK {
+            lookahead_max: parameter(LOOKAHEAD_MAX_TAG)?,
+            // only affects compression, so it may be missing
+            min_gain: parameter(MIN_GAIN_TAG).unwrap_or(0),
+        },
+        STORE_HEADER => Algorithm::STORE,
+        header => return Err(format!("Unknown compression algorithm: {:?}", header).into()),
+    })
+}
+
+/// Compresses the input using the given algorithm, writing the header and the
+/// serialized data to the output.
+///
+/// ## Arguments
+/// - `algorithm` - The compression algorithm to use, with its parameters.
+/// - `input_data` - The data to be compressed.
+/// - `file` - The output stream to write the compressed data to.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn error::Error>>` - Indicates success or failure of the operation.
+pub fn compress<W: Write + Seek>(
+    algorithm: &Algorithm,
+    input_data: &[u8],
+    file: &mut W,
+) -> Result<(), Box<dyn error::Error>> {
+    let stages = match *algorithm {
+        Algorithm::STACK {
+            lookahead_max,
+            min_gain,
+        } => {
+            let stages = stack_encode_stages(input_data, lookahead_max);
+            if !transforms_gain(input_data, &stages.2, min_gain) {
+                // plain LZW does at least as well, and its header tells decompress so
+                return compress(&Algorithm::LZW { lookahead_max }, input_data, file);
+            }
+            Some(stages)
+        }
+        _ => None,
+    };
+    write_header(algorithm, file)?;
+    match *algorithm {
+        Algorithm::LZ77 {
+            window_size,
+            lookahead_buffer_size,
+        } => {
+            let mut writer = Lz77Writer::new(file, window_size, lookahead_buffer_size)?;
+            writer.write_all(input_data)?;
+            writer.finish()?;
+            Ok(())
+        }
+        Algorithm::LZ78 {
+            lookahead_max,
+            dictionary_size,
+        } => serialize_lz78(
+            lz78_encode(input_data, lookahead_max, dictionary_size),
+            dictionary_size,
+            file,
+        ),
+        Algorithm::LZW { lookahead_max } => {
+            serialize_lzw(lzw_encode(input_data, LZW_DICIONARY, lookahead_max), file)
+        }
+        Algorithm::STACK { .. } => {
+            let (_, index, _, lzw) = stages.expect("The stages are computed above");
+            file.write_all(&index.to_le_bytes())?;
+            serialize_lzw(lzw, file)
+        }
+        Algorithm::STORE => Ok(file.write_all(input_data)?),
+    }
+}
+
+/// Checks whether the BWT and MTF transforms of the STACK algorithm reduce
+/// the estimated size of the data enough to be worth it. The size is
+/// estimated from the entropy of the bytes, which the transforms don't
+/// change the number of.
+///
+/// ## Arguments
+/// - `input_data` - The data to be compressed.
+/// - `mtf` - The data after both transforms.
+/// - `min_gain` - The minimum reduction of the estimated size, in percent.
+///
+/// ## Returns
+/// - `bool` - Whether the transforms should be kept.
+fn transforms_gain(input_data: &[u8], mtf: &[usize], min_gain: usize) -> bool {
+    let before = entropy(input_data);
+    let after = entropy(&mtf.iter().map(|&i| i as u8).collect::<Vec<_>>());
+    let gain = if before > 0.0 {
+        (1.0 - after / before) * 100.0
+    } else {
+        0.0
+    };
+    // without a required gain, the transforms only have to do no harm
+    after <= before && gain >= min_gain as f64
+}
+
+/// Compresses data into a caller provided buffer, in the same format as
+/// [compress]. The buffer is cleared first, and its capacity is reused.
+///
+/// ## Arguments
+/// - `algorithm` - The compression algorithm to use, with its parameters.
+/// - `input_data` - The data to be compressed.
+/// - `out` - The buffer to write the compressed data into.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn error::Error>>` - Indicates success or failure of the operation.
+pub fn compress_into(
+    algorithm: &Algorithm,
+    input_data: &[u8],
+    out: &mut Vec<u8>,
+) -> Result<(), Box<dyn error::Error>> {
+    out.clear();
+    compress(algorithm, input_data, &mut Cursor::new(out))
+}
+
+/// Decompresses data written by [compress], reading the header to find out
+/// which algorithm was used.
+///
+/// ## Arguments
+/// - `file` - The input stream to read the compressed data from.
+///
+/// ## Returns
+/// - `Result<Vec<u8>, Box<dyn error::Error>>` - The decompressed data or an error.
+pub fn decompress<R: Read>(file: &mut R) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    decompress_prefix(file, usize::MAX)
+}
+
+/// Decompresses data written by [compress], borrowing from the compressed
+/// data where possible. Data written by the STORE algorithm is a plain copy
+/// of the input, so it's returned without copying it again.
+///
+/// ## Arguments
+/// - `bytes` - The compressed data.
+///
+/// ## Returns
+/// - `Result<Cow<[u8]>, Box<dyn error::Error>>` - The decompressed data,
+///   borrowed if it was stored, or an error.
+pub fn decompress_cow(bytes: &[u8]) -> Result<Cow<'_, [u8]>, Box<dyn error::Error>> {
+    let mut data = bytes;
+    if read_header(&mut data)? == Algorithm::STORE {
+        Ok(Cow::Borrowed(data))
+    } else {
+        Ok(Cow::Owned(decompress(&mut &bytes[..])?))
+    }
+}
+
+/// Decompresses only the first `n` bytes of data written by [compress]. The
+/// LZ algorithms stop decoding as soon as enough bytes are produced, the
+/// STACK algorithm has to be decoded in full, as the transform needs all of
+/// the data.
+///
+/// ## Arguments
+/// - `file` - The input stream to read the compressed data from.
+/// - `n` - The number of bytes to decompress.
+///
+/// ## Returns
+/// - `Result<Vec<u8>, Box<dyn error::Error>>` - The first `n` decompressed
+///   bytes, or all of them if there are fewer, or an error.
+pub fn decompress_prefix<R: Read>(
+    file: &mut R,
+    n: usize,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    Ok(match read_header(file)? {
+        Algorithm::LZ77 { .. } => {
+            let data: Vec<LZ77entry<u8>> = deserialize_lz77(file)?;
+            lz77_decode_prefix(&data, n)?
+        }
+        Algorithm::LZ78 {
+            dictionary_size, ..
+        } => {
+            let data: Vec<LZ78entry<u8>> = deserialize_lz78(file, dictionary_size)?;
+            // every entry decodes to at least one byte
+            let mut data = lz78_decode_checked(&data[..n.min(data.len())], dictionary_size)?;
+            data.truncate(n);
+            data
+        }
+        Algorithm::LZW { .. } => {
+            let data: Vec<usize> = deserialize_lzw(file)?;
+            lzw_decode_prefix(&data, LZW_DICIONARY, n)?
+        }
+        Algorithm::STACK { .. } => {
+            let mut index_buf = [0; 8];
+            file.read_exact(&mut index_buf)?;
+            let index = usize::from_le_bytes(index_buf);
+            let data: Vec<usize> = deserialize_lzw(file)?;
+            let mtf = lzw_decode_checked(&data, LZW_DICIONARY)?;
+            let bwt = decode_move_to_front_bytes(&mtf, &mut LZW_DICIONARY.clone());
+            let mut data = decode_bwt_bytes_checked(bwt.as_slice(), index)?;
+            data.truncate(n);
+            data
+        }
+        Algorithm::STORE => {
+            let mut data = Vec::new();
+            file.take(n as u64).read_to_end(&mut data)?;
+            data
+        }
+    })
+}
+
+/// Compresses a string using the given algorithm.
+///
+/// ## Arguments
+/// - `algorithm` - The compression algorithm to use, with its parameters.
+/// - `s` - The string to be compressed.
+///
+/// ## Returns
+/// - `Result<Vec<u8>, Box<dyn error::Error>>` - The compressed data or an error.
+///
+/// ## Example
+/// ```
+/// use generic_compression::container::{Algorithm, compress_str, decompress_str};
+///
+/// let algorithm = Algorithm::LZW { lookahead_max: 255 };
+/// let compressed = compress_str(&algorithm, "TAMTARAMTAMTAMRAMTAT").unwrap();
+/// assert_eq!(decompress_str(&compressed).unwrap(), "TAMTARAMTAMTAMRAMTAT");
+/// ```
+pub fn compress_str(algorithm: &Algorithm, s: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut file = Cursor::new(Vec::new());
+    compress(algorithm, s.as_bytes(), &mut file)?;
+    Ok(file.into_inner())
+}
+
+/// Decompresses a string compressed with [compress_str].
+///
+/// ## Arguments
+/// - `bytes` - The compressed data.
+///
+/// ## Returns
+/// - `Result<String, Box<dyn error::Error>>` - The decompressed string, or an
+///   error if decompression failed or the data isn't valid UTF-8.
+pub fn decompress_str(mut bytes: &[u8]) -> Result<String, Box<dyn error::Error>> {
+    Ok(String::from_utf8(decompress(&mut bytes)?)?)
+}
+
+/// Compresses a sequence of bits using the given algorithm. The bits are
+/// packed eight to a byte before compression.
+///
+/// ## Arguments
+/// - `algorithm` - The compression algorithm to use, with its parameters.
+/// - `bits` - The bits to be compressed.
+///
+/// ## Returns
+/// - `Result<Vec<u8>, Box<dyn error::Error>>` - The compressed data or an error.
+pub fn compress_bits(
+    algorithm: &Algorithm,
+    bits: &[bool],
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let packed: BitVec = bits.iter().copied().collect();
+    let mut data = bits.len().to_le_bytes().to_vec();
+    data.extend_from_slice(packed.as_raw_slice());
+    let mut file = Cursor::new(Vec::new());
+    compress(algorithm, &data, &mut file)?;
+    Ok(file.into_inner())
+}
+
+/// Decompresses a sequence of bits compressed with [compress_bits].
+///
+/// ## Arguments
+/// - `bytes` - The compressed data.
+///
+/// ## Returns
+/// - `Result<Vec<bool>, Box<dyn error::Error>>` - The decompressed bits or an error.
+pub fn decompress_bits(mut bytes: &[u8]) -> Result<Vec<bool>, Box<dyn error::Error>> {
+    let data = decompress(&mut bytes)?;
+    if data.len() < 8 {
+        return Err("Missing the bit count".into());
+    }
+    let (len, packed) = data.split_at(8);
+    let len = usize::from_le_bytes(len.try_into()?);
+    let packed = BitSlice::from_slice(packed);
+    if len > packed.len() {
+        return Err(format!("Expected {} bits, found {}", len, packed.len()).into());
+    }
+    Ok(packed[..len].iter().map(|bit| *bit).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_data;
+
+    fn round_trip(algorithm: &Algorithm, input: &[u8]) -> Vec<u8> {
+        let mut file = Cursor::new(Vec::new());
+        compress(algorithm, input, &mut file).unwrap();
+        decompress(&mut file.into_inner().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = b"RATABARBARATABARBARAT, TAMTARAMTAMTAMRAMTAT";
+        let algorithms = [
+            Algorithm::LZ77 {
+                window_size: 255,
+                lookahead_buffer_size: 255,
+            },
+            Algorithm::LZ78 {
+                lookahead_max: 255,
+                dictionary_size: 255,
+            },
+            Algorithm::LZW { lookahead_max: 255 },
+            Algorithm::STACK {
+                lookahead_max: 255,
+                min_gain: 0,
+            },
+            Algorithm::STORE,
+        ];
+        for algorithm in &algorithms {
+            assert_eq!(round_trip(algorithm, input), input);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_minimal() {
+        let algorithms = [
+            Algorithm::LZ77 {
+                window_size: DEFAULT_WINDOW_SIZE,
+                lookahead_buffer_size: DEFAULT_LOOKAHEAD,
+            },
+            Algorithm::LZ78 {
+                lookahead_max: DEFAULT_LOOKAHEAD,
+                dictionary_size: DEFAULT_DICTIONARY_SIZE,
+            },
+            Algorithm::LZW {
+                lookahead_max: DEFAULT_LOOKAHEAD,
+            },
+            Algorithm::STACK {
+                lookahead_max: DEFAULT_LOOKAHEAD,
+                min_gain: 0,
+            },
+            Algorithm::STACK {
+                lookahead_max: DEFAULT_LOOKAHEAD,
+                min_gain: 99,
+            },
+            Algorithm::STORE,
+        ];
+        for algorithm in &algorithms {
+            for input in [&b""[..], &[0x42], &[0x42, 0x42]] {
+                let mut file = Cursor::new(Vec::new());
+                compress(algorithm, input, &mut file).unwrap();
+                let data = file.into_inner();
+                let decoded = decompress(&mut data.as_slice()).unwrap();
+                assert_eq!(decoded, input, "{:?}", algorithm);
+                let prefix = decompress_prefix(&mut data.as_slice(), 1).unwrap();
+                assert_eq!(prefix, &input[..input.len().min(1)], "{:?}", algorithm);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_into_reuse() {
+        let algorithm = Algorithm::LZ77 {
+            window_size: 255,
+            lookahead_buffer_size: 255,
+        };
+        let mut buffer = Vec::new();
+        for input in [
+            &b"RATABARBARATABARBARAT".repeat(8)[..],
+            b"TAMTARAMTAMTAMRAMTAT",
+            b"",
+        ] {
+            compress_into(&algorithm, input, &mut buffer).unwrap();
+            assert_eq!(decompress(&mut buffer.as_slice()).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut file = Cursor::new(Vec::new());
+        compress(&Algorithm::LZW { lookahead_max: 255 }, b"abc", &mut file).unwrap();
+        let mut data = file.into_inner();
+        data[MAGIC.len() + HEADER_SIZE] = FORMAT_VERSION + 1;
+        let err = decompress(&mut data.as_slice()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Unsupported format version {}, only version {} is supported",
+                FORMAT_VERSION + 1,
+                FORMAT_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_missing_magic() {
+        let mut file = Cursor::new(Vec::new());
+        compress(&Algorithm::LZW { lookahead_max: 255 }, b"abc", &mut file).unwrap();
+        let data = file.into_inner();
+        // a file from before the magic was introduced starts with the algorithm
+        let err = decompress(&mut &data[MAGIC.len()..]).unwrap_err();
+        assert!(err.to_string().starts_with("Not a compressed file"));
+        let err = decompress(&mut b"PK\x03\x04 not ours".as_slice()).unwrap_err();
+        assert!(err.to_string().starts_with("Not a compressed file"));
+    }
+
+    #[test]
+    fn test_str_round_trip() {
+        let input = "zażółć gęślą jaźń, zażółć gęślą jaźń 🦀🦀🦀".to_string();
+        let algorithm = Algorithm::LZ78 {
+            lookahead_max: 255,
+            dictionary_size: 255,
+        };
+        let compressed = compress_str(&algorithm, &input).unwrap();
+        assert_eq!(decompress_str(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_str_invalid_utf8() {
+        let mut file = Cursor::new(Vec::new());
+        compress(
+            &Algorithm::LZW { lookahead_max: 255 },
+            &[b'a', 0xff, 0xfe],
+            &mut file,
+        )
+        .unwrap();
+        let err = decompress_str(&file.into_inner()).unwrap_err();
+        assert!(err.is::<std::string::FromUtf8Error>());
+    }
+
+    #[test]
+    fn test_stack_round_trip() {
+        let alphabet: Vec<u8> = (0..=255).collect();
+        let mut shuffled = alphabet.repeat(3);
+        shuffled.reverse();
+        let inputs: [&[u8]; 6] = [
+            b"mississippi mississippi",
+            b"abababababababab",
+            b"aaaaaaaa",
+            b"",
+            &alphabet,
+            &shuffled,
+        ];
+        for input in inputs {
+            assert_eq!(
+                round_trip(
+                    &Algorithm::STACK {
+                        lookahead_max: 255,
+                        min_gain: 0,
+                    },
+                    input
+                ),
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_stack_fallback() {
+        // bytes without any structure, which the transforms only spread out. The
+        // entropy barely changes on noise, so the seed is one where it grows
+        let noise = gen_data(3, 4096, 0.0);
+        let repetitive = b"mississippi ".repeat(64);
+        for (input, min_gain, falls_back) in [
+            (&noise, 0, true),
+            (&repetitive, 0, false),
+            (&repetitive, 99, true),
+        ] {
+            let algorithm = Algorithm::STACK {
+                lookahead_max: 255,
+                min_gain,
+            };
+            let mut file = Cursor::new(Vec::new());
+            compress(&algorithm, input, &mut file).unwrap();
+            let data = file.into_inner();
+            let header = read_header(&mut data.as_slice()).unwrap();
+            assert_eq!(header == Algorithm::LZW { lookahead_max: 255 }, falls_back);
+            assert_eq!(&decompress(&mut data.as_slice()).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_stack_invalid_index() {
+        let input = &b"mississippi ".repeat(16);
+        let mut file = Cursor::new(Vec::new());
+        compress(
+            &Algorithm::STACK {
+                lookahead_max: 255,
+                min_gain: 0,
+            },
+            input,
+            &mut file,
+        )
+        .unwrap();
+        let mut data = file.into_inner();
+        // the primary index follows the header
+        let mut rest = data.as_slice();
+        assert!(matches!(
+            read_header(&mut rest).unwrap(),
+            Algorithm::STACK { .. }
+        ));
+        let offset = data.len() - rest.len();
+        data[offset..offset + 8].copy_from_slice(&(input.len() as u64).to_le_bytes());
+        let err = decompress(&mut data.as_slice()).unwrap_err();
+        assert!(err.is::<InvalidPrimaryIndexError>());
+    }
+
+    #[test]
+    fn test_lz78_invalid_index() {
+        let mut file = Cursor::new(Vec::new());
+        let algorithm = Algorithm::LZ78 {
+            lookahead_max: 255,
+            dictionary_size: 4,
+        };
+        compress(&algorithm, b"aaaaaaaaaaaa", &mut file).unwrap();
+        let mut data = file.into_inner();
+        // point the last entry past the end of the dictionary
+        let last_index = data.len() - 2;
+        data[last_index] = 200;
+        assert!(decompress(&mut data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_lz77_invalid_offset() {
+        let mut file = Cursor::new(Vec::new());
+        let algorithm = Algorithm::LZ77 {
+            window_size: 255,
+            lookahead_buffer_size: 255,
+        };
+        compress(&algorithm, b"aaaaaaaaaaaa", &mut file).unwrap();
+        let mut data = file.into_inner();
+        // point the last match before the start of the data, each entry
+        // being a byte of offset, a byte of length and the value
+        let last_offset = data.len() - 3;
+        data[last_offset] = 200;
+        let err = decompress(&mut data.as_slice()).unwrap_err();
+        assert!(err.is::<DecodeError>());
+        assert!(decompress_prefix(&mut data.as_slice(), 1).is_ok());
+    }
+
+    #[test]
+    fn test_lzw_invalid_code() {
+        let input = b"TAMTARAMTAMTAMRAMTAT";
+        for algorithm in [
+            Algorithm::LZW { lookahead_max: 255 },
+            Algorithm::STACK {
+                lookahead_max: 255,
+                min_gain: 0,
+            },
+        ] {
+            let mut file = Cursor::new(Vec::new());
+            compress(&algorithm, input, &mut file).unwrap();
+            let mut data = file.into_inner();
+            // point the last code far past the end of the dictionary
+            let last_code = data.len() - 1;
+            data[last_code] = 250;
+            let err = decompress(&mut data.as_slice()).unwrap_err();
+            assert!(err.is::<InvalidCodeError>(), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn test_decompress_prefix() {
+        let input = b"RATABARBARATABARBARAT, TAMTARAMTAMTAMRAMTAT";
+        let algorithms = [
+            Algorithm::LZ77 {
+                window_size: 255,
+                lookahead_buffer_size: 255,
+            },
+            Algorithm::LZ78 {
+                lookahead_max: 255,
+                dictionary_size: 255,
+            },
+            Algorithm::LZW { lookahead_max: 255 },
+            Algorithm::STACK {
+                lookahead_max: 255,
+                min_gain: 0,
+            },
+            Algorithm::STORE,
+        ];
+        for algorithm in &algorithms {
+            let mut file = Cursor::new(Vec::new());
+            compress(algorithm, input, &mut file).unwrap();
+            let data = file.into_inner();
+            for n in [0, 5, 20, input.len(), input.len() + 1] {
+                let prefix = decompress_prefix(&mut data.as_slice(), n).unwrap();
+                assert_eq!(prefix, &input[..n.min(input.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parameters_in_header() {
+        let algorithms = [
+            Algorithm::LZ77 {
+                window_size: 1000,
+                lookahead_buffer_size: 7,
+            },
+            Algorithm::LZ78 {
+                lookahead_max: 0,
+                dictionary_size: 70000,
+            },
+            Algorithm::LZW { lookahead_max: 3 },
+            Algorithm::STACK {
+                lookahead_max: usize::MAX,
+                min_gain: 5,
+            },
+            Algorithm::STORE,
+        ];
+        let input = b"RATABARBARATABARBARAT, TAMTARAMTAMTAMRAMTAT";
+        for algorithm in &algorithms {
+            let mut file = Cursor::new(Vec::new());
+            compress(algorithm, input, &mut file).unwrap();
+            let data = file.into_inner();
+            assert_eq!(&read_header(&mut data.as_slice()).unwrap(), algorithm);
+            assert_eq!(decompress(&mut data.as_slice()).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_bits_round_trip() {
+        let mask: Vec<bool> = (0..8003).map(|i| i % 397 == 0).collect();
+        let algorithm = Algorithm::LZ77 {
+            window_size: 255,
+            lookahead_buffer_size: 255,
+        };
+        let compressed = compress_bits(&algorithm, &mask).unwrap();
+        // smaller than even the packed bits, let alone a byte per bit
+        assert!(compressed.len() < mask.len() / 8);
+        assert_eq!(decompress_bits(&compressed).unwrap(), mask);
+    }
+
+    #[test]
+    fn test_decompress_cow() {
+        let input = "RATABARBARATABARBARAT, TAMTARAMTAMTAMRAMTAT";
+        let stored = compress_str(&Algorithm::STORE, input).unwrap();
+        match decompress_cow(&stored).unwrap() {
+            Cow::Borrowed(data) => {
+                assert_eq!(data, input.as_bytes());
+                // a view into the compressed data
+                assert_eq!(data.as_ptr(), stored[stored.len() - input.len()..].as_ptr());
+            }
+            Cow::Owned(_) => panic!("stored data was copied"),
+        }
+
+        let compressed = compress_str(&Algorithm::LZW { lookahead_max: 255 }, input).unwrap();
+        let decompressed = decompress_cow(&compressed).unwrap();
+        assert!(matches!(decompressed, Cow::Owned(_)));
+        assert_eq!(decompressed.as_ref(), input.as_bytes());
+    }
+}
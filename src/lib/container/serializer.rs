@@ -0,0 +1,861 @@
+use crate::{
+    encoding::elias::gamma_encode,
+    lz::{
+        lz77::{LZ77Encoder, LZ77entry, LZ77tuple},
+        lz78::{LZ78entry, LZ78tuple},
+    },
+    transform::bwt::column_counts,
+};
+use bits_io::bit_types::BitVec;
+use num_traits::ToBytes;
+
+use std::{
+    error,
+    io::{self, Seek, SeekFrom, Write},
+};
+
+const U8_MAX: usize = u8::MAX as usize;
+const U16_MAX: usize = u16::MAX as usize;
+const U32_MAX: usize = u32::MAX as usize;
+
+/// Returns the minimum number of bytes needed to represent a given value.
+///
+/// ## Arguments
+/// - `val` - The value to be represented.
+///
+/// ## Returns
+/// - `u8` - The number of bytes needed to represent the value.
+///
+/// ## Example
+/// ```ignore
+/// let bytes_needed = min_size(300);
+/// assert_eq!(bytes_needed, 2);
+/// ```
+fn min_size(val: usize) -> u8 {
+    if val <= U8_MAX {
+        1
+    } else if val <= U16_MAX {
+        2
+    } else if val <= U32_MAX {
+        4
+    } else {
+        8
+    }
+}
+
+/// Serializes a `usize` value into a specified number of bytes.
+///
+/// ## Arguments
+/// - `value` - The `usize` value to be serialized.
+/// - `state` - The output stream to write the serialized data.
+/// - `num_bytes` - The number of bytes to serialize the value into.
+///
+/// ## Returns
+/// - `io::Result<()>` - Indicates success or failure of the operation.
+///
+/// ## Example
+/// ```ignore
+/// let mut buffer = Vec::new();
+/// serialize_usize(42, &mut buffer, 1).unwrap();
+/// assert_eq!(buffer, vec![42]);
+/// ```
+fn serialize_usize<W: Write>(value: usize, state: &mut W, num_bytes: u8) -> io::Result<()> {
+    match num_bytes {
+        1 => {
+            state.write_all(&[value as u8])?;
+        }
+        2 => {
+            state.write_all(&(value as u16).to_le_bytes())?;
+        }
+        4 => {
+            state.write_all(&(value as u32).to_le_bytes())?;
+        }
+        8 => {
+            state.write_all(&value.to_le_bytes())?;
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Serializes a vector of LZ77 entries into a caller provided buffer.
+/// Arguments used in compression are necessary, for optimizing integer encoding.
+/// The buffer is cleared first, and the entries are drained, so both buffers
+/// keep their capacity for reuse.
+///
+/// ## Format
+/// - The first eight bytes represent the length of the vector.
+/// - The next byte represents the size that the first values in triples will be serialized into.
+/// - The next byte represents the size that the second values in triples will be serialized into.
+/// - The remaining bytes are the serialized entries, each consisting of three parts:
+///     - The first part is the offset into the sliding window.
+///     - The second part is the length of the match.
+///     - The third part is the value
+///
+/// ## Arguments
+/// - `value` - The LZ77 entries to be serialized, emptied by the call.
+/// - `window_size` - The size of the sliding window.
+/// - `lookahead_buffer_size` - The size of the lookahead buffer.
+/// - `out` - The buffer to write the serialized data into.
+///
+/// ## Returns
+/// - `io::Result<()>` - Indicates success or failure of the operation.
+pub fn serialize_lz77_into<T: ToBytes>(
+    value: &mut Vec<LZ77entry<T>>,
+    window_size: usize,
+    lookahead_buffer_size: usize,
+    out: &mut Vec<u8>,
+) -> io::Result<()> {
+    out.clear();
+    serialize_usize(value.len(), out, 8)?;
+    let window_size_bytes = min_size(window_size);
+    out.push(window_size_bytes);
+    let lookahead_buffer_size_bytes = min_size(lookahead_buffer_size);
+    out.push(lookahead_buffer_size_bytes);
+    for entry in value.drain(..) {
+        serialize_lz77_entry(entry, window_size_bytes, lookahead_buffer_size_bytes, out)?;
+    }
+    Ok(())
+}
+
+/// Serializes a single LZ77 entry, using the given integer widths.
+fn serialize_lz77_entry<T: ToBytes, W: Write>(
+    entry: LZ77entry<T>,
+    window_size_bytes: u8,
+    lookahead_buffer_size_bytes: u8,
+    state: &mut W,
+) -> io::Result<()> {
+    let tp: LZ77tuple<T> = entry.into();
+    serialize_usize(tp.0, state, window_size_bytes)?;
+    serialize_usize(tp.1, state, lookahead_buffer_size_bytes)?;
+    let bytes = tp.2.to_le_bytes();
+    state.write_all(bytes.as_ref())
+}
+
+/// The largest value stored in a nibble of a [serialize_lz77_packed] token.
+/// Larger values are continued in extension bytes.
+pub(crate) const NIBBLE_MAX: usize = 0xF;
+
+/// Serializes the part of a value that didn't fit in a token nibble, as a
+/// run of 255 bytes ended by a smaller byte, as in LZ4.
+///
+/// ## Arguments
+/// - `value` - The value, less the [NIBBLE_MAX] stored in the nibble.
+/// - `state` - The output stream to write the serialized data.
+///
+/// ## Returns
+/// - `io::Result<()>` - Indicates success or failure of the operation.
+fn serialize_extension<W: Write>(mut value: usize, state: &mut W) -> io::Result<()> {
+    while value >= U8_MAX {
+        state.write_all(&[u8::MAX])?;
+        value -= U8_MAX;
+    }
+    state.write_all(&[value as u8])
+}
+
+/// Serializes a vector of LZ77 entries into a specified output stream, packing
+/// the match length and the number of literals following the match into a
+/// single token byte, as in LZ4. Short matches, and runs of entries without a
+/// match, cost much less than in [serialize_lz77_into].
+///
+/// ## Format
+/// - The first eight bytes represent the number of entries.
+/// - The next byte represents the size that offsets will be serialized into.
+/// - The remaining bytes are sequences, each a match followed by the literals
+///   up to the next match:
+///     - A token byte, with the number of literals in the high nibble, and
+///       the match length in the low nibble. A nibble of 15 is continued in
+///       extension bytes, added to it up to and including the first byte
+///       below 255.
+///     - The extension bytes of the match length.
+///     - The offset, if the match length isn't zero.
+///     - The extension bytes of the number of literals.
+///     - The literals, the first being the next value of the matching entry,
+///       and the rest those of entries without a match.
+///
+/// Entries without a match are assumed to have an offset of zero, as the
+/// encoders produce them.
+///
+/// ## Arguments
+/// - `value` - The vector of LZ77 entries to be serialized.
+/// - `window_size` - The size of the sliding window.
+/// - `state` - The output stream to write the serialized data.
+///
+/// ## Returns
+/// - `io::Result<()>` - Indicates success or failure of the operation.
+///
+/// ## Example
+/// ```
+/// use generic_compression::{
+///     container::{deserializer::deserialize_lz77_packed, serializer::serialize_lz77_packed},
+///     lz::lz77::{LZ77entry, lz77_decode, lz77_encode},
+/// };
+///
+/// let encoded = lz77_encode(b"rabarbarbar", 255, 255);
+/// let mut buffer = Vec::new();
+/// serialize_lz77_packed(encoded, 255, &mut buffer).unwrap();
+/// let deserialized: Vec<LZ77entry<u8>> = deserialize_lz77_packed(&mut buffer.as_slice()).unwrap();
+/// assert_eq!(lz77_decode(&deserialized).unwrap(), b"rabarbarbar");
+/// ```
+pub fn serialize_lz77_packed<T: ToBytes, W: Write>(
+    value: Vec<LZ77entry<T>>,
+    window_size: usize,
+    state: &mut W,
+) -> io::Result<()> {
+    serialize_usize(value.len(), state, 8)?;
+    let window_size_bytes = min_size(window_size);
+    state.write_all(&[window_size_bytes])?;
+    let mut entries = value
+        .into_iter()
+        .map(|entry| -> LZ77tuple<T> { entry.into() })
+        .peekable();
+    while let Some((offset, length, next)) = entries.next() {
+        let mut literals = vec![next];
+        while let Some((_, 0, _)) = entries.peek() {
+            literals.push(entries.next().expect("The entry was peeked").2);
+        }
+        let token = (literals.len().min(NIBBLE_MAX) << 4) | length.min(NIBBLE_MAX);
+        state.write_all(&[token as u8])?;
+        if length >= NIBBLE_MAX {
+            serialize_extension(length - NIBBLE_MAX, state)?;
+        }
+        if length > 0 {
+            serialize_usize(offset, state, window_size_bytes)?;
+        }
+        if literals.len() >= NIBBLE_MAX {
+            serialize_extension(literals.len() - NIBBLE_MAX, state)?;
+        }
+        for literal in literals {
+            state.write_all(literal.to_le_bytes().as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+/// A writer that compresses everything written to it using the LZ77
+/// algorithm, producing the same format as [serialize_lz77_into]. Entries are
+/// serialized as soon as they are encoded, and the entry count at the start
+/// of the format is filled in by [Lz77Writer::finish], which is why the
+/// underlying stream must be seekable.
+pub struct Lz77Writer<W: Write + Seek> {
+    /// The encoder, only ever taken by [Lz77Writer::finish]
+    encoder: Option<LZ77Encoder<u8>>,
+    state: W,
+    header_position: u64,
+    count: usize,
+    window_size_bytes: u8,
+    lookahead_buffer_size_bytes: u8,
+    pending: Vec<LZ77entry<u8>>,
+}
+
+impl<W: Write + Seek> Lz77Writer<W> {
+    /// Creates a new writer, writing the header of the format.
+    ///
+    /// ## Arguments
+    /// - `state` - The output stream to write the serialized data.
+    /// - `window_size` - The size of the sliding window.
+    /// - `lookahead_buffer_size` - The size of the lookahead buffer.
+    ///
+    /// ## Returns
+    /// - `io::Result<Self>` - The writer, or an error if writing the header failed.
+    pub fn new(mut state: W, window_size: usize, lookahead_buffer_size: usize) -> io::Result<Self> {
+        let header_position = state.stream_position()?;
+        // placeholder for the entry count
+        serialize_usize(0, &mut state, 8)?;
+        let window_size_bytes = min_size(window_size);
+        state.write_all(&[window_size_bytes])?;
+        let lookahead_buffer_size_bytes = min_size(lookahead_buffer_size);
+        state.write_all(&[lookahead_buffer_size_bytes])?;
+        Ok(Lz77Writer {
+            encoder: Some(LZ77Encoder::new(window_size, lookahead_buffer_size)),
+            state,
+            header_position,
+            count: 0,
+            window_size_bytes,
+            lookahead_buffer_size_bytes,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Serializes all encoded, but not yet serialized entries.
+    fn write_pending(&mut self) -> io::Result<()> {
+        self.count += self.pending.len();
+        for entry in self.pending.drain(..) {
+            serialize_lz77_entry(
+                entry,
+                self.window_size_bytes,
+                self.lookahead_buffer_size_bytes,
+                &mut self.state,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the remaining data, and fills in the entry count.
+    ///
+    /// ## Returns
+    /// - `io::Result<W>` - The underlying stream, positioned after the serialized data.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.finish(&mut self.pending);
+        }
+        self.write_pending()?;
+        let end = self.state.stream_position()?;
+        self.state.seek(SeekFrom::Start(self.header_position))?;
+        serialize_usize(self.count, &mut self.state, 8)?;
+        self.state.seek(SeekFrom::Start(end))?;
+        self.state.flush()?;
+        Ok(self.state)
+    }
+}
+
+impl<W: Write + Seek> Write for Lz77Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder
+            .as_mut()
+            .expect("Encoder is only taken by finish")
+            .push(buf, &mut self.pending);
+        self.write_pending()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state.flush()
+    }
+}
+
+/// Serializes a vector of LZ78 entries into a specified output stream.
+/// Arguments used in compression are necessary, for optimizing integer encoding.
+///
+/// ## Format
+/// - The first eight bytes represent the length of the vector.
+/// - The next byte represents the size that the first values in pairs will be serialized into.
+/// - The following bytes represent the serialized entries, each consisting of two parts:
+///    - The first part is the index into the dictionary.
+///   - The second part is the value.
+///
+/// ## Arguments
+/// - `value` - The vector of LZ78 entries to be serialized.
+/// - `dictionary_size` - The size of the dictionary.
+/// - `state` - The output stream to write the serialized data.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn std::error::Error>>` - Indicates success or failure of the operation.
+pub fn serialize_lz78<T: ToBytes, W: Write>(
+    value: Vec<LZ78entry<T>>,
+    dictionary_size: usize,
+    state: &mut W,
+) -> Result<(), Box<dyn error::Error>> {
+    serialize_usize(value.len(), state, 8)?;
+    let dictionary_size_bytes = min_size(dictionary_size);
+    state.write_all(&[dictionary_size_bytes])?;
+    for entry in value {
+        let tp: LZ78tuple<T> = entry.into();
+        if let Some(idx) = tp.0 {
+            serialize_usize(idx + 1, state, dictionary_size_bytes)?;
+        } else {
+            serialize_usize(0, state, dictionary_size_bytes)?; // 0 for None
+        }
+        let bytes = tp.1.to_le_bytes();
+        state.write_all(bytes.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Serializes a vector of LZ78 entries along with their phrase lengths.
+///
+/// ## Format
+/// - The entries, serialized the same way as in [serialize_lz78].
+/// - The next eight bytes represent the number of bytes the lengths take up.
+/// - The remaining bytes are the phrase lengths, Elias gamma coded and
+///   padded with zeros to a whole byte.
+///
+/// ## Arguments
+/// - `value` - The vector of LZ78 entries to be serialized.
+/// - `lengths` - The phrase lengths of the entries.
+/// - `dictionary_size` - The size of the dictionary.
+/// - `state` - The output stream to write the serialized data.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn std::error::Error>>` - Indicates success or failure of the operation.
+///
+/// ## Example
+/// ```
+/// use generic_compression::{
+///     container::{
+///         deserializer::deserialize_lz78_with_lengths, serializer::serialize_lz78_with_lengths,
+///     },
+///     lz::lz78::{LZ78entry, lz78_encode_with_lengths},
+/// };
+///
+/// let (encoded, lengths) = lz78_encode_with_lengths(b"TAMTARAMTAMTAMRAMTAT", 8, 16);
+/// let mut buffer = Vec::new();
+/// serialize_lz78_with_lengths(encoded, &lengths, 16, &mut buffer).unwrap();
+/// let (_, deserialized_lengths): (Vec<LZ78entry<u8>>, _) =
+///     deserialize_lz78_with_lengths(&mut buffer.as_slice(), 16).unwrap();
+/// assert_eq!(deserialized_lengths, lengths);
+/// ```
+pub fn serialize_lz78_with_lengths<T: ToBytes, W: Write>(
+    value: Vec<LZ78entry<T>>,
+    lengths: &[usize],
+    dictionary_size: usize,
+    state: &mut W,
+) -> Result<(), Box<dyn error::Error>> {
+    serialize_lz78(value, dictionary_size, state)?;
+    let mut bits = BitVec::new();
+    for length in lengths {
+        gamma_encode(*length as u64, &mut bits);
+    }
+    let bytes = bits.as_raw_slice();
+    serialize_usize(bytes.len(), state, 8)?;
+    state.write_all(bytes)?;
+    Ok(())
+}
+
+/// Serializes a vector of LZ78 entries into bits, using Elias gamma coding
+/// for the indices. Indices grow over the stream, so early entries are much
+/// smaller than with [serialize_lz78], and no integer width needs to be chosen.
+///
+/// ## Format
+/// - The number of entries plus one, gamma coded.
+/// - The entries, each consisting of two parts:
+///    - The index into the dictionary plus one, or one for no index, gamma coded.
+///    - The value, as raw bytes.
+///
+/// ## Arguments
+/// - `value` - The vector of LZ78 entries to be serialized.
+///
+/// ## Returns
+/// - `BitVec` - The serialized data.
+///
+/// ## Example
+/// ```
+/// use generic_compression::{
+///     container::{deserializer::deserialize_lz78_gamma, serializer::serialize_lz78_gamma},
+///     lz::lz78::{LZ78entry, lz78_decode, lz78_encode},
+/// };
+///
+/// let input = b"TAMTARAMTAMTAMRAMTAT";
+/// let bits = serialize_lz78_gamma(lz78_encode(input, 8, 16));
+/// let deserialized: Vec<LZ78entry<u8>> =
+///     deserialize_lz78_gamma(&mut bits.as_bitslice(), 16).unwrap();
+/// assert_eq!(lz78_decode(&deserialized, 16), input);
+/// ```
+pub fn serialize_lz78_gamma<T: ToBytes>(value: Vec<LZ78entry<T>>) -> BitVec {
+    let mut out = BitVec::new();
+    gamma_encode(value.len() as u64 + 1, &mut out);
+    for entry in value {
+        let tp: LZ78tuple<T> = entry.into();
+        gamma_encode(tp.0.map_or(1, |idx| idx as u64 + 2), &mut out);
+        let bytes = tp.1.to_le_bytes();
+        out.extend_from_raw_slice(bytes.as_ref());
+    }
+    out
+}
+
+/// Serializes a vector of LZW entries into a specified output stream.
+///
+/// ## Format
+/// - The first eight bytes represent the length of the vector.
+/// - The next byte represents the size that the values will be serialized into.
+/// - The remaining bytes are the serialized entries.
+///
+/// ## Arguments
+/// - `value` - The vector of LZW entries to be serialized.
+/// - `state` - The output stream to write the serialized data.
+///
+/// ## Returns
+/// - `Result<(), Box<dyn std::error::Error>>` - Indicates success or failure of the operation.
+pub fn serialize_lzw<W: Write>(
+    value: Vec<usize>,
+    state: &mut W,
+) -> Result<(), Box<dyn error::Error>> {
+    serialize_usize(value.len(), state, 8)?;
+    let width = min_size(value.iter().copied().max().unwrap_or(0));
+    state.write_all(&[width])?;
+    for entry in value {
+        serialize_usize(entry, state, width)?;
+    }
+    Ok(())
+}
+
+/// Serializes a block of Burrows-Wheeler Transform output as a bit stream,
+/// optionally followed by the symbol counts that let the decoder skip sorting.
+///
+/// ## Format
+/// - The gamma code of the index of the original data, plus one.
+/// - The gamma code of one without counts, or two with counts.
+/// - With counts, the gamma code of the number of distinct bytes plus one,
+///   and for each distinct byte, the raw byte followed by the gamma code of its count.
+///   The block length is the sum of the counts.
+/// - Without counts, the gamma code of the block length plus one.
+/// - The raw bytes of the block.
+///
+/// ## Arguments
+/// - `encoded` - The transformed data.
+/// - `index` - The index of the original data.
+/// - `with_counts` - Whether to store the symbol counts.
+///
+/// ## Returns
+/// - `BitVec` - The serialized block.
+pub fn serialize_bwt_block(encoded: &[u8], index: usize, with_counts: bool) -> BitVec {
+    let mut out = BitVec::new();
+    gamma_encode(index as u64 + 1, &mut out);
+    if with_counts {
+        gamma_encode(2u64, &mut out);
+        let counts = column_counts(encoded);
+        gamma_encode(counts.len() as u64 + 1, &mut out);
+        for (byte, count) in counts {
+            out.extend_from_raw_slice(&[byte]);
+            gamma_encode(count as u64, &mut out);
+        }
+    } else {
+        gamma_encode(1u64, &mut out);
+        gamma_encode(encoded.len() as u64 + 1, &mut out);
+    }
+    out.extend_from_raw_slice(encoded);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::deserializer::{
+        TruncatedError, deserialize_bwt_block, deserialize_lz77, deserialize_lz77_packed,
+        deserialize_lz78, deserialize_lz78_gamma, deserialize_lz78_with_lengths, deserialize_lzw,
+        deserialize_usize,
+    };
+    use crate::{
+        lz::{
+            lz77::{lz77_decode, lz77_encode, lz77_encode_into},
+            lz78::{lz78_decode, lz78_encode, lz78_encode_with_lengths},
+            lzw::{lzw_decode, lzw_encode},
+        },
+        transform::bwt::{decode_bwt_bytes, decode_bwt_with_counts, encode_bwt},
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn test_serialize_usize() {
+        let mut buffer = Vec::new();
+        serialize_usize(42, &mut buffer, 1).unwrap();
+        assert_eq!(buffer, vec![42]);
+
+        buffer.clear();
+        serialize_usize(300, &mut buffer, 2).unwrap();
+        assert_eq!(buffer, vec![44, 1]);
+
+        buffer.clear();
+        serialize_usize(70000, &mut buffer, 4).unwrap();
+        assert_eq!(buffer, vec![112, 17, 1, 0]);
+
+        buffer.clear();
+        serialize_usize(7000000000, &mut buffer, 8).unwrap();
+        assert_eq!(buffer, vec![0, 134, 59, 161, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_serialize_lz77_terminal_entry() {
+        // the final entry is a match that runs up to the very last symbol,
+        // which is always kept as the next character
+        let input = b"abcabcabcabc";
+        let last: LZ77tuple<u8> = lz77_encode(input, 8, 16).pop().unwrap().into();
+        assert_eq!(last, (3, 8, b'c'));
+
+        let mut writer = Lz77Writer::new(Cursor::new(Vec::new()), 8, 16).unwrap();
+        writer.write_all(input).unwrap();
+        let buffer = writer.finish().unwrap().into_inner();
+        let deserialized: Vec<LZ77entry<u8>> = deserialize_lz77(&mut buffer.as_slice()).unwrap();
+        assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+    }
+
+    #[test]
+    fn test_lz77_writer() {
+        let input = b"RATABARBARATABARBARAT".repeat(16);
+
+        let mut writer = Lz77Writer::new(Cursor::new(Vec::new()), 16, 8).unwrap();
+        io::copy(&mut input.as_slice(), &mut writer).unwrap();
+        let buffer = writer.finish().unwrap().into_inner();
+
+        // the same bytes as serializing everything at once
+        let mut expected = Vec::new();
+        serialize_lz77_into(&mut lz77_encode(&input, 16, 8), 16, 8, &mut expected).unwrap();
+        assert_eq!(buffer, expected);
+
+        let deserialized: Vec<LZ77entry<u8>> = deserialize_lz77(&mut buffer.as_slice()).unwrap();
+        assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+    }
+
+    #[test]
+    fn test_serialize_lz77_into() {
+        let mut entries = Vec::new();
+        let mut buffer = Vec::new();
+        for input in [&b"RATABARBARATABARBARAT"[..], b"", b"TAMTARAMTAMTAMRAMTAT"] {
+            lz77_encode_into(input, 16, 8, &mut entries);
+            serialize_lz77_into(&mut entries, 16, 8, &mut buffer).unwrap();
+            assert!(entries.is_empty());
+
+            // the same bytes as the streaming writer
+            let mut writer = Lz77Writer::new(Cursor::new(Vec::new()), 16, 8).unwrap();
+            writer.write_all(input).unwrap();
+            assert_eq!(buffer, writer.finish().unwrap().into_inner());
+
+            let deserialized: Vec<LZ77entry<u8>> =
+                deserialize_lz77(&mut buffer.as_slice()).unwrap();
+            assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_serialize_lz77_packed() {
+        let input = b"RATABARBARATABARBARAT, TAMTARAMTAMTAMRAMTAT".repeat(32);
+        // the entries aren't Clone, so both serializers get them rebuilt from tuples
+        let tuples: Vec<LZ77tuple<u8>> = lz77_encode(&input, 255, 255)
+            .into_iter()
+            .map(|entry| entry.into())
+            .collect();
+        let entries = || tuples.iter().copied().map(LZ77entry::from).collect();
+
+        let mut plain = Vec::new();
+        serialize_lz77_into(&mut entries(), 255, 255, &mut plain).unwrap();
+        let mut packed = Vec::new();
+        serialize_lz77_packed(entries(), 255, &mut packed).unwrap();
+        assert!(packed.len() < plain.len());
+
+        let deserialized: Vec<LZ77entry<u8>> =
+            deserialize_lz77_packed(&mut packed.as_slice()).unwrap();
+        assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+
+        // a cut off stream is reported as such
+        packed.pop();
+        let err = deserialize_lz77_packed::<_, 1, u8>(&mut packed.as_slice())
+            .err()
+            .unwrap();
+        assert!(err.is::<TruncatedError>());
+    }
+
+    #[test]
+    fn test_serialize_lz77_packed_extensions() {
+        // a run of 300 literals, and a match of 299, both too long for a nibble
+        let input: Vec<u16> = (0..300).chain(0..300).collect();
+        let tuples: Vec<LZ77tuple<u16>> = lz77_encode(&input, 300, 400)
+            .into_iter()
+            .map(|entry| entry.into())
+            .collect();
+        assert_eq!(tuples[300], (300, 299, 299));
+
+        let mut packed = Vec::new();
+        let entries = tuples.into_iter().map(LZ77entry::from).collect();
+        serialize_lz77_packed(entries, 300, &mut packed).unwrap();
+        let deserialized: Vec<LZ77entry<u16>> =
+            deserialize_lz77_packed(&mut packed.as_slice()).unwrap();
+        assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+    }
+
+    #[test]
+    fn test_lz78_with_lengths() {
+        let input = b"TAMTARAMTAMTAMRAMTAT".repeat(4);
+        let (encoded, lengths) = lz78_encode_with_lengths(&input, 8, 16);
+
+        let mut buffer = Vec::new();
+        serialize_lz78_with_lengths(encoded, &lengths, 16, &mut buffer).unwrap();
+        let (deserialized, deserialized_lengths): (Vec<LZ78entry<u8>>, _) =
+            deserialize_lz78_with_lengths(&mut buffer.as_slice(), 16).unwrap();
+        assert_eq!(deserialized_lengths, lengths);
+        assert_eq!(lz78_decode(&deserialized, 16), input);
+    }
+
+    #[test]
+    fn test_serialize_lz77_u64_symbols() {
+        let input: Vec<u64> = [u64::MAX, 1 << 40, 7, u64::MAX, 1 << 40, 7, 0]
+            .into_iter()
+            .cycle()
+            .take(64)
+            .collect();
+        let mut encoded = lz77_encode(&input, 300, 300);
+
+        let mut buffer = Vec::new();
+        serialize_lz77_into(&mut encoded, 300, 300, &mut buffer).unwrap();
+        let deserialized: Vec<LZ77entry<u64>> = deserialize_lz77(&mut buffer.as_slice()).unwrap();
+        assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+    }
+
+    #[test]
+    fn test_deserialize_invalid_width() {
+        let mut buffer = Vec::new();
+        serialize_usize(1, &mut buffer, 8).unwrap();
+        // a width of 3 bytes is never written
+        buffer.extend([3, 1, 0, 0, 0, 0]);
+        let result: Result<Vec<LZ77entry<u8>>, _> = deserialize_lz77(&mut buffer.as_slice());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_min_size_boundaries() {
+        let boundaries = [
+            (0, 1),
+            (U8_MAX, 1),
+            (U8_MAX + 1, 2),
+            (U16_MAX, 2),
+            (U16_MAX + 1, 4),
+            (U32_MAX, 4),
+            (U32_MAX + 1, 8),
+            (usize::MAX, 8),
+        ];
+        for (value, width) in boundaries {
+            assert_eq!(min_size(value), width, "width of {}", value);
+            let mut buffer = Vec::new();
+            serialize_usize(value, &mut buffer, width).unwrap();
+            assert_eq!(buffer.len(), width as usize);
+            assert_eq!(
+                deserialize_usize(&mut buffer.as_slice(), width).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_lz78_gamma() {
+        let input = b"She sells sea shells by the sea shore, the shells she sells are sea shells."
+            .repeat(64);
+        let dictionary_size = 1 << 20;
+        let mut fixed = Vec::new();
+        serialize_lz78(
+            lz78_encode(&input, 255, dictionary_size),
+            dictionary_size,
+            &mut fixed,
+        )
+        .unwrap();
+        let bits = serialize_lz78_gamma(lz78_encode(&input, 255, dictionary_size));
+        assert!(bits.as_raw_slice().len() < fixed.len());
+
+        let deserialized: Vec<LZ78entry<u8>> =
+            deserialize_lz78_gamma(&mut bits.as_bitslice(), dictionary_size).unwrap();
+        assert_eq!(lz78_decode(&deserialized, dictionary_size), input);
+    }
+
+    #[test]
+    fn test_bwt_block_counts() {
+        let input = b"She sells sea shells by the sea shore.".repeat(4);
+        let (encoded, index) = encode_bwt(&input);
+
+        let bits = serialize_bwt_block(&encoded, index, false);
+        let (block, block_index, counts) = deserialize_bwt_block(&mut bits.as_bitslice()).unwrap();
+        assert_eq!((&block, block_index, counts), (&encoded, index, None));
+
+        let bits = serialize_bwt_block(&encoded, index, true);
+        let (block, block_index, counts) = deserialize_bwt_block(&mut bits.as_bitslice()).unwrap();
+        assert_eq!((&block, block_index), (&encoded, index));
+        let counts = counts.unwrap();
+        assert_eq!(
+            counts.iter().map(|(_, count)| count).sum::<usize>(),
+            input.len()
+        );
+        assert_eq!(
+            decode_bwt_with_counts(&block, block_index, &counts).unwrap(),
+            decode_bwt_bytes(&encoded, index)
+        );
+    }
+
+    #[test]
+    fn test_bwt_block_mismatched_counts() {
+        // counts of one a and one b, followed by a block of two a's
+        let mut bits = BitVec::new();
+        gamma_encode(1u64, &mut bits);
+        gamma_encode(2u64, &mut bits);
+        gamma_encode(3u64, &mut bits);
+        for byte in [b'a', b'b'] {
+            bits.extend_from_raw_slice(&[byte]);
+            gamma_encode(1u64, &mut bits);
+        }
+        bits.extend_from_raw_slice(b"aa");
+        let err = deserialize_bwt_block(&mut bits.as_bitslice()).unwrap_err();
+        assert_eq!(err.to_string(), "The counts don't match the block");
+    }
+
+    const ROUND_TRIP_INPUT: &[u8] =
+        b"She sells sea shells by the sea shore, the shells she sells are sea shells.";
+
+    #[test]
+    fn test_lz77_cursor_round_trip() {
+        let input = ROUND_TRIP_INPUT.repeat(8);
+        // widths of one, two and four bytes for both parameters
+        for (window_size, lookahead_buffer_size) in [(200, 100), (300, 255), (70000, 300)] {
+            let mut writer =
+                Lz77Writer::new(Cursor::new(Vec::new()), window_size, lookahead_buffer_size)
+                    .unwrap();
+            writer.write_all(&input).unwrap();
+            let buffer = writer.finish().unwrap().into_inner();
+            let len = buffer.len() as u64;
+            let mut cursor = Cursor::new(buffer);
+            let deserialized: Vec<LZ77entry<u8>> = deserialize_lz77(&mut cursor).unwrap();
+            assert_eq!(cursor.position(), len);
+            assert_eq!(lz77_decode(&deserialized).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_lz78_cursor_round_trip() {
+        let input = ROUND_TRIP_INPUT.repeat(8);
+        for dictionary_size in [16, 255, 300, 70000] {
+            let mut buffer = Vec::new();
+            serialize_lz78(
+                lz78_encode(&input, 255, dictionary_size),
+                dictionary_size,
+                &mut buffer,
+            )
+            .unwrap();
+            let len = buffer.len() as u64;
+            let mut cursor = Cursor::new(buffer);
+            let deserialized: Vec<LZ78entry<u8>> =
+                deserialize_lz78(&mut cursor, dictionary_size).unwrap();
+            assert_eq!(cursor.position(), len);
+            assert_eq!(lz78_decode(&deserialized, dictionary_size), input);
+        }
+    }
+
+    #[test]
+    fn test_lzw_cursor_round_trip() {
+        let initial: Vec<u8> = (0..=255).collect();
+        for repeats in [1, 8, 256] {
+            let input = ROUND_TRIP_INPUT.repeat(repeats);
+            let mut buffer = Vec::new();
+            serialize_lzw(lzw_encode(&input, &initial, 255), &mut buffer).unwrap();
+            let len = buffer.len() as u64;
+            let mut cursor = Cursor::new(buffer);
+            let deserialized = deserialize_lzw(&mut cursor).unwrap();
+            assert_eq!(cursor.position(), len);
+            assert_eq!(lzw_decode(&deserialized, &initial), input);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_truncated() {
+        let input = ROUND_TRIP_INPUT.repeat(4);
+        let entries = lz77_encode(&input, 200, 100).len();
+        let mut writer = Lz77Writer::new(Cursor::new(Vec::new()), 200, 100).unwrap();
+        writer.write_all(&input).unwrap();
+        let buffer = writer.finish().unwrap().into_inner();
+        // length prefix, two widths, then three bytes per entry
+        let header = 8 + 2;
+        for kept in [0, 1, entries / 2, entries - 1] {
+            // cut in the middle of the entry after the kept ones
+            let cut = &buffer[..header + kept * 3 + 2];
+            let err = deserialize_lz77::<_, 1, u8>(&mut &cut[..]).err().unwrap();
+            assert_eq!(
+                err.downcast_ref::<TruncatedError>(),
+                Some(&TruncatedError {
+                    entries_read: kept,
+                    declared: entries
+                })
+            );
+        }
+
+        let mut buffer = Vec::new();
+        let initial: Vec<u8> = (0..=255).collect();
+        serialize_lzw(lzw_encode(&input, &initial, 255), &mut buffer).unwrap();
+        buffer.pop();
+        let err = deserialize_lzw(&mut buffer.as_slice()).unwrap_err();
+        assert!(err.is::<TruncatedError>());
+    }
+}
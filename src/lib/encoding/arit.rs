@@ -1,20 +1,278 @@
 use num::{Integer, One, Unsigned, Zero, rational::Ratio};
-use num_traits::{NumAssignOps, NumOps};
+use num_traits::{CheckedAdd, NumAssignOps, NumOps};
 
-use std::{collections::HashMap, hash::Hash, iter::Sum};
+use std::{collections::HashMap, error, fmt, hash::Hash, iter::Sum};
 
-fn weights_to_ranges<T: Hash + Eq, U: Integer + Clone + NumOps + NumAssignOps + Sum>(
-    weights: &[(T, U)],
-) -> HashMap<&T, (Ratio<U>, Ratio<U>)> {
-    let mut ranges = HashMap::with_capacity(weights.len());
-    let sum = weights.iter().map(|(_, weight)| weight.clone()).sum::<U>();
-    let mut total_weight: Ratio<U> = Ratio::zero();
-    for (key, weight) in weights.iter() {
-        let l_weight = total_weight.clone();
-        total_weight += Ratio::new(weight.clone(), sum.clone());
-        ranges.insert(key, (l_weight, total_weight.clone()));
-    }
-    ranges
+/// Error returned by the fallible arithmetic coding functions.
+#[derive(Debug, PartialEq)]
+pub enum ArithError {
+    /// The sum of the weights doesn't fit in the weight type
+    WeightOverflow,
+    /// A symbol of the input has no weight
+    UnknownSymbol {
+        /// The position of the symbol in the input
+        position: usize,
+    },
+    /// The encoded value is not in the range [0, 1)
+    InputOutOfRange,
+    /// The weights sum to zero, so no symbol has a range
+    ZeroTotalWeight,
+    /// The total to normalize the weights to is not a power of two, or is
+    /// smaller than the number of symbols
+    InvalidTotal {
+        /// The requested total
+        total: u32,
+    },
+}
+
+impl fmt::Display for ArithError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArithError::WeightOverflow => {
+                write!(f, "The sum of the weights overflows the weight type")
+            }
+            ArithError::UnknownSymbol { position } => {
+                write!(f, "The symbol at position {} has no weight", position)
+            }
+            ArithError::InputOutOfRange => {
+                write!(f, "The encoded value is not in the range [0, 1)")
+            }
+            ArithError::ZeroTotalWeight => write!(f, "The weights sum to zero"),
+            ArithError::InvalidTotal { total } => write!(
+                f,
+                "Can't normalize the weights to {}, which must be a power of two no smaller than the number of symbols",
+                total
+            ),
+        }
+    }
+}
+
+impl error::Error for ArithError {}
+
+/// Precomputed ranges of the symbols, that can be reused between calls to
+/// [arithmetic_encode_with_ranges] and [arithmetic_decode_with_ranges] that
+/// use the same weights.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::arit::{Ranges, arithmetic_encode_with_ranges};
+/// use num::rational::Ratio;
+///
+/// let weights = [(0, 1), (1, 3)];
+/// let ranges = Ranges::new(&weights);
+/// let encoded = arithmetic_encode_with_ranges(&[0, 1, 0, 1], &ranges);
+/// assert_eq!(encoded, Ratio::<u32>::new(47, 512));
+/// ```
+pub struct Ranges<'a, T, U> {
+    ranges: HashMap<&'a T, (Ratio<U>, Ratio<U>)>,
+}
+
+impl<'a, T: Hash + Eq, U: Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd>
+    Ranges<'a, T, U>
+{
+    /// Computes the ranges of the symbols, given their weights.
+    ///
+    /// ## Arguments
+    ///
+    /// - `weights` - A map of symbols to their weights.
+    ///
+    /// ## Returns
+    ///
+    /// The ranges of the symbols.
+    ///
+    /// ## Panics
+    ///
+    /// If the sum of the weights overflows `U`, or is zero, see [Ranges::try_new].
+    pub fn new(weights: &'a [(T, U)]) -> Self {
+        Self::try_new(weights).expect("Invalid weights")
+    }
+
+    /// Computes the ranges of the symbols, given their weights, checking
+    /// that their sum fits in `U`.
+    ///
+    /// ## Arguments
+    ///
+    /// - `weights` - A map of symbols to their weights.
+    ///
+    /// ## Returns
+    ///
+    /// The ranges of the symbols, or an error if the sum of the weights
+    /// overflows, or is zero.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use generic_compression::encoding::arit::{ArithError, Ranges};
+    ///
+    /// assert!(Ranges::try_new(&[(0, 100u8), (1, 100)]).is_ok());
+    /// assert_eq!(
+    ///     Ranges::try_new(&[(0, 200u8), (1, 100)]).err(),
+    ///     Some(ArithError::WeightOverflow)
+    /// );
+    /// ```
+    pub fn try_new(weights: &'a [(T, U)]) -> Result<Self, ArithError> {
+        let mut ranges = HashMap::with_capacity(weights.len());
+        let sum = weights
+            .iter()
+            .try_fold(U::zero(), |sum, (_, weight)| sum.checked_add(weight))
+            .ok_or(ArithError::WeightOverflow)?;
+        if sum.is_zero() {
+            return Err(ArithError::ZeroTotalWeight);
+        }
+        let mut total_weight: Ratio<U> = Ratio::zero();
+        for (key, weight) in weights.iter() {
+            let l_weight = total_weight.clone();
+            total_weight += Ratio::new(weight.clone(), sum.clone());
+            ranges.insert(key, (l_weight, total_weight.clone()));
+        }
+        Ok(Ranges { ranges })
+    }
+}
+
+/// Scales integer counts into weights that sum to exactly `total`, a power
+/// of two, so that the ranges of the symbols are clean binary fractions.
+/// Every symbol keeps a weight of at least one, even if its count is zero,
+/// so the coder accepts all of them.
+///
+/// ## Arguments
+///
+/// - `counts` - A map of symbols to how often they occur.
+/// - `total` - The sum of the normalized weights.
+///
+/// ## Returns
+///
+/// The normalized weights, or an error if `total` is not a power of two, or
+/// is smaller than the number of symbols.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::arit::normalize_weights;
+///
+/// let weights = normalize_weights(&[(b'a', 30), (b'b', 10), (b'c', 0)], 16).unwrap();
+/// assert_eq!(weights, vec![(b'a', 11), (b'b', 4), (b'c', 1)]);
+/// ```
+pub fn normalize_weights<T: Clone>(
+    counts: &[(T, u32)],
+    total: u32,
+) -> Result<Vec<(T, u32)>, ArithError> {
+    if !total.is_power_of_two() || (total as usize) < counts.len() {
+        return Err(ArithError::InvalidTotal { total });
+    }
+    let sum: u64 = counts.iter().map(|(_, count)| *count as u64).sum();
+    let mut weights: Vec<(T, u32)> = counts
+        .iter()
+        .map(|(symbol, count)| {
+            // without any counts, the symbols are weighted equally
+            let weight = (*count as u64 * total as u64)
+                .checked_div(sum)
+                .unwrap_or(total as u64 / counts.len() as u64);
+            (symbol.clone(), (weight as u32).max(1))
+        })
+        .collect();
+    // rounding leaves the sum a little off, which the heaviest symbols absorb
+    let mut actual: u32 = weights.iter().map(|(_, weight)| weight).sum();
+    while actual != total {
+        let Some(heaviest) = weights.iter_mut().max_by_key(|(_, weight)| *weight) else {
+            break;
+        };
+        if actual < total {
+            heaviest.1 += total - actual;
+            actual = total;
+        } else {
+            let taken = (actual - total).min(heaviest.1 - 1);
+            heaviest.1 -= taken;
+            actual -= taken;
+        }
+    }
+    Ok(weights)
+}
+
+/// Converts probabilities, such as the output of a model, into integer
+/// weights that sum to exactly `2^precision`. The probabilities don't need
+/// to sum to one, they are scaled by their sum. Each weight is rounded down,
+/// and the units lost to rounding go to the symbols with the largest
+/// remainders. Like [normalize_weights], every symbol keeps a weight of at
+/// least one, so the coder accepts all of them.
+///
+/// ## Arguments
+///
+/// - `probs` - A map of symbols to their probabilities.
+/// - `precision` - The number of bits of the sum of the weights.
+///
+/// ## Returns
+///
+/// The integer weights.
+///
+/// ## Panics
+///
+/// If a probability is negative or not finite, `precision` is not smaller
+/// than 64, or there are more symbols than `2^precision`.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::arit::weights_from_probabilities;
+///
+/// let weights = weights_from_probabilities(&[(b'a', 0.5), (b'b', 0.3), (b'c', 0.2)], 4);
+/// assert_eq!(weights, vec![(b'a', 8), (b'b', 5), (b'c', 3)]);
+/// ```
+pub fn weights_from_probabilities<T: Clone>(probs: &[(T, f64)], precision: u32) -> Vec<(T, u64)> {
+    assert!(precision < u64::BITS, "Too many bits of precision");
+    let total = 1u64 << precision;
+    assert!(
+        probs.len() as u64 <= total,
+        "More symbols than the total weight"
+    );
+    assert!(
+        probs
+            .iter()
+            .all(|(_, prob)| prob.is_finite() && *prob >= 0.0),
+        "Probabilities must be finite and not negative"
+    );
+    if probs.is_empty() {
+        return Vec::new();
+    }
+    let sum: f64 = probs.iter().map(|(_, prob)| prob).sum();
+    let scaled: Vec<f64> = probs
+        .iter()
+        .map(|(_, prob)| {
+            if sum > 0.0 {
+                prob / sum * total as f64
+            } else {
+                // without any probabilities, the symbols are weighted equally
+                total as f64 / probs.len() as f64
+            }
+        })
+        .collect();
+    let mut weights: Vec<(T, u64)> = probs
+        .iter()
+        .zip(&scaled)
+        .map(|((symbol, _), scaled)| (symbol.clone(), (*scaled as u64).max(1)))
+        .collect();
+    let mut actual: u64 = weights.iter().map(|(_, weight)| weight).sum();
+    if actual < total {
+        let mut order: Vec<usize> = (0..weights.len()).collect();
+        order.sort_by(|a, b| {
+            (scaled[*b] - scaled[*b].floor()).total_cmp(&(scaled[*a] - scaled[*a].floor()))
+        });
+        for i in order.into_iter().cycle().take((total - actual) as usize) {
+            weights[i].1 += 1;
+        }
+        actual = total;
+    }
+    // the minimum weights, and floating point errors, may overshoot the total
+    while actual > total {
+        let heaviest = weights
+            .iter_mut()
+            .max_by_key(|(_, weight)| *weight)
+            .expect("There is at least one symbol");
+        let taken = (actual - total).min(heaviest.1 - 1);
+        heaviest.1 -= taken;
+        actual -= taken;
+    }
+    weights
 }
 
 /// Encode a sequence of symbols using arithmetic encoding.
@@ -29,6 +287,10 @@ fn weights_to_ranges<T: Hash + Eq, U: Integer + Clone + NumOps + NumAssignOps +
 ///
 /// A Rational32 representing the encoded value.
 ///
+/// ## Panics
+///
+/// If a symbol has no weight, see [arithmetic_encode_checked].
+///
 /// ## Example
 ///
 /// ```
@@ -43,22 +305,91 @@ fn weights_to_ranges<T: Hash + Eq, U: Integer + Clone + NumOps + NumAssignOps +
 /// ```
 pub fn arithmetic_encode<
     T: Hash + Eq,
-    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum,
+    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd,
 >(
     input: &[T],
     weights: &[(T, U)],
 ) -> Ratio<U> {
-    let ranges = weights_to_ranges(weights);
+    arithmetic_encode_with_ranges(input, &Ranges::new(weights))
+}
+
+/// Encode a sequence of symbols using arithmetic encoding, with precomputed
+/// ranges.
+///
+/// ## Arguments
+///
+/// - `input` - A slice of symbols to be encoded.
+/// - `ranges` - The precomputed ranges of the symbols.
+///
+/// ## Returns
+///
+/// A Rational32 representing the encoded value.
+///
+/// ## Panics
+///
+/// If a symbol has no range.
+pub fn arithmetic_encode_with_ranges<
+    T: Hash + Eq,
+    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd,
+>(
+    input: &[T],
+    ranges: &Ranges<T, U>,
+) -> Ratio<U> {
+    encode(input, ranges).expect("Symbol missing from the weights")
+}
+
+/// Encode a sequence of symbols using arithmetic encoding, reporting symbols
+/// missing from the weights, and weights whose sum overflows, as errors
+/// instead of panicking.
+///
+/// ## Arguments
+///
+/// - `input` - A slice of symbols to be encoded.
+/// - `weights` - A map of symbols to their weights.
+///
+/// ## Returns
+///
+/// The encoded value, or an error.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::arit::{ArithError, arithmetic_encode_checked};
+/// use num::rational::Ratio;
+///
+/// let weights = [(0, 1), (1, 3)];
+/// let encoded = arithmetic_encode_checked(&[0, 1, 0, 1], &weights);
+/// assert_eq!(encoded, Ok(Ratio::<u32>::new(47, 512)));
+/// let encoded = arithmetic_encode_checked(&[0, 1, 2], &weights);
+/// assert_eq!(encoded, Err(ArithError::UnknownSymbol { position: 2 }));
+/// ```
+pub fn arithmetic_encode_checked<
+    T: Hash + Eq,
+    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd,
+>(
+    input: &[T],
+    weights: &[(T, U)],
+) -> Result<Ratio<U>, ArithError> {
+    encode(input, &Ranges::try_new(weights)?)
+}
+
+fn encode<T: Hash + Eq, U: Unsigned + Integer + Clone + NumOps + NumAssignOps>(
+    input: &[T],
+    ranges: &Ranges<T, U>,
+) -> Result<Ratio<U>, ArithError> {
     let mut l = Ratio::zero();
     let mut r = Ratio::one();
-    for symbol in input {
-        let (l_weight, r_weight) = ranges.get(symbol).unwrap();
+    for (position, symbol) in input.iter().enumerate() {
+        let (l_weight, r_weight) = ranges
+            .ranges
+            .get(symbol)
+            .ok_or(ArithError::UnknownSymbol { position })?;
         let range = r - l.clone();
         r = l.clone() + range.clone() * r_weight;
         l = l + range * l_weight;
     }
 
-    return (r + l) / (U::one() + U::one());
+    Ok((r + l) / (U::one() + U::one()))
 }
 
 /// Decode a sequence of symbols using arithmetic decoding.
@@ -74,6 +405,10 @@ pub fn arithmetic_encode<
 ///
 /// A vector of symbols representing the decoded sequence.
 ///
+/// ## Panics
+///
+/// If the input is not in the range [0, 1), see [arithmetic_decode_checked].
+///
 /// ## Example
 ///
 /// ```
@@ -85,23 +420,98 @@ pub fn arithmetic_encode<
 /// let encoded = arithmetic_encode(&input, weights);
 /// let decoded = arithmetic_decode(encoded, weights, input.len());
 /// assert_eq!(decoded, input);
-///
+/// ```
 pub fn arithmetic_decode<
     T: Hash + Eq + Clone,
-    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum,
+    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd,
 >(
     input: Ratio<U>,
     weights: &[(T, U)],
     length: usize,
 ) -> Vec<T> {
-    let ranges = weights_to_ranges(weights);
+    arithmetic_decode_with_ranges(input, &Ranges::new(weights), length)
+}
+
+/// Decode a sequence of symbols using arithmetic decoding, with precomputed
+/// ranges.
+///
+/// ## Arguments
+///
+/// - `input` - A Rational32 representing the encoded value.
+/// - `ranges` - The precomputed ranges of the symbols.
+/// - `length` - The length of the output sequence.
+///
+/// ## Returns
+///
+/// A vector of symbols representing the decoded sequence.
+///
+/// ## Panics
+///
+/// If the input is not in the range [0, 1).
+pub fn arithmetic_decode_with_ranges<
+    T: Hash + Eq + Clone,
+    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd,
+>(
+    input: Ratio<U>,
+    ranges: &Ranges<T, U>,
+    length: usize,
+) -> Vec<T> {
+    decode(input, ranges, length).expect("Encoded value out of range")
+}
+
+/// Decode a sequence of symbols using arithmetic decoding, reporting an
+/// encoded value outside of [0, 1), and weights whose sum overflows, as
+/// errors instead of panicking. Guards against corrupted encoded values.
+///
+/// ## Arguments
+///
+/// - `input` - The encoded value.
+/// - `weights` - A map of symbols to their weights.
+/// - `length` - The length of the output sequence.
+///
+/// ## Returns
+///
+/// The decoded sequence, or an error.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::arit::{ArithError, arithmetic_decode_checked};
+/// use num::rational::Ratio;
+///
+/// let weights = [(0, 1), (1, 3)];
+/// let decoded = arithmetic_decode_checked(Ratio::<u32>::new(47, 512), &weights, 4);
+/// assert_eq!(decoded, Ok(vec![0, 1, 0, 1]));
+/// let decoded = arithmetic_decode_checked(Ratio::<u32>::new(3, 2), &weights, 4);
+/// assert_eq!(decoded, Err(ArithError::InputOutOfRange));
+/// ```
+pub fn arithmetic_decode_checked<
+    T: Hash + Eq + Clone,
+    U: Unsigned + Integer + Clone + NumOps + NumAssignOps + Sum + CheckedAdd,
+>(
+    input: Ratio<U>,
+    weights: &[(T, U)],
+    length: usize,
+) -> Result<Vec<T>, ArithError> {
+    decode(input, &Ranges::try_new(weights)?, length)
+}
+
+fn decode<T: Hash + Eq + Clone, U: Unsigned + Integer + Clone + NumOps + NumAssignOps>(
+    input: Ratio<U>,
+    ranges: &Ranges<T, U>,
+    length: usize,
+) -> Result<Vec<T>, ArithError> {
+    // unsigned, so it can't be below zero
+    if input >= Ratio::one() {
+        return Err(ArithError::InputOutOfRange);
+    }
     let mut l = Ratio::zero();
     let mut r = Ratio::one();
     let mut output: Vec<T> = Vec::with_capacity(length);
     for _ in 0..length {
         let d = r.clone() - l.clone();
         let x = (input.clone() - l.clone()) / d.clone();
-        for (key, (l_weight, r_weight)) in ranges.iter() {
+        for (key, (l_weight, r_weight)) in ranges.ranges.iter() {
             if x >= *l_weight && x < *r_weight {
                 output.push((*key).clone());
                 r = l.clone() + d.clone() * r_weight;
@@ -110,7 +520,7 @@ pub fn arithmetic_decode<
             }
         }
     }
-    return output;
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -135,4 +545,130 @@ mod tests {
         let decoded = arithmetic_decode(input, &weights, length);
         assert_eq!(decoded, b"abcd");
     }
+
+    #[test]
+    fn test_ranges_overflow() {
+        let weights: &[(u8, u8)] = &[(b'a', 200), (b'b', 50), (b'c', 6)];
+        assert_eq!(
+            Ranges::try_new(weights).err(),
+            Some(ArithError::WeightOverflow)
+        );
+        // right at the limit is fine
+        let weights: &[(u8, u8)] = &[(b'a', 200), (b'b', 50), (b'c', 5)];
+        assert!(Ranges::try_new(weights).is_ok());
+    }
+
+    #[test]
+    fn test_arithmetic_unknown_symbol() {
+        let weights: &[(u8, u32)] = &[(b'a', 1), (b'b', 1)];
+        assert_eq!(
+            arithmetic_encode_checked(b"abba!ab", weights),
+            Err(ArithError::UnknownSymbol { position: 4 })
+        );
+        assert_eq!(
+            arithmetic_encode_checked(b"abba", weights),
+            Ok(arithmetic_encode(b"abba", weights))
+        );
+        let weights: &[(u8, u8)] = &[(b'a', 255), (b'b', 1)];
+        assert_eq!(
+            arithmetic_encode_checked(b"ab", weights),
+            Err(ArithError::WeightOverflow)
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_decode_out_of_range() {
+        let weights: &[(u8, u32)] = &[(b'a', 1), (b'b', 1), (b'c', 1), (b'd', 1)];
+        assert_eq!(
+            arithmetic_decode_checked(Ratio::new(3, 2), weights, 4),
+            Err(ArithError::InputOutOfRange)
+        );
+        assert_eq!(
+            arithmetic_decode_checked(Ratio::one(), weights, 4),
+            Err(ArithError::InputOutOfRange)
+        );
+        assert_eq!(
+            arithmetic_decode_checked(Ratio::new(55, 512), weights, 4),
+            Ok(b"abcd".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_normalize_weights() {
+        let counts: &[(u8, u32)] = &[(b'a', 1234), (b'b', 7), (b'c', 0), (b'd', 99), (b'e', 1)];
+        for total in [8, 16, 256, 1 << 20] {
+            let weights = normalize_weights(counts, total).unwrap();
+            assert_eq!(weights.iter().map(|(_, weight)| weight).sum::<u32>(), total);
+            assert!(weights.iter().all(|(_, weight)| *weight >= 1));
+            assert!(weights[0].1 >= weights[3].1 && weights[3].1 >= weights[1].1);
+        }
+        // the coder accepts the normalized weights
+        let weights = normalize_weights(counts, 16).unwrap();
+        let input = b"aadace";
+        let encoded = arithmetic_encode_checked(input, &weights).unwrap();
+        assert_eq!(
+            arithmetic_decode_checked(encoded, &weights, input.len()),
+            Ok(input.to_vec())
+        );
+
+        assert_eq!(
+            normalize_weights(counts, 12),
+            Err(ArithError::InvalidTotal { total: 12 })
+        );
+        assert_eq!(
+            normalize_weights(counts, 4),
+            Err(ArithError::InvalidTotal { total: 4 })
+        );
+        let weights: &[(u8, u32)] = &[(b'a', 0), (b'b', 0)];
+        assert_eq!(
+            Ranges::try_new(weights).err(),
+            Some(ArithError::ZeroTotalWeight)
+        );
+        let weights = normalize_weights(weights, 8).unwrap();
+        assert_eq!(weights, vec![(b'a', 4), (b'b', 4)]);
+    }
+
+    #[test]
+    fn test_weights_from_probabilities() {
+        let probs: &[(u8, f64)] = &[
+            (b'a', 0.61),
+            (b'b', 0.2),
+            (b'c', 0.1),
+            (b'd', 0.0899),
+            (b'e', 0.0001),
+        ];
+        for precision in [3, 8, 10, 32, 62] {
+            let weights = weights_from_probabilities(probs, precision);
+            assert_eq!(
+                weights.iter().map(|(_, weight)| weight).sum::<u64>(),
+                1 << precision
+            );
+            assert!(weights.iter().all(|(_, weight)| *weight >= 1));
+            assert!(weights.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        }
+        // the coder accepts the weights
+        let weights = weights_from_probabilities(probs, 8);
+        let input = b"abcdea";
+        let encoded = arithmetic_encode_checked(input, &weights).unwrap();
+        assert_eq!(
+            arithmetic_decode_checked(encoded, &weights, input.len()),
+            Ok(input.to_vec())
+        );
+
+        let weights = weights_from_probabilities(&[(b'a', 0.0), (b'b', 0.0)], 3);
+        assert_eq!(weights, vec![(b'a', 4), (b'b', 4)]);
+        assert!(weights_from_probabilities::<u8>(&[], 8).is_empty());
+    }
+
+    #[test]
+    fn test_arithmetic_with_ranges() {
+        let weights: &[(u8, u64)] = &[(b'a', 5), (b'b', 2), (b'c', 1)];
+        let ranges = Ranges::new(weights);
+        for input in [&b"abacab"[..], b"cccc", b"a"] {
+            let encoded = arithmetic_encode_with_ranges(input, &ranges);
+            assert_eq!(encoded, arithmetic_encode(input, weights));
+            let decoded = arithmetic_decode_with_ranges(encoded, &ranges, input.len());
+            assert_eq!(decoded, input);
+        }
+    }
 }
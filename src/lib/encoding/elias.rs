@@ -7,6 +7,8 @@ use bits_io::{
 };
 use num_traits::{FromBytes, ToBytes};
 
+use std::marker::PhantomData;
+
 /// Encodes a value using Elias gamma encoding.
 ///
 /// ## Arguments
@@ -63,15 +65,27 @@ pub fn gamma_encode<I: ToBytes<Bytes: Send + 'static>>(value: I, out: &mut BitVe
 /// ```
 pub fn gamma_decode<const N: usize, I: FromBytes<Bytes = [u8; N]>, R: BitRead>(
     state: &mut R,
+) -> Result<I, Box<dyn std::error::Error>> {
+    let buff = bits![mut 0; 1];
+    state.read_bits_exact(buff)?;
+    gamma_decode_from(buff[0], state)
+}
+
+/// Decodes the rest of a gamma code, given its first bit.
+fn gamma_decode_from<const N: usize, I: FromBytes<Bytes = [u8; N]>, R: BitRead>(
+    first: bool,
+    state: &mut R,
 ) -> Result<I, Box<dyn std::error::Error>> {
     let mut num_zeros = 0;
     let buff = bits![mut 0; 1];
-    loop {
-        state.read_bits_exact(buff)?;
-        if buff[0] {
-            break;
-        }
+    buff.set(0, first);
+    while !buff[0] {
         num_zeros += 1;
+        // the value wouldn't fit in the integer, so the stream is malformed
+        if num_zeros >= N * 8 {
+            return Err(format!("Too many leading zeros for a {} byte integer", N).into());
+        }
+        state.read_bits_exact(buff)?;
     }
     let mut buff = [0u8; N];
     let slice = BitSlice::from_slice_mut(&mut buff);
@@ -81,6 +95,96 @@ pub fn gamma_decode<const N: usize, I: FromBytes<Bytes = [u8; N]>, R: BitRead>(
     Ok(I::from_be_bytes(&buff))
 }
 
+/// Decodes gamma encoded values until the input stream is exhausted. Useful
+/// when the number of values isn't stored, and the stream ends exactly where
+/// the last code does, so it must not be padded.
+///
+/// ## Arguments
+///
+/// - `state`: The input stream to read the encoded bits from.
+///
+/// ## Returns
+///
+/// - `Result<Vec<I>, Box<dyn std::error::Error>>` - The decoded values, or an
+///   error if the stream ends in the middle of a code.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::elias::gamma_decode_until_end;
+/// use bits_io::bits;
+///
+/// let mut buffer = bits![1, 0, 1, 0, 0, 0, 0, 1, 0, 0, 1];
+/// let decoded: Vec<u32> = gamma_decode_until_end(&mut buffer).unwrap();
+/// assert_eq!(decoded, vec![1, 2, 9]);
+/// ```
+pub fn gamma_decode_until_end<const N: usize, I: FromBytes<Bytes = [u8; N]>, R: BitRead>(
+    state: &mut R,
+) -> Result<Vec<I>, Box<dyn std::error::Error>> {
+    GammaDecoder::new(state).collect()
+}
+
+/// An iterator lazily decoding gamma encoded values from a stream, until
+/// the stream is exhausted. Every item is a decoded value, or an error if the
+/// stream is malformed or ends in the middle of a code, after which the
+/// iterator stops.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::elias::{GammaDecoder, gamma_encode};
+/// use bits_io::bit_types::BitVec;
+///
+/// let mut buffer = BitVec::new();
+/// for value in [3u32, 1, 4, 1, 5] {
+///     gamma_encode(value, &mut buffer);
+/// }
+/// let mut bits = buffer.as_bitslice();
+/// let decoded: Vec<u32> = GammaDecoder::new(&mut bits).map(Result::unwrap).collect();
+/// assert_eq!(decoded, vec![3, 1, 4, 1, 5]);
+/// ```
+pub struct GammaDecoder<'a, R, I> {
+    state: &'a mut R,
+    done: bool,
+    _value: PhantomData<I>,
+}
+
+impl<'a, R: BitRead, I> GammaDecoder<'a, R, I> {
+    /// Creates a decoder reading from the stream.
+    ///
+    /// ## Arguments
+    ///
+    /// - `state`: The input stream to read the encoded bits from.
+    pub fn new(state: &'a mut R) -> Self {
+        GammaDecoder {
+            state,
+            done: false,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<const N: usize, I: FromBytes<Bytes = [u8; N]>, R: BitRead> Iterator
+    for GammaDecoder<'_, R, I>
+{
+    type Item = Result<I, Box<dyn std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let buff = bits![mut 0; 1];
+        // running out of bits is only fine before the first bit of a code
+        let result = match self.state.read_bits(buff) {
+            Ok(0) => None,
+            Ok(_) => Some(gamma_decode_from(buff[0], self.state)),
+            Err(err) => Some(Err(err.into())),
+        };
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
 /// Encodes a value using Elias delta encoding.
 ///
 /// ## Arguments
@@ -136,6 +240,13 @@ pub fn delta_decode<const N: usize, I: FromBytes<Bytes = [u8; N]>, R: BitRead>(
     state: &mut R,
 ) -> Result<I, Box<dyn std::error::Error>> {
     let num_bits: usize = gamma_decode(state)?;
+    if num_bits > N * 8 {
+        return Err(format!(
+            "A {} bit value doesn't fit in a {} byte integer",
+            num_bits, N
+        )
+        .into());
+    }
     let mut buff = [0u8; N];
     let slice = BitSlice::from_slice_mut(&mut buff);
     let slice_len = slice.len();
@@ -177,4 +288,71 @@ mod tests {
         let decoded_value: u32 = delta_decode(&mut buffer).unwrap();
         assert_eq!(decoded_value, 42);
     }
+
+    #[test]
+    fn test_gamma_decode_too_many_zeros() {
+        let zeros = BitVec::repeat(false, 40);
+        let mut buffer = zeros.as_bitslice();
+        let result: Result<u32, _> = gamma_decode(&mut buffer);
+        assert!(result.is_err());
+        // a truncated stream is an error too
+        let mut buffer = bits![0, 0, 0, 1, 0];
+        let result: Result<u32, _> = gamma_decode(&mut buffer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_delta_decode_too_wide() {
+        // the length prefix says 33 bits, more than a u32 holds
+        let mut prefix = BitVec::new();
+        gamma_encode(33u32, &mut prefix);
+        prefix.extend(BitVec::repeat(true, 32));
+        let mut buffer = prefix.as_bitslice();
+        let result: Result<u32, _> = delta_decode(&mut buffer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_gamma_decode_until_end() {
+        let values: Vec<u32> = vec![1, 42, 7, 1000, 3];
+        let mut encoded = BitVec::new();
+        for value in &values {
+            gamma_encode(*value, &mut encoded);
+        }
+        let mut buffer = encoded.as_bitslice();
+        let decoded: Vec<u32> = gamma_decode_until_end(&mut buffer).unwrap();
+        assert_eq!(decoded, values);
+
+        let empty = BitVec::new();
+        let mut buffer = empty.as_bitslice();
+        let decoded: Vec<u32> = gamma_decode_until_end(&mut buffer).unwrap();
+        assert!(decoded.is_empty());
+
+        // cut inside the value of the last code, and inside its zeros
+        for cut in [1, 2] {
+            let mut buffer = &encoded[..encoded.len() - cut];
+            let result: Result<Vec<u32>, _> = gamma_decode_until_end(&mut buffer);
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_gamma_decoder() {
+        let values: Vec<u64> = (1..200).map(|i| i * i * 31).collect();
+        let mut encoded = BitVec::new();
+        for value in &values {
+            gamma_encode(*value, &mut encoded);
+        }
+        let mut buffer = encoded.as_bitslice();
+        let decoded: Vec<u64> = GammaDecoder::new(&mut buffer)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, values);
+
+        // a truncated stream yields the complete values, then a single error
+        let mut buffer = &encoded[..encoded.len() - 1];
+        let items: Vec<Result<u64, _>> = GammaDecoder::new(&mut buffer).collect();
+        assert_eq!(items.len(), values.len());
+        assert!(items.last().unwrap().is_err());
+    }
 }
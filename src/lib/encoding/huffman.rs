@@ -1,6 +1,10 @@
-use std::ops::Deref;
+use std::{error, ops::Deref};
 
-use bits_io::{bit_types::BitVec, bitvec};
+use bits_io::{
+    bit_types::BitVec,
+    bitvec,
+    prelude::{BitRead, BitSlice},
+};
 use num::Integer;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -27,6 +31,13 @@ pub struct HuffmanEncoding<T: Clone + Eq, W: Integer + Clone> {
     root: Vec<HeapValue<T, W>>,
 }
 
+/// A [HuffmanEncoding] with `u16` weights.
+pub type HuffmanU16<T> = HuffmanEncoding<T, u16>;
+/// A [HuffmanEncoding] with `u32` weights.
+pub type HuffmanU32<T> = HuffmanEncoding<T, u32>;
+/// A [HuffmanEncoding] with `u64` weights.
+pub type HuffmanU64<T> = HuffmanEncoding<T, u64>;
+
 #[inline(always)]
 fn left_child_index(index: usize) -> usize {
     index * 2 + 1
@@ -43,6 +54,22 @@ impl<T: Clone + Eq, W: Integer + Clone> HuffmanEncoding<T, W> {
         HuffmanEncoding { root: Vec::new() }
     }
 
+    /// Creates a new empty HuffmanEncoding, with space reserved for `capacity`
+    /// symbols, so that adding them doesn't reallocate.
+    ///
+    /// ## Arguments
+    ///
+    /// - `capacity`: The number of symbols to reserve space for.
+    ///
+    /// ## Returns
+    ///
+    /// A new HuffmanEncoding instance.
+    pub fn with_capacity(capacity: usize) -> Self {
+        HuffmanEncoding {
+            root: Vec::with_capacity(capacity),
+        }
+    }
+
     /// Creates a new HuffmanEncoding with the given weights
     ///
     /// ## Arguments
@@ -53,17 +80,54 @@ impl<T: Clone + Eq, W: Integer + Clone> HuffmanEncoding<T, W> {
     ///
     /// A new HuffmanEncoding instance.
     pub fn with_weights(weights: &[(T, W)]) -> Self {
-        let mut heap = weights
-            .iter()
-            .map(|(value, frequency)| HeapValue {
-                value: value.clone(),
-                frequency: frequency.clone(),
-            })
-            .collect::<Vec<_>>();
+        let mut heap = Vec::with_capacity(weights.len());
+        heap.extend(weights.iter().map(|(value, frequency)| HeapValue {
+            value: value.clone(),
+            frequency: frequency.clone(),
+        }));
         heap.sort_by(|a, b| b.cmp(a));
         HuffmanEncoding { root: heap }
     }
 
+    /// Creates a new HuffmanEncoding with the given counts, converting them
+    /// into the weight type. Convenient when the counts come from a
+    /// histogram of `usize`.
+    ///
+    /// ## Arguments
+    ///
+    /// - `counts`: A slice of tuples containing the value and how often it occurs.
+    ///
+    /// ## Returns
+    ///
+    /// A new HuffmanEncoding instance.
+    ///
+    /// ## Panics
+    ///
+    /// If a count doesn't fit in the weight type.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use generic_compression::encoding::HuffmanU32;
+    /// use bits_io::bits;
+    /// let codec = HuffmanU32::with_counts(&[(b'a', 5usize), (b'b', 9)]);
+    /// assert_eq!(codec.encode_value(&b'b').unwrap().as_bitslice(), bits![0]);
+    /// ```
+    pub fn with_counts(counts: &[(T, usize)]) -> Self
+    where
+        W: TryFrom<usize>,
+    {
+        let weights: Vec<(T, W)> = counts
+            .iter()
+            .map(|(value, count)| {
+                let weight = W::try_from(*count)
+                    .unwrap_or_else(|_| panic!("Count {} doesn't fit in the weight type", count));
+                (value.clone(), weight)
+            })
+            .collect();
+        Self::with_weights(&weights)
+    }
+
     fn encode_and_index(&self, value: &T) -> Option<(BitVec, usize)> {
         let mut stack = vec![(0, bitvec![0; 1]), (1, bitvec!(1; 1))];
         while let Some((index, bits)) = stack.pop() {
@@ -220,6 +284,9 @@ impl<T: Clone + Eq, W: Integer + Clone> HuffmanEncoding<T, W> {
 
     /// Decodes a bit vector into a value
     /// Also increases the frequency of the value in the heap, ensuring future encodings are better
+    /// Applies the same update as [HuffmanEncoding::encode_value_mut], so a
+    /// decoder starting with the same weights as the encoder stays in sync
+    /// with it, as long as every symbol was known to both from the start.
     ///
     /// ## Arguments
     ///
@@ -233,17 +300,139 @@ impl<T: Clone + Eq, W: Integer + Clone> HuffmanEncoding<T, W> {
         input: I,
     ) -> Option<T> {
         if let Some(index) = self.decode_index(input) {
+            // the value may move once the heap is reordered
+            let value = self.root[index].value.clone();
             // Increase the frequency of the value in the heap
             self.root[index].frequency = self.root[index].frequency.clone() + W::one();
             // Reorder the heap to maintain the heap property
             self.reorder_index(index);
-            return Some(self.root[index].value.clone());
+            return Some(value);
         } else {
             return None;
         }
     }
 }
 
+/// The largest code length representable in a code length table.
+pub const MAX_CODE_LENGTH: u8 = 15;
+
+/// Code length table symbol: repeat the previous length 3-6 times.
+const REPEAT_PREVIOUS: u8 = 16;
+/// Code length table symbol: repeat a zero length 3-10 times.
+const REPEAT_ZERO_SHORT: u8 = 17;
+/// Code length table symbol: repeat a zero length 11-138 times.
+const REPEAT_ZERO_LONG: u8 = 18;
+/// The number of bits every code length table symbol is written with.
+const SYMBOL_BITS: usize = 5;
+
+/// Writes the lowest `width` bits of `value` into `out`, most significant first.
+fn push_bits(value: u8, width: usize, out: &mut BitVec) {
+    let bytes = [value];
+    let bits = BitSlice::from_slice(&bytes);
+    out.extend_from_bitslice(&bits[bits.len() - width..]);
+}
+
+/// Reads `width` bits from `state` into the lowest bits of a byte.
+fn read_bits<R: BitRead>(state: &mut R, width: usize) -> Result<u8, Box<dyn error::Error>> {
+    let mut buff = [0u8; 1];
+    let slice = BitSlice::from_slice_mut(&mut buff);
+    let slice_len = slice.len();
+    state.read_bits_exact(&mut slice[slice_len - width..])?;
+    Ok(buff[0])
+}
+
+/// Encodes a table of code lengths into a compact bit representation.
+/// Runs of zeros and repeated lengths are replaced with repeat symbols, the
+/// same way DEFLATE transmits its code length alphabet. Every symbol is
+/// written using five bits, followed by the extra bits of repeat symbols.
+///
+/// ## Arguments
+///
+/// - `lengths`: The code lengths of each symbol. Each must be at most [MAX_CODE_LENGTH].
+/// - `out`: The output buffer to store the encoded bits.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::encoding::{decode_code_lengths, encode_code_lengths};
+/// use bits_io::bit_types::BitVec;
+///
+/// let lengths = [0, 0, 0, 0, 3, 3, 3, 3, 2];
+/// let mut buffer = BitVec::new();
+/// encode_code_lengths(&lengths, &mut buffer);
+/// let decoded = decode_code_lengths(&mut buffer.as_bitslice(), lengths.len()).unwrap();
+/// assert_eq!(decoded, lengths);
+/// ```
+pub fn encode_code_lengths(lengths: &[u8], out: &mut BitVec) {
+    let mut i = 0;
+    while i < lengths.len() {
+        let length = lengths[i];
+        assert!(length <= MAX_CODE_LENGTH, "Code length too large");
+        // find how long the run of the current length is
+        let mut run = 1;
+        while i + run < lengths.len() && lengths[i + run] == length {
+            run += 1;
+        }
+        if length == 0 && run >= 11 {
+            let run = run.min(138);
+            push_bits(REPEAT_ZERO_LONG, SYMBOL_BITS, out);
+            push_bits((run - 11) as u8, 7, out);
+            i += run;
+        } else if length == 0 && run >= 3 {
+            push_bits(REPEAT_ZERO_SHORT, SYMBOL_BITS, out);
+            push_bits((run - 3) as u8, 3, out);
+            i += run;
+        } else if run >= 4 {
+            // the length itself, and then repeats of it
+            push_bits(length, SYMBOL_BITS, out);
+            let run = (run - 1).min(6);
+            push_bits(REPEAT_PREVIOUS, SYMBOL_BITS, out);
+            push_bits((run - 3) as u8, 2, out);
+            i += run + 1;
+        } else {
+            push_bits(length, SYMBOL_BITS, out);
+            i += 1;
+        }
+    }
+}
+
+/// Decodes a table of code lengths encoded with [encode_code_lengths].
+///
+/// ## Arguments
+///
+/// - `state`: The input stream to read the encoded bits from.
+/// - `count`: The number of code lengths in the table.
+///
+/// ## Returns
+///
+/// - `Result<Vec<u8>, Box<dyn std::error::Error>>` - The decoded code lengths or an error.
+pub fn decode_code_lengths<R: BitRead>(
+    state: &mut R,
+    count: usize,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut lengths = Vec::with_capacity(count);
+    while lengths.len() < count {
+        let symbol = read_bits(state, SYMBOL_BITS)?;
+        let (length, run) = match symbol {
+            REPEAT_PREVIOUS => {
+                let previous = *lengths
+                    .last()
+                    .ok_or("Repeat symbol without a previous length")?;
+                (previous, read_bits(state, 2)? as usize + 3)
+            }
+            REPEAT_ZERO_SHORT => (0, read_bits(state, 3)? as usize + 3),
+            REPEAT_ZERO_LONG => (0, read_bits(state, 7)? as usize + 11),
+            length if length <= MAX_CODE_LENGTH => (length, 1),
+            _ => return Err("Invalid code length symbol".into()),
+        };
+        if lengths.len() + run > count {
+            return Err("Code length run exceeds the table size".into());
+        }
+        lengths.extend(std::iter::repeat_n(length, run));
+    }
+    Ok(lengths)
+}
+
 #[cfg(test)]
 mod tests {
     use bits_io::bits;
@@ -297,7 +486,7 @@ mod tests {
 
     #[test]
     fn test_dynamic_huffman() {
-        let mut huffman: HuffmanEncoding<char, u16> = HuffmanEncoding::new();
+        let mut huffman = HuffmanU16::new();
         let weights = [
             ('a', 5),
             ('b', 9),
@@ -317,4 +506,106 @@ mod tests {
             assert_eq!(decoded, *value);
         }
     }
+
+    #[test]
+    fn test_dynamic_huffman_sync() {
+        let weights: Vec<(u8, u32)> = (b'a'..=b'z').chain([b' ', b',']).map(|c| (c, 1)).collect();
+        let input = b"she sells sea shells by the sea shore, the shells she sells are sea shells";
+        let mut encoder = HuffmanEncoding::with_weights(&weights);
+        let mut decoder = HuffmanEncoding::with_weights(&weights);
+        for value in input.repeat(4) {
+            let encoded = encoder.encode_value_mut(&value);
+            let decoded = decoder
+                .decode_value_mut(encoded.as_bitslice().iter())
+                .unwrap();
+            assert_eq!(decoded, value);
+        }
+    }
+
+    #[test]
+    fn test_huffman_with_counts() {
+        let input = b"she sells sea shells by the sea shore";
+        let mut histogram = [0usize; 256];
+        for byte in input {
+            histogram[*byte as usize] += 1;
+        }
+        let counts: Vec<(u8, usize)> = (0..=255u8)
+            .filter(|byte| histogram[*byte as usize] > 0)
+            .map(|byte| (byte, histogram[byte as usize]))
+            .collect();
+        let weights: Vec<(u8, u16)> = counts
+            .iter()
+            .map(|(byte, count)| (*byte, *count as u16))
+            .collect();
+        let from_counts = HuffmanU16::with_counts(&counts);
+        let from_weights = HuffmanEncoding::with_weights(&weights);
+        for byte in input {
+            let encoded = from_counts.encode_value(byte).unwrap();
+            assert_eq!(Some(encoded.clone()), from_weights.encode_value(byte));
+            assert_eq!(
+                from_counts.decode_value(encoded.as_bitslice().iter()),
+                Some(*byte)
+            );
+        }
+        // the most frequent byte gets the shortest code
+        let shortest = from_counts.encode_value(&b's').unwrap().len();
+        for (byte, _) in &counts {
+            assert!(from_counts.encode_value(byte).unwrap().len() >= shortest);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_huffman_with_counts_overflow() {
+        HuffmanU16::with_counts(&[(b'a', 1usize << 16)]);
+    }
+
+    #[test]
+    fn test_huffman_with_capacity() {
+        let mut reserved: HuffmanEncoding<u8, u32> = HuffmanEncoding::with_capacity(256);
+        let mut grown: HuffmanEncoding<u8, u32> = HuffmanEncoding::new();
+        for value in (0..=255).chain([b'e', b'e', 7, b'e', 200]) {
+            assert_eq!(
+                reserved.encode_value_mut(&value),
+                grown.encode_value_mut(&value)
+            );
+        }
+        assert!(reserved.root.capacity() >= 256);
+        for value in 0..=255 {
+            assert_eq!(reserved.encode_value(&value), grown.encode_value(&value));
+        }
+    }
+
+    #[test]
+    fn test_code_lengths_round_trip() {
+        // a typical byte alphabet table, where most symbols never occur
+        let mut lengths = [0u8; 256];
+        lengths[b'a' as usize..=b'z' as usize].fill(5);
+        lengths[b'e' as usize] = 3;
+        lengths[b' ' as usize] = 2;
+        lengths[b'.' as usize] = 7;
+        lengths[200..210].fill(12);
+
+        let mut buffer = BitVec::new();
+        encode_code_lengths(&lengths, &mut buffer);
+        // smaller than even nibble packing the raw table
+        assert!(buffer.len() < lengths.len() * 4);
+
+        let decoded = decode_code_lengths(&mut buffer.as_bitslice(), lengths.len()).unwrap();
+        assert_eq!(decoded, lengths);
+    }
+
+    #[test]
+    fn test_code_lengths_invalid() {
+        // a repeat with nothing to repeat
+        let mut buffer = BitVec::new();
+        push_bits(REPEAT_PREVIOUS, SYMBOL_BITS, &mut buffer);
+        push_bits(0, 2, &mut buffer);
+        assert!(decode_code_lengths(&mut buffer.as_bitslice(), 3).is_err());
+
+        // a run longer than the table
+        let mut buffer = BitVec::new();
+        encode_code_lengths(&[0; 20], &mut buffer);
+        assert!(decode_code_lengths(&mut buffer.as_bitslice(), 10).is_err());
+    }
 }
@@ -7,7 +7,10 @@ pub mod arit;
 
 /// A module providing Huffman encoding and decoding implementations.
 mod huffman;
-pub use huffman::HuffmanEncoding;
+pub use huffman::{
+    HuffmanEncoding, HuffmanU16, HuffmanU32, HuffmanU64, MAX_CODE_LENGTH, decode_code_lengths,
+    encode_code_lengths,
+};
 
 /// A module providing Elias encoding algorithms, used for representing
 /// arbitrary integers greater than zero. These algorithms all are based on the
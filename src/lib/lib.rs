@@ -23,7 +23,7 @@
 /// ```
 /// use generic_compression::lz::lz78::{lz78_encode};
 ///
-/// #[derive(Clone, PartialEq)]
+/// #[derive(Clone, PartialEq, Eq, Hash)]
 /// struct MyStruct {
 ///     a: u8,
 ///     b: String,
@@ -101,3 +101,27 @@ pub mod transform;
 /// Compression can be seen as a special case of encoding, where the goal is to
 /// reduce the size of the data.
 pub mod encoding;
+
+/// Module providing helpers for checking that data survives being encoded and
+/// decoded. Useful for testing, and for checking the parameters you chose
+/// work for your data.
+pub mod verify;
+
+/// Module providing cheap statistics of data, for deciding whether it's worth
+/// compressing at all, and the symbol counts coding models are built from.
+pub mod analysis;
+
+/// Module providing the pipelines combining transforms and encoders, with
+/// access to the output of every stage.
+pub mod pipeline;
+
+/// Module providing the file format of the command line utility. A header
+/// identifies the algorithm and its parameters, and is followed by the
+/// serialized output of the encoder, so that the data can be decompressed
+/// without knowing how it was compressed.
+pub mod container;
+
+/// Module providing utilities shared by the tests and benchmarks, such as a
+/// generator of reproducible data. Not part of the stable API.
+#[doc(hidden)]
+pub mod test_util;
@@ -1,3 +1,17 @@
+#[cfg(feature = "trace")]
+use super::trace::{TraceEvent, Traced};
+use super::{Observer, window::SlidingWindow};
+
+use bits_io::bit_types::BitVec;
+
+use std::{
+    collections::HashMap,
+    error, fmt,
+    hash::Hash,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    slice,
+};
+
 /// A struct to represent an LZ77 entry
 /// Traditionally a LZ77 entry is represented as a tuple of (offset, length, next_char)
 /// where offset is the distance to the last occurrence of the string, length is the length of the
@@ -5,6 +19,11 @@
 ///
 /// In this implementation, we use a struct to represent the entry.
 /// This is more Rust-idiomatic and allows us to use the `serde` crate for serialization and deserialization.
+///
+/// An entry without a match has both offset and length equal to zero. Whenever
+/// the length is greater than zero, the offset is at least one, and no greater
+/// than the amount of data decoded so far. The encoders uphold this, and
+/// [lz77_decode] rejects entries that don't.
 pub struct LZ77entry<T> {
     offset: usize,
     length: usize,
@@ -58,6 +77,13 @@ mod lz77_serde {
 /// A function to encode a slice of data using the LZ77 algorithm
 /// The function takes a slice of data, a maximum offset, and a maximum length.
 /// It returns a vector of LZ77 entries.
+/// When several matches share the longest length, the closest one, with the
+/// smallest offset, is chosen.
+/// Always taking the longest match never costs extra entries, even near the
+/// end of the input where matches are capped at `max_length`. Whatever
+/// follows a match is the rest of that same match, so it can be referenced
+/// as well, and the tail of a repeat is covered by references rather than
+/// literals.
 ///
 /// ## Arguments
 ///
@@ -83,51 +109,699 @@ pub fn lz77_encode<T: PartialEq + Clone>(
     max_offset: usize,
     max_length: usize,
 ) -> Vec<LZ77entry<T>> {
+    let mut output = Vec::new();
+    encode_from(input, 0, max_offset, max_length, 1, &mut output, &mut ());
+    output
+}
+
+/// A function to encode a slice of data using the LZ77 algorithm, reporting
+/// every token to a callback. Produces the same entries as [lz77_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+/// - `trace`: The callback receiving an event for every token.
+///
+/// ## Returns
+///
+/// A vector of LZ77 entries.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::{lz77::lz77_encode_traced, trace::TraceEvent};
+/// let mut events = Vec::new();
+/// let encoded = lz77_encode_traced(b"ABABABABA", 4, 4, |event| events.push(event));
+/// assert_eq!(events.len(), encoded.len());
+/// assert_eq!(events[2], TraceEvent::Match { position: 2, offset: 2, length: 4 });
+/// ```
+#[cfg(feature = "trace")]
+pub fn lz77_encode_traced<T: PartialEq + Clone>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+    trace: impl FnMut(TraceEvent),
+) -> Vec<LZ77entry<T>> {
+    let mut output = Vec::new();
+    encode_from(
+        input,
+        0,
+        max_offset,
+        max_length,
+        1,
+        &mut output,
+        &mut Traced(trace),
+    );
+    output
+}
+
+/// A function to encode a slice of data using the LZ77 algorithm, into a
+/// caller provided buffer. The buffer is cleared first, and its capacity is
+/// reused, which avoids allocating when compressing many inputs.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+/// - `output`: The buffer to store the LZ77 entries in.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode, lz77_encode_into};
+/// let mut encoded = Vec::new();
+/// for input in [&b"ABABABABA"[..], b"CDCDCD"] {
+///     lz77_encode_into(input, 4, 4, &mut encoded);
+///     assert_eq!(lz77_decode(&encoded).unwrap(), input);
+/// }
+/// ```
+pub fn lz77_encode_into<T: PartialEq + Clone>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+    output: &mut Vec<LZ77entry<T>>,
+) {
+    output.clear();
+    encode_from(input, 0, max_offset, max_length, 1, output, &mut ());
+}
+
+/// A function to encode a slice of data using the LZ77 algorithm, rejecting
+/// matches shorter than a minimum length, like `MIN_MATCH` in zlib. A short
+/// match can cost more to serialize than the literals it replaces, so such
+/// positions are emitted as literals instead.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+/// - `min_match_length`: The minimum length of matches. Values of zero and
+///   one accept every match, same as [lz77_encode].
+///
+/// ## Returns
+///
+/// A vector of LZ77 entries.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode, lz77_encode_with_min_match};
+/// let input = b"ABCAB_ABCABC";
+/// let encoded = lz77_encode_with_min_match(input, 16, 16, 3);
+/// assert_eq!(encoded.len(), 7);
+/// assert_eq!(lz77_decode(&encoded).unwrap(), input);
+/// ```
+pub fn lz77_encode_with_min_match<T: PartialEq + Clone>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+    min_match_length: usize,
+) -> Vec<LZ77entry<T>> {
+    let mut output = Vec::new();
+    encode_from(
+        input,
+        0,
+        max_offset,
+        max_length,
+        min_match_length,
+        &mut output,
+        &mut (),
+    );
+    output
+}
+
+/// A function to encode a slice of data using the LZ77 algorithm, with a
+/// preset dictionary. The preset acts as if it was data preceding the input,
+/// so matches may refer to it, but it is not itself encoded. The decoder must
+/// be given the same preset.
+///
+/// ## Arguments
+///
+/// - `preset`: A slice of data preloaded into the window.
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+///
+/// ## Returns
+///
+/// A vector of LZ77 entries.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode_with_preset, lz77_encode_with_preset};
+/// let preset = b"hello world";
+/// let input = b"hello there world";
+/// let encoded = lz77_encode_with_preset(preset, input, 32, 16);
+/// assert!(encoded.len() < 8);
+/// let decoded = lz77_decode_with_preset(preset, &encoded).unwrap();
+/// assert_eq!(decoded, input.to_vec());
+/// ```
+pub fn lz77_encode_with_preset<T: PartialEq + Clone>(
+    preset: &[T],
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+) -> Vec<LZ77entry<T>> {
+    let mut window = Vec::with_capacity(preset.len() + input.len());
+    window.extend_from_slice(preset);
+    window.extend_from_slice(input);
+    let mut output = Vec::new();
+    encode_from(
+        &window,
+        preset.len(),
+        max_offset,
+        max_length,
+        1,
+        &mut output,
+        &mut (),
+    );
+    output
+}
+
+/// Encodes the input from position `start` onwards, allowing matches to refer
+/// to everything before it. Matches shorter than `min_length` are replaced
+/// by literals. The entries are appended to `output`, and reported to
+/// `observer` with positions relative to `start`.
+fn encode_from<T: PartialEq + Clone>(
+    input: &[T],
+    start: usize,
+    max_offset: usize,
+    max_length: usize,
+    min_length: usize,
+    output: &mut Vec<LZ77entry<T>>,
+    observer: &mut impl Observer,
+) {
+    let mut i = start; // our position in the input
+
+    while i < input.len() {
+        let mut entry = encode_at(input, i, max_offset, max_length);
+        if entry.length < min_length {
+            // the longest match is too short, so every match is
+            entry = LZ77entry {
+                offset: 0,
+                length: 0,
+                next_char: input[i].clone(),
+            };
+        }
+        if entry.length > 0 {
+            observer.matched(i - start, entry.offset, entry.length);
+        } else {
+            observer.literal(i - start);
+        }
+        i += entry.length + 1;
+        output.push(entry);
+    }
+}
+
+/// A function to encode a slice of data using the LZ77 algorithm, with one
+/// step of lazy matching, as in deflate. Before taking a match, the match at
+/// the next position is looked up too, and if it's longer, the current
+/// element is emitted as a literal instead, so the longer match can be
+/// taken. Every entry carries a literal, so unlike in deflate the deferred
+/// element costs a whole entry. As the greedy parsing of [lz77_encode]
+/// already uses the fewest entries, the output is never smaller than its
+/// output, and it's one entry longer for every deferred element. It's only
+/// useful for comparing the two parsings.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+///
+/// ## Returns
+///
+/// A vector of LZ77 entries.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode, lz77_encode, lz77_encode_lazy};
+/// let input = b"RATABARBARATABARBARAT";
+/// let encoded = lz77_encode_lazy(input, 4, 4);
+/// assert!(encoded.len() >= lz77_encode(input, 4, 4).len());
+/// assert_eq!(lz77_decode(&encoded).unwrap(), input);
+/// ```
+pub fn lz77_encode_lazy<T: PartialEq + Clone>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+) -> Vec<LZ77entry<T>> {
+    let mut output = Vec::new();
+    let mut i = 0;
+    // the match at the next position, if it was already looked up
+    let mut next: Option<LZ77entry<T>> = None;
+
+    while i < input.len() {
+        let entry = next
+            .take()
+            .unwrap_or_else(|| encode_at(input, i, max_offset, max_length));
+        if entry.length > 0 && i + 1 < input.len() {
+            let following = encode_at(input, i + 1, max_offset, max_length);
+            if following.length > entry.length {
+                // a longer match starts right after this element
+                output.push(LZ77entry {
+                    offset: 0,
+                    length: 0,
+                    next_char: input[i].clone(),
+                });
+                next = Some(following);
+                i += 1;
+                continue;
+            }
+        }
+        i += entry.length + 1;
+        output.push(entry);
+    }
+
+    output
+}
+
+/// Finds the longest match for position `i` of the input, and returns the
+/// entry encoding it.
+fn encode_at<T: PartialEq + Clone>(
+    input: &[T],
+    i: usize,
+    max_offset: usize,
+    max_length: usize,
+) -> LZ77entry<T> {
     /// A struct to represent a match in the input data
     struct Match {
         pub offset: usize,
         pub length: usize,
     }
 
+    let mut m: Option<Match> = None; // the longest match
+
+    // Find the longest match
+    for j in (i.saturating_sub(max_offset)..i).rev() {
+        let mut k = 0;
+        // as long as we are within bounds, and the characters match
+        while k < max_length && i + k + 1 < input.len() && input[j + k] == input[i + k] {
+            k += 1; // increment the length of the match
+        }
+        if k > m.as_ref().map_or(0, |m| m.length) {
+            // update the longest match
+            m = Some(Match {
+                offset: i - j,
+                length: k,
+            });
+        }
+    }
+
+    // If no match found, just output the next character
+    if let Some(m) = m {
+        LZ77entry {
+            offset: m.offset,
+            length: m.length,
+            next_char: input[i + m.length].clone(),
+        }
+    } else {
+        // we found nothing, so we just output the next character
+        LZ77entry {
+            offset: 0,
+            length: 0,
+            next_char: input[i].clone(),
+        }
+    }
+}
+
+/// An incremental LZ77 encoder. Data can be fed to it in chunks, and entries
+/// are produced as soon as enough data is buffered to be sure they are the
+/// same entries [lz77_encode] would produce for the whole input. Only the
+/// window and the lookahead are kept in memory.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{LZ77Encoder, lz77_decode, lz77_encode};
+/// let mut encoder = LZ77Encoder::new(4, 4);
+/// let mut encoded = Vec::new();
+/// encoder.push(b"ABABA", &mut encoded);
+/// encoder.push(b"BABA", &mut encoded);
+/// encoder.finish(&mut encoded);
+/// assert_eq!(lz77_decode(&encoded).unwrap(), b"ABABABABA");
+/// ```
+pub struct LZ77Encoder<T> {
+    buffer: Vec<T>,
+    position: usize,
+    max_offset: usize,
+    max_length: usize,
+}
+
+impl<T: PartialEq + Clone> LZ77Encoder<T> {
+    /// Creates a new incremental LZ77 encoder
+    ///
+    /// ## Arguments
+    ///
+    /// - `max_offset`: The maximum offset to search for matches.
+    /// - `max_length`: The maximum length of matches.
+    pub fn new(max_offset: usize, max_length: usize) -> Self {
+        LZ77Encoder {
+            buffer: Vec::new(),
+            position: 0,
+            max_offset,
+            max_length,
+        }
+    }
+
+    /// Feeds more data into the encoder
+    ///
+    /// ## Arguments
+    ///
+    /// - `input`: The next chunk of data to be encoded.
+    /// - `out`: The output buffer to store finished entries in.
+    pub fn push(&mut self, input: &[T], out: &mut Vec<LZ77entry<T>>) {
+        self.buffer.extend_from_slice(input);
+        // a match can't grow any further once the lookahead is full
+        while self.position + self.max_length < self.buffer.len() {
+            self.encode_next(out);
+        }
+        // drop data that fell out of the window, in bulk
+        if self.position > self.max_offset * 2 {
+            let stale = self.position - self.max_offset;
+            self.buffer.drain(..stale);
+            self.position -= stale;
+        }
+    }
+
+    /// Encodes the rest of the buffered data
+    ///
+    /// ## Arguments
+    ///
+    /// - `out`: The output buffer to store the remaining entries in.
+    pub fn finish(mut self, out: &mut Vec<LZ77entry<T>>) {
+        while self.position < self.buffer.len() {
+            self.encode_next(out);
+        }
+    }
+
+    fn encode_next(&mut self, out: &mut Vec<LZ77entry<T>>) {
+        let entry = encode_at(
+            &self.buffer,
+            self.position,
+            self.max_offset,
+            self.max_length,
+        );
+        self.position += entry.length + 1;
+        out.push(entry);
+    }
+}
+
+/// An incremental LZ77 decoder. Entries can be fed to it in chunks, and
+/// only the last `max_offset` decoded elements are kept in memory, so
+/// arbitrarily long streams can be decoded in bounded memory. The entries
+/// are validated the same way as in [lz77_decode].
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{LZ77Decoder, lz77_encode};
+/// let encoded = lz77_encode(b"ABABABABA", 4, 4);
+/// let mut decoder = LZ77Decoder::new(4);
+/// let mut decoded = Vec::new();
+/// for chunk in encoded.chunks(1) {
+///     decoder.push(chunk, &mut decoded).unwrap();
+/// }
+/// assert_eq!(decoded, b"ABABABABA");
+/// ```
+pub struct LZ77Decoder<T> {
+    window: SlidingWindow<T>,
+    /// The number of entries decoded so far, for reporting invalid ones
+    entries: usize,
+}
+
+impl<T: Clone> LZ77Decoder<T> {
+    /// Creates a new incremental LZ77 decoder
+    ///
+    /// ## Arguments
+    ///
+    /// - `max_offset`: The maximum offset the entries were encoded with.
+    pub fn new(max_offset: usize) -> Self {
+        LZ77Decoder {
+            window: SlidingWindow::new(max_offset),
+            entries: 0,
+        }
+    }
+
+    /// Decodes more entries
+    ///
+    /// ## Arguments
+    ///
+    /// - `input`: The next chunk of entries to be decoded.
+    /// - `out`: The output buffer to append the decoded data to.
+    ///
+    /// ## Returns
+    ///
+    /// An error describing the first invalid entry, counting from the start
+    /// of the stream. The entries before it are still decoded.
+    pub fn push(&mut self, input: &[LZ77entry<T>], out: &mut Vec<T>) -> Result<(), DecodeError> {
+        for entry in input {
+            check_entry(self.entries, entry, self.window.len())?;
+            for _ in 0..entry.length {
+                // reading and pushing one at a time handles overlapping matches
+                let value = self
+                    .window
+                    .get(entry.offset)
+                    .expect("The offset was checked")
+                    .clone();
+                self.window.push(value.clone());
+                out.push(value);
+            }
+            self.window.push(entry.next_char.clone());
+            out.push(entry.next_char.clone());
+            self.entries += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The size of the chunks [lz77_encode_stream] reads its input in.
+const STREAM_CHUNK_SIZE: usize = 1 << 16;
+
+/// The number of bytes an offset or a length up to `max` is stored in by
+/// [lz77_encode_stream].
+fn stream_width(max: usize) -> usize {
+    ((usize::BITS - max.leading_zeros()).div_ceil(8) as usize).max(1)
+}
+
+fn write_stream_entry<W: Write>(
+    entry: &LZ77entry<u8>,
+    offset_width: usize,
+    length_width: usize,
+    out: &mut W,
+) -> io::Result<()> {
+    out.write_all(&entry.offset.to_le_bytes()[..offset_width])?;
+    out.write_all(&entry.length.to_le_bytes()[..length_width])?;
+    out.write_all(&[entry.next_char])
+}
+
+fn read_stream_usize(bytes: &[u8]) -> usize {
+    let mut buffer = [0; size_of::<usize>()];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    usize::from_le_bytes(buffer)
+}
+
+/// A function to encode a stream of bytes using the LZ77 algorithm, writing
+/// the entries as they are produced. Only the window and the lookahead are
+/// kept in memory, so inputs larger than the available memory can be
+/// encoded. The entries are the same [lz77_encode] would produce, each
+/// written as its offset and length, in as few little endian bytes as
+/// `window_size` and `lookahead` fit in, followed by the byte.
+///
+/// ## Arguments
+///
+/// - `input`: The stream of bytes to be encoded.
+/// - `out`: The stream to write the entries to.
+/// - `window_size`: The maximum offset to search for matches.
+/// - `lookahead`: The maximum length of matches.
+///
+/// ## Returns
+///
+/// An error if reading or writing failed.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode_stream, lz77_encode_stream};
+/// let mut encoded = Vec::new();
+/// lz77_encode_stream(&b"ABABABABA"[..], &mut encoded, 4, 4).unwrap();
+/// let mut decoded = Vec::new();
+/// lz77_decode_stream(encoded.as_slice(), &mut decoded, 4, 4).unwrap();
+/// assert_eq!(decoded, b"ABABABABA");
+/// ```
+pub fn lz77_encode_stream<R: Read, W: Write>(
+    mut input: R,
+    out: W,
+    window_size: usize,
+    lookahead: usize,
+) -> io::Result<()> {
+    let offset_width = stream_width(window_size);
+    let length_width = stream_width(lookahead);
+    let mut out = BufWriter::new(out);
+    let mut encoder = LZ77Encoder::new(window_size, lookahead);
+    let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+    let mut entries = Vec::new();
+    loop {
+        let read = match input.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        encoder.push(&chunk[..read], &mut entries);
+        for entry in entries.drain(..) {
+            write_stream_entry(&entry, offset_width, length_width, &mut out)?;
+        }
+    }
+    encoder.finish(&mut entries);
+    for entry in entries.drain(..) {
+        write_stream_entry(&entry, offset_width, length_width, &mut out)?;
+    }
+    out.flush()
+}
+
+/// A function to decode a stream of entries written by
+/// [lz77_encode_stream], writing the data as it is decoded. Only the last
+/// `window_size` bytes are kept in memory. The entries are validated the
+/// same way as in [lz77_decode].
+///
+/// ## Arguments
+///
+/// - `input`: The stream of entries to be decoded.
+/// - `out`: The stream to write the decoded data to.
+/// - `window_size`: The maximum offset used when encoding.
+/// - `lookahead`: The maximum length of matches used when encoding.
+///
+/// ## Returns
+///
+/// An error if reading or writing failed, with the kind
+/// [io::ErrorKind::UnexpectedEof] if the stream ends in the middle of an
+/// entry, or [io::ErrorKind::InvalidData] if an entry is invalid.
+pub fn lz77_decode_stream<R: Read, W: Write>(
+    input: R,
+    out: W,
+    window_size: usize,
+    lookahead: usize,
+) -> io::Result<()> {
+    let offset_width = stream_width(window_size);
+    let length_width = stream_width(lookahead);
+    let mut input = BufReader::new(input);
+    let mut out = BufWriter::new(out);
+    let mut decoder = LZ77Decoder::new(window_size);
+    let mut buffer = vec![0; offset_width + length_width + 1];
+    let mut decoded = Vec::new();
+    // the stream may only end between entries
+    while !input.fill_buf()?.is_empty() {
+        input.read_exact(&mut buffer)?;
+        let entry = LZ77entry {
+            offset: read_stream_usize(&buffer[..offset_width]),
+            length: read_stream_usize(&buffer[offset_width..offset_width + length_width]),
+            next_char: buffer[offset_width + length_width],
+        };
+        decoder
+            .push(slice::from_ref(&entry), &mut decoded)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        out.write_all(&decoded)?;
+        decoded.clear();
+    }
+    out.flush()
+}
+
+/// The number of elements hashed to find match candidates in [lz77_encode_fast].
+/// Shorter matches are not searched for.
+const HASH_LENGTH: usize = 3;
+
+/// A function to encode a slice of data using the LZ77 algorithm, using hash
+/// chains to find matches. Instead of comparing against every position in the
+/// window, only positions starting with the same few elements are compared,
+/// which is much faster for large windows. Matches shorter than three elements
+/// are never found, so the output may differ slightly from [lz77_encode].
+/// Same as there, ties are broken in favour of the smallest offset.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+/// - `max_chain`: The maximum number of candidate positions examined per
+///   position. Smaller values trade compression ratio for speed.
+///
+/// ## Returns
+///
+/// A vector of LZ77 entries.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode, lz77_encode_fast};
+/// let input = b"ABCABCABCABCABC";
+/// let encoded = lz77_encode_fast(input, 8, 8, 16);
+/// assert!(encoded.len() < input.len());
+/// assert_eq!(lz77_decode(&encoded).unwrap(), input.to_vec());
+/// ```
+pub fn lz77_encode_fast<T: Clone + Hash + Eq>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+    max_chain: usize,
+) -> Vec<LZ77entry<T>> {
+    // the most recent position starting with a given key
+    let mut head: HashMap<&[T], usize> = HashMap::new();
+    // the previous position starting with the same key, for each position
+    // within reach of the window
+    let mut prev: SlidingWindow<Option<usize>> = SlidingWindow::new(max_offset);
+
     let mut output = Vec::new();
     let mut i = 0; // our position in the input
 
     while i < input.len() {
-        let mut m: Option<Match> = None; // the longest match
+        let mut best_offset = 0;
+        let mut best_length = 0;
 
-        // Find the longest match
-        for j in (i.saturating_sub(max_offset)..i).rev() {
+        let mut candidate = if i + HASH_LENGTH <= input.len() {
+            head.get(&input[i..i + HASH_LENGTH]).copied()
+        } else {
+            None
+        };
+        let mut chain = 0;
+        // walk the chain, from the closest position to the furthest
+        while let Some(j) = candidate {
+            if chain >= max_chain || i - j > max_offset {
+                break;
+            }
             let mut k = 0;
             // as long as we are within bounds, and the characters match
             while k < max_length && i + k + 1 < input.len() && input[j + k] == input[i + k] {
-                k += 1; // increment the length of the match
+                k += 1;
             }
-            if k > m.as_ref().map_or(0, |m| m.length) {
-                // update the longest match
-                m = Some(Match {
-                    offset: i - j,
-                    length: k,
-                });
+            if k > best_length {
+                best_offset = i - j;
+                best_length = k;
             }
+            // the window ends at the position right before i
+            candidate = prev.get(i - j).copied().flatten();
+            chain += 1;
         }
 
-        // If no match found, just output the next character
-        if let Some(m) = m {
-            output.push(LZ77entry {
-                offset: m.offset,
-                length: m.length,
-                next_char: input[i + m.length].clone(),
+        output.push(LZ77entry {
+            offset: best_offset,
+            length: best_length,
+            next_char: input[i + best_length].clone(),
+        });
+        // every covered position is indexed, so later matches can refer to them
+        for pos in i..=i + best_length {
+            prev.push(if pos + HASH_LENGTH <= input.len() {
+                head.insert(&input[pos..pos + HASH_LENGTH], pos)
+            } else {
+                None
             });
-            i += m.length + 1;
-        } else {
-            // we found nothing, so we just output the next character
-            output.push(LZ77entry {
-                offset: 0,
-                length: 0,
-                next_char: input[i].clone(),
-            });
-            i += 1;
         }
+        i += best_length + 1;
     }
 
     output
@@ -135,6 +809,8 @@ pub fn lz77_encode<T: PartialEq + Clone>(
 
 /// A function to decode a vector of LZ77 entries
 /// The function takes a vector of LZ77 entries and returns a vector of data.
+/// The entries are validated instead of trusted, so it is safe to decode
+/// entries from an untrusted source.
 ///
 /// ## Arguments
 ///
@@ -142,46 +818,429 @@ pub fn lz77_encode<T: PartialEq + Clone>(
 ///
 /// ## Returns
 ///
-/// A vector of data.
+/// A vector of data, or an error describing the first invalid entry.
 ///
 /// ## Example
 ///
 /// ```
-/// use generic_compression::lz::lz77::{lz77_decode, lz77_encode};
+/// use generic_compression::lz::lz77::{DecodeError, LZ77entry, lz77_decode, lz77_encode};
 /// let input = b"ABABABABA";
 /// let encoded = lz77_encode(input, 4, 4);
 /// assert!(encoded.len() < input.len());
-/// let decoded = lz77_decode(&encoded);
+/// let decoded = lz77_decode(&encoded).unwrap();
 /// assert_eq!(input.to_vec(), decoded);
+///
+/// let invalid = vec![LZ77entry::from((0, 0, b'a')), LZ77entry::from((0, 2, b'b'))];
+/// assert_eq!(lz77_decode(&invalid), Err(DecodeError::EmptyOffsetWithLength { entry: 1 }));
 /// ```
-pub fn lz77_decode<T: Clone>(input: &[LZ77entry<T>]) -> Vec<T> {
+pub fn lz77_decode<T: Clone>(input: &[LZ77entry<T>]) -> Result<Vec<T>, DecodeError> {
     let mut output: Vec<T> = Vec::new();
 
-    for entry in input {
-        // foreach entry
-        let start = output.len() - entry.offset;
-        for i in 0..entry.length {
-            // copy the match
-            output.push(output[start + i].clone());
+    for (i, entry) in input.iter().enumerate() {
+        check_entry(i, entry, output.len())?;
+        copy_match(&mut output, entry.offset, entry.length);
+        output.push(entry.next_char.clone());
+    }
+
+    Ok(output)
+}
+
+/// Error returned when decoding an invalid LZ77 stream.
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// A match of nonzero length with an offset of zero, which would refer to
+    /// data that hasn't been decoded yet.
+    EmptyOffsetWithLength {
+        /// The index of the offending entry
+        entry: usize,
+    },
+    /// A match reaching further back than the start of the data.
+    OffsetOutOfRange {
+        /// The index of the offending entry
+        entry: usize,
+        /// The offset of the match
+        offset: usize,
+        /// The amount of data decoded before the entry
+        available: usize,
+    },
+    /// The decoded data doesn't fit in the output buffer.
+    OutputTooSmall {
+        /// The index of the entry that didn't fit
+        entry: usize,
+        /// The size of the output buffer
+        capacity: usize,
+    },
+    /// The streams of [LZ77Streams] don't line up with the flags.
+    StreamMismatch {
+        /// The index of the entry a stream ran out at, or the number of
+        /// entries if a stream has values left over
+        entry: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::EmptyOffsetWithLength { entry } => {
+                write!(f, "Entry {} has a match with an offset of zero", entry)
+            }
+            DecodeError::OffsetOutOfRange {
+                entry,
+                offset,
+                available,
+            } => write!(
+                f,
+                "Entry {} has an offset of {}, but only {} elements were decoded",
+                entry, offset, available
+            ),
+            DecodeError::OutputTooSmall { entry, capacity } => write!(
+                f,
+                "Entry {} doesn't fit in an output of {} elements",
+                entry, capacity
+            ),
+            DecodeError::StreamMismatch { entry } => {
+                write!(f, "The streams don't line up at entry {}", entry)
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Checks that the match of the entry at index `i` refers only to the
+/// `available` elements decoded before it.
+fn check_entry<T>(i: usize, entry: &LZ77entry<T>, available: usize) -> Result<(), DecodeError> {
+    if entry.length == 0 {
+        Ok(())
+    } else if entry.offset == 0 {
+        Err(DecodeError::EmptyOffsetWithLength { entry: i })
+    } else if entry.offset > available {
+        Err(DecodeError::OffsetOutOfRange {
+            entry: i,
+            offset: entry.offset,
+            available,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// A function to decode a vector of LZ77 entries into a caller provided
+/// buffer, without allocating. Useful for fixed size frames, where the length
+/// of the decoded data is known upfront. The entries are validated the same
+/// way as in [lz77_decode].
+///
+/// ## Arguments
+///
+/// - `input`: A vector of LZ77 entries to be decoded.
+/// - `out`: The buffer to decode the data into.
+///
+/// ## Returns
+///
+/// The number of elements written to the start of `out`, or an error if the
+/// entries are invalid or the data doesn't fit.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode_into, lz77_encode};
+/// let input = b"ABABABABA";
+/// let encoded = lz77_encode(input, 4, 4);
+/// let mut out = [0u8; 16];
+/// let written = lz77_decode_into(&encoded, &mut out).unwrap();
+/// assert_eq!(&out[..written], input);
+/// assert!(lz77_decode_into(&encoded, &mut out[..8]).is_err());
+/// ```
+pub fn lz77_decode_into<T: Clone>(
+    input: &[LZ77entry<T>],
+    out: &mut [T],
+) -> Result<usize, DecodeError> {
+    let mut written = 0;
+
+    for (i, entry) in input.iter().enumerate() {
+        check_entry(i, entry, written)?;
+        // a huge length from a corrupted entry can't fit either
+        if written
+            .checked_add(entry.length)
+            .is_none_or(|end| end >= out.len())
+        {
+            return Err(DecodeError::OutputTooSmall {
+                entry: i,
+                capacity: out.len(),
+            });
+        }
+        let start = written - entry.offset;
+        for j in 0..entry.length {
+            // one by one, as the match may overlap itself
+            out[written + j] = out[start + j].clone();
+        }
+        written += entry.length;
+        out[written] = entry.next_char.clone();
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// A function to decode only the beginning of a vector of LZ77 entries.
+/// Decoding stops as soon as `n` elements are produced, so the remaining
+/// entries are never expanded.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of LZ77 entries to be decoded.
+/// - `n`: The number of elements to decode.
+///
+/// ## Returns
+///
+/// The first `n` elements of the data, or all of it if it's shorter, or an
+/// error describing the first invalid entry decoded.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode_prefix, lz77_encode};
+/// let input = b"ABABABABA";
+/// let encoded = lz77_encode(input, 4, 4);
+/// assert_eq!(lz77_decode_prefix(&encoded, 3).unwrap(), b"ABA");
+/// ```
+pub fn lz77_decode_prefix<T: Clone>(
+    input: &[LZ77entry<T>],
+    n: usize,
+) -> Result<Vec<T>, DecodeError> {
+    let mut output: Vec<T> = Vec::new();
+
+    for (i, entry) in input.iter().enumerate() {
+        if output.len() >= n {
+            break;
         }
+        check_entry(i, entry, output.len())?;
+        copy_match(&mut output, entry.offset, entry.length);
         output.push(entry.next_char.clone());
     }
 
-    output
+    output.truncate(n);
+    Ok(output)
+}
+
+/// A function to decode a vector of LZ77 entries, taking ownership of them.
+/// Unlike [lz77_decode], the next characters are moved into the output
+/// instead of being cloned. Matches still have to be cloned.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of LZ77 entries to be decoded.
+///
+/// ## Returns
+///
+/// A vector of data, or an error describing the first invalid entry.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode_owned, lz77_encode};
+/// let input = b"ABABABABA";
+/// let encoded = lz77_encode(input, 4, 4);
+/// let decoded = lz77_decode_owned(encoded).unwrap();
+/// assert_eq!(input.to_vec(), decoded);
+/// ```
+pub fn lz77_decode_owned<T: Clone>(input: Vec<LZ77entry<T>>) -> Result<Vec<T>, DecodeError> {
+    let mut output: Vec<T> = Vec::new();
+
+    for (i, entry) in input.into_iter().enumerate() {
+        check_entry(i, &entry, output.len())?;
+        copy_match(&mut output, entry.offset, entry.length);
+        output.push(entry.next_char);
+    }
+
+    Ok(output)
+}
+
+/// A function to decode a vector of LZ77 entries, encoded with a preset
+/// dictionary by [lz77_encode_with_preset].
+///
+/// ## Arguments
+///
+/// - `preset`: The same preset used while encoding.
+/// - `input`: A vector of LZ77 entries to be decoded.
+///
+/// ## Returns
+///
+/// A vector of data, not including the preset, or an error describing the
+/// first invalid entry. Matches may reach back into the preset.
+pub fn lz77_decode_with_preset<T: Clone>(
+    preset: &[T],
+    input: &[LZ77entry<T>],
+) -> Result<Vec<T>, DecodeError> {
+    let mut output: Vec<T> = preset.to_vec();
+
+    for (i, entry) in input.iter().enumerate() {
+        check_entry(i, entry, output.len())?;
+        copy_match(&mut output, entry.offset, entry.length);
+        output.push(entry.next_char.clone());
+    }
+
+    Ok(output.split_off(preset.len()))
+}
+
+/// LZ77 entries split into a separate stream for each kind of value, so that
+/// every stream can be entropy coded with its own model. Every entry has a
+/// literal, while only matches have an offset and a length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LZ77Streams<T> {
+    /// The next character of every entry
+    pub literals: Vec<T>,
+    /// The offset of every match
+    pub offsets: Vec<usize>,
+    /// The length of every match
+    pub lengths: Vec<usize>,
+    /// Whether each entry is a match
+    pub flags: BitVec,
+}
+
+impl<T> From<Vec<LZ77entry<T>>> for LZ77Streams<T> {
+    fn from(entries: Vec<LZ77entry<T>>) -> Self {
+        let mut streams = LZ77Streams {
+            literals: Vec::with_capacity(entries.len()),
+            offsets: Vec::new(),
+            lengths: Vec::new(),
+            flags: BitVec::with_capacity(entries.len()),
+        };
+        for entry in entries {
+            let is_match = entry.length > 0;
+            if is_match {
+                streams.offsets.push(entry.offset);
+                streams.lengths.push(entry.length);
+            }
+            streams.flags.push(is_match);
+            streams.literals.push(entry.next_char);
+        }
+        streams
+    }
+}
+
+impl<T> LZ77Streams<T> {
+    /// Reassembles the entries from the streams.
+    ///
+    /// ## Returns
+    ///
+    /// The LZ77 entries, or an error if the streams don't line up.
+    pub fn into_entries(self) -> Result<Vec<LZ77entry<T>>, DecodeError> {
+        let mut literals = self.literals.into_iter();
+        let mut matches = self.offsets.into_iter().zip(self.lengths);
+        let mut entries = Vec::with_capacity(self.flags.len());
+        for (i, is_match) in self.flags.iter().enumerate() {
+            let (offset, length) = if *is_match {
+                matches
+                    .next()
+                    .ok_or(DecodeError::StreamMismatch { entry: i })?
+            } else {
+                (0, 0)
+            };
+            let next_char = literals
+                .next()
+                .ok_or(DecodeError::StreamMismatch { entry: i })?;
+            entries.push(LZ77entry {
+                offset,
+                length,
+                next_char,
+            });
+        }
+        if literals.next().is_some() || matches.next().is_some() {
+            return Err(DecodeError::StreamMismatch {
+                entry: entries.len(),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// A function to encode a slice of data using the LZ77 algorithm, splitting
+/// the entries into separate streams of literals, offsets and lengths.
+/// Produces the same entries as [lz77_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of a match.
+///
+/// ## Returns
+///
+/// The streams of the encoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz77::{lz77_decode_split, lz77_encode_split};
+/// let input = b"ABABABABA";
+/// let streams = lz77_encode_split(input, 4, 4);
+/// assert_eq!(streams.literals.len(), streams.flags.len());
+/// assert!(streams.offsets.iter().all(|offset| *offset == 2));
+/// assert_eq!(lz77_decode_split(streams).unwrap(), input);
+/// ```
+pub fn lz77_encode_split<T: PartialEq + Clone>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+) -> LZ77Streams<T> {
+    LZ77Streams::from(lz77_encode(input, max_offset, max_length))
+}
+
+/// A function to decode data encoded by [lz77_encode_split]. The entries
+/// are validated the same way as in [lz77_decode].
+///
+/// ## Arguments
+///
+/// - `input`: The streams of the encoded data.
+///
+/// ## Returns
+///
+/// A vector of data, or an error if the streams don't line up or an entry
+/// is invalid.
+pub fn lz77_decode_split<T: Clone>(input: LZ77Streams<T>) -> Result<Vec<T>, DecodeError> {
+    lz77_decode(&input.into_entries()?)
+}
+
+/// Copies a match of `length` elements, starting `offset` elements back, to
+/// the end of the output.
+fn copy_match<T: Clone>(output: &mut Vec<T>, offset: usize, length: usize) {
+    // malformed entries, which the decoders check for and report as errors
+    debug_assert!(
+        offset <= output.len(),
+        "offset {} reaches before the start of the output",
+        offset
+    );
+    debug_assert!(
+        offset >= 1 || length == 0,
+        "match of length {} with an offset of zero",
+        length
+    );
+    let start = output.len() - offset;
+    if offset >= length {
+        // the match doesn't overlap the output we are producing
+        output.extend_from_within(start..start + length);
+    } else {
+        for i in 0..length {
+            // copy the match one by one, as it overlaps itself
+            output.push(output[start + i].clone());
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{assert_round_trip, gen_data};
 
     #[test]
     fn test_lz77() {
         let input = b"RATABARBARATABARBARAT";
 
         let encoded = lz77_encode(input, 4, 4);
-        let decoded = lz77_decode(&encoded);
+        let decoded = lz77_decode(&encoded).unwrap();
 
-        assert_eq!(input.to_vec(), decoded);
+        assert_round_trip(&decoded, input);
     }
 
     #[test]
@@ -189,9 +1248,9 @@ mod tests {
         let input: Vec<u8> = vec![];
 
         let encoded = lz77_encode(&input, 4, 4);
-        let decoded = lz77_decode(&encoded);
+        let decoded = lz77_decode(&encoded).unwrap();
 
-        assert_eq!(input, decoded);
+        assert_round_trip(&decoded, &input);
     }
 
     #[test]
@@ -208,7 +1267,486 @@ mod tests {
                 next_char: 2,
             },
         ];
-        let decoded = lz77_decode(&input);
+        let decoded = lz77_decode(&input).unwrap();
         assert_eq!(decoded, vec![1, 1, 1, 1, 1, 1, 2]);
     }
+
+    #[test]
+    fn test_overlapping_and_disjoint_decode() {
+        let input: Vec<LZ77entry<u8>> = vec![
+            (0, 0, b'a').into(),
+            (0, 0, b'b').into(),
+            (0, 0, b'c').into(),
+            // disjoint, offset == length
+            (3, 3, b'd').into(),
+            // disjoint, offset > length
+            (7, 2, b'e').into(),
+            // overlapping, offset < length
+            (2, 5, b'f').into(),
+        ];
+        let decoded = lz77_decode(&input).unwrap();
+        assert_eq!(decoded, b"abcabcdabebebebf");
+    }
+
+    #[test]
+    fn test_lz77_decode_owned() {
+        let input = b"RATABARBARATABARBARAT";
+        let encoded = lz77_encode(input, 4, 4);
+        let decoded = lz77_decode(&encoded).unwrap();
+        assert_round_trip(&lz77_decode_owned(encoded).unwrap(), &decoded);
+
+        // works for types that are expensive to clone too
+        let input: Vec<String> = ["a", "b", "a", "b", "a", "c"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let encoded = lz77_encode(&input, 4, 4);
+        assert_round_trip(&lz77_decode_owned(encoded).unwrap(), &input);
+    }
+
+    #[test]
+    fn test_lz77_fast() {
+        let input = b"RATABARBARATABARBARAT";
+
+        let encoded = lz77_encode_fast(input, 16, 16, 8);
+        assert!(encoded.len() < input.len());
+        let decoded = lz77_decode(&encoded).unwrap();
+
+        assert_round_trip(&decoded, input);
+    }
+
+    #[test]
+    fn test_lz77_min_match() {
+        let input = gen_data(8, 5000, 0.5);
+        let greedy = lz77_encode(&input, 255, 32);
+        assert!(
+            greedy
+                .iter()
+                .any(|entry| entry.length > 0 && entry.length < 3)
+        );
+        let encoded = lz77_encode_with_min_match(&input, 255, 32, 3);
+        assert!(
+            encoded
+                .iter()
+                .all(|entry| entry.length == 0 || entry.length >= 3)
+        );
+        // positions without a long enough match are plain literals
+        assert!(
+            encoded
+                .iter()
+                .filter(|entry| entry.length == 0)
+                .all(|entry| entry.offset == 0)
+        );
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), &input);
+
+        let all = lz77_encode_with_min_match(&input, 255, 32, 1);
+        assert_eq!(all.len(), greedy.len());
+    }
+
+    #[test]
+    fn test_lz77_lazy() {
+        // no longer match follows any match, so the parsing is the greedy one
+        let input = b"RATABARBARATABARBARAT";
+        let encoded = lz77_encode_lazy(input, 4, 4);
+        let entries = |encoded: &[LZ77entry<u8>]| {
+            encoded
+                .iter()
+                .map(|entry| (entry.offset, entry.length, entry.next_char))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(entries(&encoded), entries(&lz77_encode(input, 4, 4)));
+        assert_eq!(encoded.len(), 11);
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+
+        // "abc" is passed over as a literal, for "bcdefg" right after it,
+        // which costs an entry more than the greedy parsing
+        let input = b"abcX-bcdefgh-abcdefgh";
+        let encoded = lz77_encode_lazy(input, 32, 32);
+        assert_eq!((encoded[11].length, encoded[11].next_char), (0, b'a'));
+        assert_eq!((encoded[12].offset, encoded[12].length), (9, 6));
+        assert_eq!(encoded.len(), 13);
+        assert_eq!(lz77_encode(input, 32, 32).len(), 12);
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+
+        let input = gen_data(6, 5000, 0.7);
+        assert_round_trip(
+            &lz77_decode(&lz77_encode_lazy(&input, 255, 32)).unwrap(),
+            &input,
+        );
+    }
+
+    #[test]
+    fn test_lz77_fast_matches_naive() {
+        // the input of test_lz77, with the same small window and lookahead
+        let input = b"RATABARBARATABARBARAT";
+        let encoded = lz77_encode_fast(input, 4, 4, 8);
+        assert!(
+            encoded
+                .iter()
+                .all(|entry| entry.offset <= 4 && entry.length <= 4)
+        );
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+
+        // the overlapping run of test_nasty_decode
+        let input = [1, 1, 1, 1, 1, 1, 2];
+        let encoded = lz77_encode_fast(&input, 16, 16, 8);
+        let naive = lz77_encode(&input, 16, 16);
+        assert_eq!(encoded.len(), naive.len());
+        for (fast, naive) in encoded.iter().zip(&naive) {
+            assert_eq!(
+                (fast.offset, fast.length, fast.next_char),
+                (naive.offset, naive.length, naive.next_char)
+            );
+        }
+        assert_eq!(encoded[1].offset, 1);
+        assert_eq!(encoded[1].length, 5);
+    }
+
+    #[test]
+    fn test_lz77_fast_references_inside_match() {
+        let input = b"ABCDEFGHABCDEFGH!DEFG!";
+
+        let encoded = lz77_encode_fast(input, 32, 32, 8);
+        // the second copy is a single match, covering positions 8 to 16
+        assert_eq!(encoded[8].offset, 8);
+        assert_eq!(encoded[8].length, 8);
+        // DEFG refers to the middle of that match, rather than the first copy
+        assert_eq!(encoded[9].offset, 6);
+        assert_eq!(encoded[9].length, 4);
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn test_lz77_benchmarked_sizes() {
+        // the sizes, parameters and input of the lz77 benchmark
+        for size in [16 * 1024, 64 * 1024] {
+            let input = gen_data(12, size, 0.7);
+            assert_round_trip(
+                &lz77_decode(&lz77_encode(&input, 1024, 64)).unwrap(),
+                &input,
+            );
+            assert_round_trip(
+                &lz77_decode(&lz77_encode_fast(&input, 1024, 64, 16)).unwrap(),
+                &input,
+            );
+        }
+    }
+
+    #[test]
+    fn test_lz77_fast_max_chain() {
+        let input = b"abcdabcxabcyabczabcdabcxabcyabczabcdabcxabcyabcz".repeat(4);
+
+        let mut previous_len = usize::MAX;
+        for max_chain in [0, 1, 2, 4, 8, 64] {
+            let encoded = lz77_encode_fast(&input, 255, 255, max_chain);
+            assert_round_trip(&lz77_decode(&encoded).unwrap(), &input);
+            // walking more of the chain never makes the output worse
+            assert!(encoded.len() <= previous_len);
+            previous_len = encoded.len();
+        }
+        // without a chain to walk, there are no matches at all
+        assert_eq!(lz77_encode_fast(&input, 255, 255, 0).len(), input.len());
+    }
+
+    #[test]
+    fn test_lz77_preset() {
+        let preset = b"RATABARBAR";
+        let input = b"RATABARBARAT";
+
+        let encoded = lz77_encode_with_preset(preset, input, 16, 16);
+        assert!(encoded.len() < lz77_encode(input, 16, 16).len());
+        assert_round_trip(&lz77_decode_with_preset(preset, &encoded).unwrap(), input);
+
+        // an empty preset is the same as no preset
+        let encoded = lz77_encode_with_preset(&[], input, 16, 16);
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn test_lz77_preset_references() {
+        let preset = b"hello world";
+        let input = b"hello there";
+        let encoded = lz77_encode_with_preset(preset, input, 32, 16);
+        // the first bytes reach back into the preset, past the start of the input
+        let first = &encoded[0];
+        assert_eq!(first.length, 6);
+        assert_eq!(first.offset, preset.len());
+        assert_eq!(first.next_char, b't');
+        assert_round_trip(&lz77_decode_with_preset(preset, &encoded).unwrap(), input);
+
+        // without the preset, they are literals
+        assert_eq!(lz77_encode(input, 32, 16)[0].length, 0);
+    }
+
+    #[test]
+    fn test_lz77_encoder_chunks() {
+        let input = b"RATABARBARATABARBARAT".repeat(8);
+        let expected: Vec<LZ77tuple<u8>> = lz77_encode(&input, 6, 5)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        for chunk_size in [1, 2, 3, 7, 64, input.len()] {
+            let mut encoder = LZ77Encoder::new(6, 5);
+            let mut encoded = Vec::new();
+            for chunk in input.chunks(chunk_size) {
+                encoder.push(chunk, &mut encoded);
+            }
+            encoder.finish(&mut encoded);
+            let encoded: Vec<LZ77tuple<u8>> = encoded.into_iter().map(Into::into).collect();
+            assert_eq!(encoded, expected);
+        }
+    }
+
+    #[test]
+    fn test_lz77_fast_window() {
+        let input = gen_data(7, 4000, 0.6);
+        for max_offset in [1, 5, 64, 5000] {
+            let encoded = lz77_encode_fast(&input, max_offset, 32, 16);
+            assert!(encoded.iter().all(|entry| entry.offset <= max_offset));
+            assert_round_trip(&lz77_decode(&encoded).unwrap(), &input);
+        }
+    }
+
+    #[test]
+    fn test_lz77_decoder_chunks() {
+        let input = gen_data(3, 4000, 0.8);
+        let encoded = lz77_encode(&input, 64, 16);
+        for chunk_size in [1, 3, 100, encoded.len()] {
+            let mut decoder = LZ77Decoder::new(64);
+            let mut decoded = Vec::new();
+            for chunk in encoded.chunks(chunk_size) {
+                decoder.push(chunk, &mut decoded).unwrap();
+            }
+            assert_round_trip(&decoded, &input);
+        }
+    }
+
+    #[test]
+    fn test_lz77_decoder_invalid_offset() {
+        let mut decoder = LZ77Decoder::new(4);
+        let mut decoded = Vec::new();
+        decoder
+            .push(&[LZ77entry::from((0, 0, b'a'))], &mut decoded)
+            .unwrap();
+        // the entries are counted across chunks, and only three bytes precede the last
+        let invalid = [LZ77entry::from((1, 1, b'b')), LZ77entry::from((4, 1, b'c'))];
+        assert_eq!(
+            decoder.push(&invalid, &mut decoded),
+            Err(DecodeError::OffsetOutOfRange {
+                entry: 2,
+                offset: 4,
+                available: 3
+            })
+        );
+        assert_eq!(decoded, b"aab");
+    }
+
+    #[test]
+    fn test_lz77_encode_into() {
+        let mut encoded = Vec::new();
+        for seed in 0..4 {
+            let input = gen_data(seed, 1000, 0.5);
+            lz77_encode_into(&input, 32, 16, &mut encoded);
+            assert_round_trip(&lz77_decode(&encoded).unwrap(), &input);
+        }
+    }
+
+    #[test]
+    fn test_lz77_tie_smallest_offset() {
+        // the final "abc" matches equally well at offsets 4 and 8
+        let input = b"abcXabcYabc!";
+        for mut encoded in [
+            lz77_encode(input, 16, 16),
+            lz77_encode_fast(input, 16, 16, 16),
+        ] {
+            assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+            let last: LZ77tuple<u8> = encoded.pop().unwrap().into();
+            assert_eq!(last, (4, 3, b'!'));
+        }
+    }
+
+    #[test]
+    fn test_lz77_repeated_tail() {
+        let prefix = gen_data(9, 100, 0.0);
+        let tail = prefix[40..].to_vec();
+        let max_length = 8;
+        let encoded = lz77_encode_with_preset(&prefix, &tail, 128, max_length);
+        assert_round_trip(&lz77_decode_with_preset(&prefix, &encoded).unwrap(), &tail);
+        // the fewest entries that can cover the tail, all of them references
+        assert_eq!(encoded.len(), tail.len().div_ceil(max_length + 1));
+        assert!(encoded.iter().all(|entry| entry.length > 0));
+    }
+
+    #[test]
+    fn test_lz77_decode_into() {
+        let input = b"RATABARBARATABARBARAT".repeat(4);
+        let encoded = lz77_encode(&input, 16, 8);
+        let mut out = vec![0u8; input.len()];
+        assert_eq!(lz77_decode_into(&encoded, &mut out), Ok(input.len()));
+        assert_eq!(out, input);
+
+        let mut out = vec![0u8; input.len() - 1];
+        assert_eq!(
+            lz77_decode_into(&encoded, &mut out),
+            Err(DecodeError::OutputTooSmall {
+                entry: encoded.len() - 1,
+                capacity: input.len() - 1
+            })
+        );
+        assert_eq!(
+            lz77_decode_into(&[LZ77entry::from((2, 1, b'a'))], &mut [0u8; 4]),
+            Err(DecodeError::OffsetOutOfRange {
+                entry: 0,
+                offset: 2,
+                available: 0
+            })
+        );
+        let huge = [
+            LZ77entry::from((0, 0, b'a')),
+            LZ77entry::from((1, usize::MAX, b'b')),
+        ];
+        assert_eq!(
+            lz77_decode_into(&huge, &mut [0u8; 4]),
+            Err(DecodeError::OutputTooSmall {
+                entry: 1,
+                capacity: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_lz77_decode_prefix() {
+        let input = b"RATABARBARATABARBARAT";
+        let encoded = lz77_encode(input, 4, 4);
+        for n in [0, 1, 7, input.len(), input.len() + 10] {
+            let prefix = lz77_decode_prefix(&encoded, n).unwrap();
+            assert_eq!(prefix, &input[..n.min(input.len())]);
+        }
+    }
+
+    #[test]
+    fn test_lz77_decode_invalid() {
+        let input = b"RATABARBARATABARBARAT";
+        let encoded = lz77_encode(input, 4, 4);
+        assert_round_trip(&lz77_decode(&encoded).unwrap(), input);
+
+        let zero_offset = vec![
+            LZ77entry::from((0, 0, b'a')),
+            LZ77entry::from((1, 2, b'b')),
+            LZ77entry::from((0, 3, b'c')),
+        ];
+        assert_eq!(
+            lz77_decode(&zero_offset),
+            Err(DecodeError::EmptyOffsetWithLength { entry: 2 })
+        );
+
+        let too_far = vec![LZ77entry::from((0, 0, b'a')), LZ77entry::from((2, 1, b'b'))];
+        assert_eq!(
+            lz77_decode(&too_far),
+            Err(DecodeError::OffsetOutOfRange {
+                entry: 1,
+                offset: 2,
+                available: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_lz77_decode_zero_offset() {
+        let entries = || vec![LZ77entry::from((0, 0, b'a')), LZ77entry::from((0, 1, b'b'))];
+        let error = || Err(DecodeError::EmptyOffsetWithLength { entry: 1 });
+        assert_eq!(lz77_decode_prefix(&entries(), 2), error());
+        assert_eq!(lz77_decode_owned(entries()), error());
+        assert_eq!(lz77_decode_with_preset(b"xy", &entries()), error());
+    }
+
+    #[test]
+    fn test_lz77_decode_large_offset() {
+        let entries = || vec![LZ77entry::from((0, 0, b'a')), LZ77entry::from((5, 1, b'b'))];
+        let error = |available| {
+            Err(DecodeError::OffsetOutOfRange {
+                entry: 1,
+                offset: 5,
+                available,
+            })
+        };
+        assert_eq!(lz77_decode_prefix(&entries(), 2), error(1));
+        assert_eq!(lz77_decode_owned(entries()), error(1));
+        // the preset counts as decoded data
+        assert_eq!(lz77_decode_with_preset(b"xyz", &entries()), error(4));
+        assert_eq!(
+            lz77_decode_with_preset(b"wxyz", &entries()),
+            Ok(b"awb".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_lz77_stream() {
+        let input = gen_data(4, 200000, 0.8);
+        let mut encoded = Vec::new();
+        lz77_encode_stream(input.as_slice(), &mut encoded, 1000, 32).unwrap();
+        // two bytes for the offset, one for the length, and the byte itself
+        assert_eq!(encoded.len(), lz77_encode(&input, 1000, 32).len() * 4);
+        let mut decoded = Vec::new();
+        lz77_decode_stream(encoded.as_slice(), &mut decoded, 1000, 32).unwrap();
+        assert_round_trip(&decoded, &input);
+
+        let too_far: &[u8] = &[0, 0, b'a', 5, 1, b'b'];
+        let error = lz77_decode_stream(too_far, &mut Vec::new(), 255, 255).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let truncated: &[u8] = &[0, 0, b'a', 1];
+        let error = lz77_decode_stream(truncated, &mut Vec::new(), 255, 255).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_lz77_split() {
+        let input = gen_data(3, 5000, 0.8);
+        let encoded = lz77_encode(&input, 255, 32);
+        let streams = lz77_encode_split(&input, 255, 32);
+        assert_eq!(streams.literals.len(), encoded.len());
+        assert_eq!(streams.flags.len(), encoded.len());
+        assert_eq!(streams.offsets.len(), streams.flags.count_ones());
+        assert_eq!(streams.lengths.len(), streams.offsets.len());
+        assert!(streams.lengths.iter().all(|length| *length > 0));
+
+        // every stream is stored on its own, and the streams are put back together
+        let store = |values: &[usize]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect()
+        };
+        let load = |bytes: &[u8]| -> Vec<usize> {
+            bytes
+                .chunks(size_of::<usize>())
+                .map(|chunk| usize::from_le_bytes(chunk.try_into().unwrap()))
+                .collect()
+        };
+        let flags: Vec<u8> = streams.flags.iter().map(|flag| *flag as u8).collect();
+        let streams = LZ77Streams {
+            literals: streams.literals.clone(),
+            offsets: load(&store(&streams.offsets)),
+            lengths: load(&store(&streams.lengths)),
+            flags: flags.iter().map(|flag| *flag == 1).collect(),
+        };
+        assert_eq!(
+            lz77_decode_split(streams.clone()).unwrap(),
+            lz77_decode(&encoded).unwrap()
+        );
+
+        // a stream cut short doesn't line up with the flags
+        let mut short = streams.clone();
+        short.lengths.pop();
+        assert!(matches!(
+            lz77_decode_split(short),
+            Err(DecodeError::StreamMismatch { .. })
+        ));
+        let mut long = streams;
+        long.literals.push(0);
+        assert!(matches!(
+            lz77_decode_split(long),
+            Err(DecodeError::StreamMismatch { .. })
+        ));
+    }
 }
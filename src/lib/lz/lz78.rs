@@ -1,3 +1,12 @@
+#[cfg(feature = "trace")]
+use super::trace::{TraceEvent, Traced};
+use super::{
+    Children, Dictionary, DictionaryStats, Eviction, Observer, OffsetIndex, ScannedDictionary,
+    output_estimate,
+};
+
+use std::{error, fmt, hash::Hash};
+
 /// A struct to represent an LZ78 entry
 /// It contains an index to the dictionary and the next character.
 /// The index is `None` if the entry is a new character.
@@ -20,6 +29,85 @@ impl<T> From<LZ78tuple<T>> for LZ78entry<T> {
     }
 }
 
+/// An LZ78 tuple, along with the size of the dictionary its index points
+/// into. Converting it into an [LZ78entry] checks that the index is in range,
+/// which makes it useful for validating untrusted input.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{BoundedLZ78tuple, LZ78entry};
+/// assert!(LZ78entry::try_from(BoundedLZ78tuple::new((Some(3), b'a'), 4)).is_ok());
+/// assert!(LZ78entry::try_from(BoundedLZ78tuple::new((Some(4), b'a'), 4)).is_err());
+/// ```
+pub struct BoundedLZ78tuple<T> {
+    tuple: LZ78tuple<T>,
+    dictionary_size: usize,
+}
+
+impl<T> BoundedLZ78tuple<T> {
+    /// Creates a new bounded tuple.
+    ///
+    /// ## Arguments
+    ///
+    /// - `tuple`: The LZ78 tuple.
+    /// - `dictionary_size`: The maximum size of the dictionary.
+    pub fn new(tuple: LZ78tuple<T>, dictionary_size: usize) -> Self {
+        BoundedLZ78tuple {
+            tuple,
+            dictionary_size,
+        }
+    }
+}
+
+/// Error returned when an LZ78 index points outside of the dictionary.
+#[derive(Debug, PartialEq)]
+pub struct InvalidIndexError {
+    /// The offending index
+    pub index: usize,
+    /// The maximum size of the dictionary
+    pub dictionary_size: usize,
+}
+
+impl fmt::Display for InvalidIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Index {} is out of range for a dictionary of size {}",
+            self.index, self.dictionary_size
+        )
+    }
+}
+
+impl error::Error for InvalidIndexError {}
+
+impl<T> TryFrom<BoundedLZ78tuple<T>> for LZ78entry<T> {
+    type Error = InvalidIndexError;
+
+    fn try_from(bounded: BoundedLZ78tuple<T>) -> Result<Self, Self::Error> {
+        match bounded.tuple.0 {
+            Some(index) if index >= bounded.dictionary_size => Err(InvalidIndexError {
+                index,
+                dictionary_size: bounded.dictionary_size,
+            }),
+            _ => Ok(LZ78entry::from(bounded.tuple)),
+        }
+    }
+}
+
+/// Error returned by [lz78_encode_checked] when the dictionary can't hold any
+/// entries.
+#[derive(Debug, PartialEq)]
+pub struct DictionarySizeError;
+
+impl fmt::Display for DictionarySizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The dictionary must hold at least one entry")
+    }
+}
+
+impl error::Error for DictionarySizeError {}
+
 impl<T> Into<LZ78tuple<T>> for LZ78entry<T> {
     fn into(self) -> LZ78tuple<T> {
         (self.index, self.next_char)
@@ -52,18 +140,29 @@ mod lz78_serde {
 }
 
 impl<T: Clone> LZ78entry<T> {
-    fn resolve(&self, dictionary: &Vec<Vec<T>>) -> Vec<T> {
-        let mut res = if let Some(index) = self.index {
-            let target = &dictionary[index];
-            target.clone()
-        } else {
-            Vec::with_capacity(1)
-        };
-        res.push(self.next_char.clone());
-        return res;
+    /// Appends the phrase the entry decodes to to the output.
+    fn resolve<C: Children<T>>(&self, dictionary: &Dictionary<'_, T, C>, output: &mut Vec<T>) {
+        if let Some(index) = self.index {
+            dictionary.write_phrase(index, output);
+        }
+        output.push(self.next_char.clone());
     }
 }
 
+/// Creates the dictionary shared by the LZ78 encoder and decoder. It starts
+/// empty, and once full every new phrase replaces the first one.
+fn new_dictionary<'a, T: Clone, C: Children<T>>(
+    max_size: usize,
+    memory_limit_bytes: Option<usize>,
+) -> Dictionary<'a, T, C> {
+    Dictionary::new(
+        Default::default(),
+        max_size,
+        Eviction::ReplaceFirst,
+        memory_limit_bytes,
+    )
+}
+
 /// A function to encode a slice of data using the LZ78 algorithm
 /// The function takes a slice of data, a maximum lookahead size, and a maximum dictionary size.
 /// It returns a vector of LZ78 entries.
@@ -86,61 +185,198 @@ impl<T: Clone> LZ78entry<T> {
 /// let encoded = lz78_encode(input, 4, 4);
 /// assert!(encoded.len() < input.len());
 /// ```
-pub fn lz78_encode<T: Clone + PartialEq>(
+pub fn lz78_encode<T: Clone + Eq + Hash>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+) -> Vec<LZ78entry<T>> {
+    lz78_encode_with_lengths(input, lookahead_max, max_dictionary_size).0
+}
+
+/// A function to encode a slice of data using the LZ78 algorithm, that
+/// rejects a maximum dictionary size of zero. [lz78_encode] accepts it, but
+/// with no room for phrases every entry is a single literal, which is rarely
+/// what the caller meant.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `lookahead_max`: The maximum lookahead size.
+/// - `max_dictionary_size`: The maximum size of the dictionary, at least one.
+///
+/// ## Returns
+///
+/// A vector of LZ78 entries, or an error if the dictionary size is zero.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{DictionarySizeError, lz78_encode_checked};
+/// let input = b"rabarbarbar";
+/// assert!(lz78_encode_checked(input, 4, 4).is_ok());
+/// assert_eq!(lz78_encode_checked(input, 4, 0).err(), Some(DictionarySizeError));
+/// ```
+pub fn lz78_encode_checked<T: Clone + Eq + Hash>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+) -> Result<Vec<LZ78entry<T>>, DictionarySizeError> {
+    if max_dictionary_size == 0 {
+        return Err(DictionarySizeError);
+    }
+    Ok(lz78_encode(input, lookahead_max, max_dictionary_size))
+}
+
+/// A function to encode a slice of data using the LZ78 algorithm, that also
+/// returns the length of the phrase each entry decodes to. Knowing the
+/// lengths upfront lets a decoder preallocate its output, and lets a
+/// serializer pick a better representation for the indices.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `lookahead_max`: The maximum lookahead size.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+///
+/// ## Returns
+///
+/// A vector of LZ78 entries, and a parallel vector of phrase lengths.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{lz78_encode, lz78_encode_with_lengths};
+/// let input = b"rabarbarbar";
+/// let (encoded, lengths) = lz78_encode_with_lengths(input, 4, 4);
+/// assert!(encoded == lz78_encode(input, 4, 4));
+/// assert_eq!(lengths.iter().sum::<usize>(), input.len());
+/// ```
+pub fn lz78_encode_with_lengths<T: Clone + Eq + Hash>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+) -> (Vec<LZ78entry<T>>, Vec<usize>) {
+    let (output, lengths, _) = encode(input, lookahead_max, max_dictionary_size, None, &mut ());
+    (output, lengths)
+}
+
+/// A function to encode a slice of data using the LZ78 algorithm, reporting
+/// every token and dictionary addition to a callback. Produces the same
+/// entries as [lz78_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `lookahead_max`: The maximum lookahead size.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+/// - `trace`: The callback receiving the events.
+///
+/// ## Returns
+///
+/// A vector of LZ78 entries.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::{lz78::lz78_encode_traced, trace::TraceEvent};
+/// let mut events = Vec::new();
+/// let encoded = lz78_encode_traced(b"rabarbarbar", 4, 4, |event| events.push(event));
+/// assert_eq!(events[0], TraceEvent::Literal { position: 0 });
+/// assert_eq!(events[1], TraceEvent::DictionaryAddition { index: 0, length: 1 });
+/// ```
+#[cfg(feature = "trace")]
+pub fn lz78_encode_traced<T: Clone + Eq + Hash>(
     input: &[T],
     lookahead_max: usize,
     max_dictionary_size: usize,
+    trace: impl FnMut(TraceEvent),
 ) -> Vec<LZ78entry<T>> {
+    encode(
+        input,
+        lookahead_max,
+        max_dictionary_size,
+        None,
+        &mut Traced(trace),
+    )
+    .0
+}
+
+/// A function to encode a slice of data using the LZ78 algorithm, while
+/// keeping the estimated memory usage of the dictionary under a limit. Once
+/// adding a phrase would exceed the limit, the dictionary is cleared instead.
+/// The data must be decoded with [lz78_decode_with_memory_limit], using the
+/// same limit.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `lookahead_max`: The maximum lookahead size.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+/// - `memory_limit_bytes`: The limit on the estimated dictionary memory usage, if any.
+///
+/// ## Returns
+///
+/// A vector of LZ78 entries, and statistics about the dictionary.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{lz78_decode_with_memory_limit, lz78_encode_with_memory_limit};
+/// let input = b"rabarbarbarrabarbarbar";
+/// let (encoded, stats) = lz78_encode_with_memory_limit(input, 4, 16, Some(8));
+/// assert!(stats.peak_dictionary_bytes <= 8);
+/// let decoded = lz78_decode_with_memory_limit(&encoded, 16, Some(8));
+/// assert_eq!(input, decoded.as_slice());
+/// ```
+pub fn lz78_encode_with_memory_limit<T: Clone + Eq + Hash>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+    memory_limit_bytes: Option<usize>,
+) -> (Vec<LZ78entry<T>>, DictionaryStats) {
+    let (output, _, stats) = encode(
+        input,
+        lookahead_max,
+        max_dictionary_size,
+        memory_limit_bytes,
+        &mut (),
+    );
+    (output, stats)
+}
+
+fn encode<T: Clone + Eq + Hash>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+    memory_limit_bytes: Option<usize>,
+    observer: &mut impl Observer,
+) -> (Vec<LZ78entry<T>>, Vec<usize>, DictionaryStats) {
     let mut output = Vec::new();
-    let mut dictionary: Vec<Vec<T>> = Vec::with_capacity(max_dictionary_size);
+    let mut lengths = Vec::new();
+    let mut dictionary: Dictionary<_> = new_dictionary(max_dictionary_size, memory_limit_bytes);
 
     let mut i = 0;
     while i < input.len() {
-        // Find the longest prefix in the dictionary
-        let mut longest_prefix: Option<usize> = None;
-        for (idx, entry) in dictionary.iter().enumerate() {
-            let entry_len = entry.len();
-            // sanity check
-            if entry_len > lookahead_max
-                || i + entry_len + 1 > input.len()
-                || input[i..i + entry_len] != *entry
-            {
-                continue;
-            }
-            // If we found a prefix, check if it's the longest one
-            if let Some(longest) = &mut longest_prefix {
-                if entry_len > dictionary[*longest].len() {
-                    *longest = idx;
-                }
-            } else {
-                longest_prefix = Some(idx);
-            }
-        }
-        let new_entry = if let Some(idx) = longest_prefix {
-            // If we found a prefix, add it to the output
-            i += dictionary[idx].len() + 1;
-            LZ78entry {
-                index: Some(idx),
-                next_char: input[i - 1].clone(),
-            }
-        } else {
-            // If we didn't find a prefix, add the current character to the dictionary
-            i += 1;
-            LZ78entry {
-                index: None,
-                next_char: input[i - 1].clone(),
-            }
+        // Find the longest prefix in the dictionary, leaving room for the next character
+        let end = i.saturating_add(lookahead_max).min(input.len() - 1);
+        let index = dictionary.longest_match(&input[i..end]);
+        let length = index.map_or(0, |idx| dictionary.length(idx)) + 1;
+        i += length;
+        let new_entry = LZ78entry {
+            index,
+            next_char: input[i - 1].clone(),
         };
-        let new_dict_entry = new_entry.resolve(&dictionary);
-        // If the dictionary is full, remove the oldest entry
-        if dictionary.len() == max_dictionary_size {
-            *dictionary.get_mut(0).unwrap() = new_dict_entry;
-        } else {
-            dictionary.push(new_dict_entry);
+        match index {
+            Some(index) => observer.phrase(i - length, index, length - 1),
+            None => observer.literal(i - length),
+        }
+        lengths.push(length);
+        if let Some(index) = dictionary.add(index, input[i - 1].clone()) {
+            observer.added(index, length);
         }
         output.push(new_entry);
     }
-    return output;
+    (output, lengths, dictionary.stats)
 }
 
 /// A function to decode a slice of data using the LZ78 algorithm
@@ -156,6 +392,11 @@ pub fn lz78_encode<T: Clone + PartialEq>(
 ///
 /// A vector of decoded data.
 ///
+/// ## Panics
+///
+/// If an entry refers to a phrase that isn't in the dictionary. Use
+/// [lz78_decode_checked] to get an error instead.
+///
 /// ## Example
 ///
 /// ```
@@ -170,39 +411,204 @@ pub fn lz78_decode<T: Clone + PartialEq>(
     input: &[LZ78entry<T>],
     max_dictionary_size: usize,
 ) -> Vec<T> {
-    let mut output = Vec::new();
-    let mut dictionary: Vec<Vec<T>> = Vec::with_capacity(input.len());
+    lz78_decode_with_memory_limit(input, max_dictionary_size, None)
+}
+
+/// A function to decode a slice of data encoded by
+/// [lz78_encode_with_memory_limit].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of LZ78 entries to be decoded.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+/// - `memory_limit_bytes`: The limit on the estimated dictionary memory usage used when encoding.
+///
+/// ## Returns
+///
+/// A vector of decoded data.
+pub fn lz78_decode_with_memory_limit<T: Clone + PartialEq>(
+    input: &[LZ78entry<T>],
+    max_dictionary_size: usize,
+    memory_limit_bytes: Option<usize>,
+) -> Vec<T> {
+    let dictionary = new_dictionary(max_dictionary_size, memory_limit_bytes);
+    decode(input, dictionary, usize::MAX).expect("Invalid LZ78 entry")
+}
+
+/// A function to decode a slice of LZ78 entries, that validates the entries
+/// instead of panicking on invalid ones. An entry may only refer to a phrase
+/// added by an earlier entry, so its index must be below the length of the
+/// dictionary at the time it's decoded. Useful when the entries come from an
+/// untrusted source.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of LZ78 entries to be decoded.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+///
+/// ## Returns
+///
+/// A vector of decoded data, or an error describing the first invalid entry.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{InvalidIndexError, LZ78entry, lz78_decode_checked};
+/// // the second entry refers to the phrase it's itself adding
+/// let input = vec![LZ78entry::from((None, b'a')), LZ78entry::from((Some(1), b'b'))];
+/// assert_eq!(
+///     lz78_decode_checked(&input, 4),
+///     Err(InvalidIndexError { index: 1, dictionary_size: 1 })
+/// );
+/// ```
+pub fn lz78_decode_checked<T: Clone + PartialEq>(
+    input: &[LZ78entry<T>],
+    max_dictionary_size: usize,
+) -> Result<Vec<T>, InvalidIndexError> {
+    decode(input, new_dictionary(max_dictionary_size, None), usize::MAX)
+}
+
+/// A function to decode only the beginning of a slice of LZ78 entries.
+/// Decoding stops as soon as `n` elements are produced.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of LZ78 entries to be decoded.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+/// - `n`: The number of elements to decode.
+///
+/// ## Returns
+///
+/// The first `n` elements of the data, or all of it if it's shorter.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{lz78_decode_prefix, lz78_encode};
+/// let input = b"rabarbarbar";
+/// let encoded = lz78_encode(input, 4, 4);
+/// assert_eq!(lz78_decode_prefix(&encoded, 4, 5), b"rabar");
+/// ```
+pub fn lz78_decode_prefix<T: Clone + PartialEq>(
+    input: &[LZ78entry<T>],
+    max_dictionary_size: usize,
+    n: usize,
+) -> Vec<T> {
+    decode(input, new_dictionary(max_dictionary_size, None), n).expect("Invalid LZ78 entry")
+}
+
+/// A function to encode a slice of data using the LZ78 algorithm, that also
+/// builds an index of where every entry ends in the data, for
+/// [lz78_seek_decode]. Produces the same entries as [lz78_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `lookahead_max`: The maximum lookahead size.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+///
+/// ## Returns
+///
+/// A vector of LZ78 entries, and the offset index.
+pub fn lz78_encode_indexed<T: Clone + Eq + Hash>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+) -> (Vec<LZ78entry<T>>, OffsetIndex) {
+    let (output, lengths) = lz78_encode_with_lengths(input, lookahead_max, max_dictionary_size);
+    (output, OffsetIndex::from_lengths(&lengths))
+}
+
+/// A function to decode a slice of LZ78 entries starting at an offset in the
+/// decoded data. The dictionary is rebuilt from the entries before the one
+/// containing the offset, without decoding their phrases.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of LZ78 entries to be decoded.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+/// - `index`: The offset index built by [lz78_encode_indexed].
+/// - `output_offset`: The offset in the decoded data to start at.
+///
+/// ## Returns
+///
+/// The decoded data from the offset onwards, which is empty if the offset is
+/// past the end.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lz78::{lz78_encode_indexed, lz78_seek_decode};
+/// let input = b"rabarbarbar";
+/// let (encoded, index) = lz78_encode_indexed(input, 4, 4);
+/// assert_eq!(lz78_seek_decode(&encoded, 4, &index, 6), b"arbar");
+/// ```
+pub fn lz78_seek_decode<T: Clone + PartialEq>(
+    input: &[LZ78entry<T>],
+    max_dictionary_size: usize,
+    index: &OffsetIndex,
+    output_offset: usize,
+) -> Vec<T> {
+    let Some((code, start)) = index.locate(output_offset) else {
+        return Vec::new();
+    };
+    let mut dictionary = new_dictionary(max_dictionary_size, None);
+    for entry in &input[..code] {
+        dictionary.add(entry.index, entry.next_char.clone());
+    }
+    let mut output = decode(&input[code..], dictionary, usize::MAX).expect("Invalid LZ78 entry");
+    output.drain(..output_offset - start);
+    output
+}
+
+/// Decodes the entries, validating every index against the dictionary at
+/// the time the entry is decoded.
+fn decode<T: Clone + PartialEq>(
+    input: &[LZ78entry<T>],
+    mut dictionary: ScannedDictionary<'_, T>,
+    n: usize,
+) -> Result<Vec<T>, InvalidIndexError> {
+    // every entry decodes to at least one element, and on compressible data to a few
+    let mut output = Vec::with_capacity(output_estimate(input.len()).min(n));
 
     for entry in input {
-        // find the canonical form of the entry
-        let resolved = entry.resolve(&dictionary);
-        for el in &resolved {
-            output.push(el.clone());
+        if output.len() >= n {
+            break;
         }
-        // if the dictionary is full, remove the oldest entry
-        if dictionary.len() == max_dictionary_size {
-            *dictionary.get_mut(0).unwrap() = resolved.clone();
-        } else {
-            // if not, add the new entry to the dictionary
-            dictionary.push(resolved.clone());
+        if let Some(index) = entry.index
+            && index >= dictionary.len()
+        {
+            return Err(InvalidIndexError {
+                index,
+                dictionary_size: dictionary.len(),
+            });
         }
+        entry.resolve(&dictionary, &mut output);
+        dictionary.add(entry.index, entry.next_char.clone());
     }
-    return output;
+    output.truncate(n);
+    Ok(output)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{assert_round_trip, gen_data};
 
     #[test]
     fn test_resolve() {
         let other: Vec<char> = "test".chars().collect();
-        let dictionary: Vec<Vec<char>> = vec![vec!['t'], vec!['t', 'e'], vec!['t', 'e', 's']];
+        let mut dictionary: ScannedDictionary<_> = new_dictionary(4, None);
+        dictionary.add(None, 't');
+        dictionary.add(Some(0), 'e');
+        dictionary.add(Some(1), 's');
         let target = LZ78entry {
             index: Some(2),
             next_char: 't',
         };
-        assert_eq!(target.resolve(&dictionary), other);
+        let mut resolved = Vec::new();
+        target.resolve(&dictionary, &mut resolved);
+        assert_eq!(resolved, other);
     }
 
     #[test]
@@ -211,6 +617,149 @@ mod tests {
         let encoded = lz78_encode(input, 4, 4);
         assert!(encoded.len() < input.len());
         let decoded = lz78_decode(&encoded, 4);
-        assert_eq!(input, decoded.as_slice());
+        assert_round_trip(&decoded, input);
+    }
+
+    #[test]
+    fn test_lz78_bounded_dictionary() {
+        let input = b"abababababcabcabcabcdabcdabcdabcd";
+        for max_dictionary_size in [1, 2, 3] {
+            let encoded = lz78_encode(input, 8, max_dictionary_size);
+            // no entry may point outside of the bounded dictionary
+            assert!(
+                encoded
+                    .iter()
+                    .all(|entry| entry.index.is_none_or(|idx| idx < max_dictionary_size))
+            );
+            let decoded = lz78_decode(&encoded, max_dictionary_size);
+            assert_round_trip(&decoded, input);
+        }
+    }
+
+    #[test]
+    fn test_lz78_encode_with_lengths() {
+        let input = b"TAMTARAMTAMTAMRAMTAT";
+        let max_dictionary_size = 4;
+        let (encoded, lengths) = lz78_encode_with_lengths(input, 4, max_dictionary_size);
+        assert_eq!(encoded.len(), lengths.len());
+        // replay the dictionary, to resolve the entries the same way the decoder does
+        let mut dictionary: ScannedDictionary<_> = new_dictionary(max_dictionary_size, None);
+        for (entry, length) in encoded.iter().zip(&lengths) {
+            let mut resolved = Vec::new();
+            entry.resolve(&dictionary, &mut resolved);
+            assert_eq!(resolved.len(), *length);
+            dictionary.add(entry.index, entry.next_char);
+        }
+        assert_round_trip(&lz78_decode(&encoded, max_dictionary_size), input);
+    }
+
+    #[test]
+    fn test_lz78_decode_checked() {
+        let input = b"TAMTARAMTAMTAMRAMTAT".repeat(4);
+        let encoded = lz78_encode(&input, 8, 16);
+        assert_eq!(lz78_decode_checked(&encoded, 16), Ok(input));
+
+        // an entry referring to the phrase it's adding itself
+        let mut encoded = lz78_encode(b"abcabc", 8, 16);
+        encoded[3].index = Some(3);
+        assert_eq!(
+            lz78_decode_checked(&encoded, 16),
+            Err(InvalidIndexError {
+                index: 3,
+                dictionary_size: 3
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid LZ78 entry")]
+    fn test_lz78_decode_prefix_invalid_index() {
+        let mut encoded = lz78_encode(b"abcabc", 8, 16);
+        encoded[3].index = Some(3);
+        lz78_decode_prefix(&encoded, 16, usize::MAX);
+    }
+
+    #[test]
+    fn test_lz78_seek_decode() {
+        let input = gen_data(7, 5000, 0.8);
+        for max_dictionary_size in [1, 64, 4096] {
+            let (encoded, index) = lz78_encode_indexed(&input, 64, max_dictionary_size);
+            assert_eq!(index.decoded_len(), input.len());
+            let decoded = lz78_decode(&encoded, max_dictionary_size);
+            for offset in [0, 1, 1234, 4321, input.len() - 1, input.len()] {
+                assert_eq!(
+                    lz78_seek_decode(&encoded, max_dictionary_size, &index, offset),
+                    &decoded[offset..]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lz78_memory_limit() {
+        let input = b"TAMTARAMTAMTAMRAMTAT".repeat(8);
+        let (_, unlimited) = lz78_encode_with_memory_limit(&input, 8, 64, None);
+        let limit = unlimited.peak_dictionary_bytes / 4;
+        let (encoded, stats) = lz78_encode_with_memory_limit(&input, 8, 64, Some(limit));
+        assert!(stats.peak_dictionary_bytes <= limit);
+        assert!(stats.resets > 0);
+        let decoded = lz78_decode_with_memory_limit(&encoded, 64, Some(limit));
+        assert_round_trip(&decoded, &input);
+    }
+
+    #[test]
+    fn test_lz78_dictionary_size() {
+        let input = b"rabarbarbarrabarbarbar";
+        assert_eq!(
+            lz78_encode_checked(input, 4, 0).err(),
+            Some(DictionarySizeError)
+        );
+        // without a dictionary, everything is a literal
+        let encoded = lz78_encode(input, 4, 0);
+        assert_eq!(encoded.len(), input.len());
+        assert_round_trip(&lz78_decode(&encoded, 0), input);
+
+        // a single entry, replaced by every new phrase
+        let encoded = lz78_encode_checked(input, 4, 1).unwrap();
+        assert!(
+            encoded
+                .iter()
+                .all(|entry| entry.index.is_none_or(|i| i == 0))
+        );
+        assert_round_trip(&lz78_decode(&encoded, 1), input);
+    }
+
+    #[test]
+    fn test_lz78_try_from() {
+        let entry = LZ78entry::try_from(BoundedLZ78tuple::new((None, 'a'), 0)).unwrap();
+        assert!(entry == LZ78entry::from((None, 'a')));
+        assert!(LZ78entry::try_from(BoundedLZ78tuple::new((Some(0), 'a'), 1)).is_ok());
+        let err = LZ78entry::try_from(BoundedLZ78tuple::new((Some(usize::MAX), 'a'), 16));
+        assert_eq!(
+            err.err(),
+            Some(InvalidIndexError {
+                index: usize::MAX,
+                dictionary_size: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_lz78_decode_large() {
+        for redundancy in [0.0, 0.5, 0.99] {
+            let input = gen_data(7, 20000, redundancy);
+            let encoded = lz78_encode(&input, 64, 4096);
+            let decoded = lz78_decode(&encoded, 4096);
+            assert!(decoded.capacity() >= output_estimate(encoded.len()));
+            assert_round_trip(&decoded, &input);
+        }
+        // every entry decodes to a single byte, fewer than estimated, so the
+        // capacity can only come from reserving the estimate upfront
+        let input: Vec<u8> = (0..=255).collect();
+        let encoded = lz78_encode(&input, 64, 4096);
+        let decoded = lz78_decode(&encoded, 4096);
+        assert!(decoded.len() < output_estimate(encoded.len()));
+        assert!(decoded.capacity() >= output_estimate(encoded.len()));
+        assert_round_trip(&decoded, &input);
     }
 }
@@ -1,7 +1,63 @@
+#[cfg(feature = "trace")]
+use super::trace::{TraceEvent, Traced};
+use super::{
+    Children, Dictionary, DictionaryStats, Eviction, Observer, OffsetIndex, ScannedDictionary,
+    output_estimate,
+};
+
+use std::{collections::HashMap, error, fmt, hash::Hash};
+
+/// Error returned when a symbol of the input is missing from the initial
+/// dictionary, so it can't be encoded.
+#[derive(Debug, PartialEq)]
+pub struct UnknownSymbolError<T> {
+    /// The offending symbol
+    pub symbol: T,
+    /// The position of the symbol in the input
+    pub position: usize,
+}
+
+impl<T: fmt::Debug> fmt::Display for UnknownSymbolError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Symbol {:?} at position {} is not in the initial dictionary",
+            self.symbol, self.position
+        )
+    }
+}
+
+impl<T: fmt::Debug> error::Error for UnknownSymbolError<T> {}
+
+/// Error returned when an LZW code points outside of the dictionary.
+#[derive(Debug, PartialEq)]
+pub struct InvalidCodeError {
+    /// The offending code
+    pub code: usize,
+    /// The size of the dictionary when the code was read
+    pub dictionary_size: usize,
+}
+
+impl fmt::Display for InvalidCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Code {} is out of range for a dictionary of size {}",
+            self.code, self.dictionary_size
+        )
+    }
+}
+
+impl error::Error for InvalidCodeError {}
+
 /// A function to encode a slice of data using the LZW algorithm
 /// The function takes a slice of data, an initial dictionary, and a maximum lookahead size.
 /// It returns a vector of indices representing the encoded data.
 ///
+/// The dictionary grows without bound, so on long inputs the indices keep
+/// growing too. Use [lzw_encode_with_max_bits] to keep them below a fixed
+/// number of bits, by clearing the dictionary whenever it fills up.
+///
 /// ## Arguments
 ///
 /// - `input`: A slice of data to be encoded.
@@ -12,6 +68,11 @@
 ///
 /// A vector of indices representing the encoded data.
 ///
+/// ## Panics
+///
+/// If a symbol of the input is missing from the initial dictionary. Use
+/// [lzw_encode_checked] to get an error instead.
+///
 /// ## Example
 ///
 /// ```
@@ -21,61 +82,376 @@
 /// let encoded = lzw_encode(input, initial, 4);
 /// assert_eq!(encoded, vec![0, 1, 2, 4, 3]);
 /// ```
-pub fn lzw_encode<T: Clone + PartialEq>(
+pub fn lzw_encode<T: Clone + Eq + Hash>(
     input: &[T],
     initial: &[T],
     max_lookahead: usize,
 ) -> Vec<usize> {
-    let mut dictionary: Vec<Vec<T>> = Vec::with_capacity(initial.len());
-    for i in initial {
-        dictionary.push(vec![i.clone()]);
+    lzw_encode_with_memory_limit(input, initial, max_lookahead, None).0
+}
+
+/// A function to encode a slice of data using the LZW algorithm, that checks
+/// every symbol of the input is in the initial dictionary before encoding,
+/// instead of panicking midway through.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data, or an error naming
+/// the first symbol missing from the initial dictionary.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{UnknownSymbolError, lzw_encode_checked};
+/// assert!(lzw_encode_checked(b"ABABABABA", b"AB", 4).is_ok());
+/// assert_eq!(
+///     lzw_encode_checked(b"ABACABA", b"AB", 4),
+///     Err(UnknownSymbolError { symbol: b'C', position: 3 })
+/// );
+/// ```
+pub fn lzw_encode_checked<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+) -> Result<Vec<usize>, UnknownSymbolError<T>> {
+    check_symbols(input, initial)?;
+    Ok(lzw_encode(input, initial, max_lookahead))
+}
+
+/// Checks that every symbol of the input is in the initial dictionary.
+fn check_symbols<T: Clone + PartialEq>(
+    input: &[T],
+    initial: &[T],
+) -> Result<(), UnknownSymbolError<T>> {
+    match input.iter().position(|symbol| !initial.contains(symbol)) {
+        Some(position) => Err(UnknownSymbolError {
+            symbol: input[position].clone(),
+            position,
+        }),
+        None => Ok(()),
     }
+}
+
+/// A function to encode a slice of data using the LZW algorithm, reporting
+/// every code and dictionary addition to a callback. Produces the same codes
+/// as [lzw_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+/// - `trace`: The callback receiving the events.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::{lzw::lzw_encode_traced, trace::TraceEvent};
+/// let mut events = Vec::new();
+/// let encoded = lzw_encode_traced(b"ABABABABA", b"AB", 4, |event| events.push(event));
+/// assert_eq!(events[0], TraceEvent::Phrase { position: 0, index: 0, length: 1 });
+/// assert_eq!(events[1], TraceEvent::DictionaryAddition { index: 2, length: 2 });
+/// ```
+#[cfg(feature = "trace")]
+pub fn lzw_encode_traced<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+    trace: impl FnMut(TraceEvent),
+) -> Vec<usize> {
+    let dictionary: Dictionary<_> = new_dictionary(initial, None);
+    encode(input, dictionary, max_lookahead, None, &mut Traced(trace)).0
+}
+
+/// A function to encode a slice of data using the LZW algorithm, while
+/// keeping the estimated memory usage of the dictionary under a limit. Once
+/// adding a phrase would exceed the limit, the dictionary is reset to the
+/// initial dictionary instead. The data must be decoded with
+/// [lzw_decode_with_memory_limit], using the same limit.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+/// - `memory_limit_bytes`: The limit on the estimated dictionary memory usage, if any.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data, and statistics about
+/// the dictionary.
+///
+/// ## Panics
+///
+/// If the initial dictionary alone exceeds the memory limit. A limit equal
+/// to its size leaves no room for any phrase, so the dictionary never grows.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_with_memory_limit, lzw_encode_with_memory_limit};
+/// let input = b"ABABABABABABABABA";
+/// let (encoded, stats) = lzw_encode_with_memory_limit(input, b"AB", 4, Some(8));
+/// assert!(stats.peak_dictionary_bytes <= 8);
+/// let decoded = lzw_decode_with_memory_limit(&encoded, b"AB", Some(8));
+/// assert_eq!(input.to_vec(), decoded);
+/// ```
+pub fn lzw_encode_with_memory_limit<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+    memory_limit_bytes: Option<usize>,
+) -> (Vec<usize>, DictionaryStats) {
+    let dictionary: Dictionary<_> = new_dictionary(initial, memory_limit_bytes);
+    encode(input, dictionary, max_lookahead, None, &mut ())
+}
+
+/// A function to encode a slice of data using the LZW algorithm, with a cap
+/// on the length of the dictionary phrases. Once a phrase reaches the cap it
+/// isn't extended any further, which bounds the memory of a single entry on
+/// pathological inputs, like long runs of one symbol. The data must be
+/// decoded with [lzw_decode_with_max_phrase_len], using the same cap.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+/// - `max_phrase_len`: The maximum length of a dictionary phrase.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_with_max_phrase_len, lzw_encode_with_max_phrase_len};
+/// let input = [b'a'; 64];
+/// let encoded = lzw_encode_with_max_phrase_len(&input, b"a", 64, 4);
+/// assert!(encoded.len() >= 64 / 4);
+/// let decoded = lzw_decode_with_max_phrase_len(&encoded, b"a", 4);
+/// assert_eq!(input.to_vec(), decoded);
+/// ```
+pub fn lzw_encode_with_max_phrase_len<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+    max_phrase_len: usize,
+) -> Vec<usize> {
+    let dictionary: Dictionary<_> =
+        new_dictionary(initial, None).with_max_phrase_len(max_phrase_len);
+    encode(input, dictionary, max_lookahead, None, &mut ()).0
+}
+
+/// A function to encode a slice of data using the LZW algorithm, with the
+/// codes bounded to `max_bits` bits. The largest code is reserved as the
+/// clear code. Once the dictionary holds every other code, the encoder emits
+/// the clear code and starts over from the initial dictionary, the way GIF
+/// and TIFF keep their codes bounded. The data must be decoded with
+/// [lzw_decode_with_max_bits], using the same number of bits.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+/// - `max_bits`: The number of bits every code fits in.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data.
+///
+/// ## Panics
+///
+/// If the initial dictionary leaves no room for a phrase below the clear
+/// code, or `max_bits` is not smaller than the number of bits in a `usize`.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_with_max_bits, lzw_encode_with_max_bits};
+/// let input = b"ABABABABABABABABA";
+/// let encoded = lzw_encode_with_max_bits(input, b"AB", 4, 2);
+/// assert!(encoded.iter().all(|code| *code < 4));
+/// let decoded = lzw_decode_with_max_bits(&encoded, b"AB", 2);
+/// assert_eq!(input.to_vec(), decoded);
+/// ```
+pub fn lzw_encode_with_max_bits<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+    max_bits: u32,
+) -> Vec<usize> {
+    let (dictionary, clear_code): (Dictionary<_>, _) = new_bounded_dictionary(initial, max_bits);
+    encode(input, dictionary, max_lookahead, Some(clear_code), &mut ()).0
+}
+
+fn encode<T: Clone, D: Phrases<T>>(
+    input: &[T],
+    mut dictionary: D,
+    max_lookahead: usize,
+    clear_code: Option<usize>,
+    observer: &mut impl Observer,
+) -> (Vec<usize>, DictionaryStats) {
     let mut output: Vec<usize> = Vec::new();
 
     let mut i = 0;
     while i < input.len() {
-        // Find the longest prefix in the dictionary
-        let mut longest_prefix: Option<usize> = None;
-        for (idx, entry) in dictionary.iter().enumerate() {
-            let entry_len = entry.len();
-            if entry_len > max_lookahead
-                || i + entry_len > input.len()
-                || input[i..i + entry_len] != *entry
-            {
-                continue;
-            }
-            if let Some(longest) = &mut longest_prefix {
-                if entry_len > dictionary[*longest].len() {
-                    *longest = idx;
-                }
-            } else {
-                longest_prefix = Some(idx);
-            }
-        }
-        // If we found a prefix, add it to the output
-        if let Some(idx) = longest_prefix {
-            i += dictionary[idx].len();
-            output.push(idx);
+        // Find the longest prefix in the dictionary, by walking down from the single symbol
+        let end = i.saturating_add(max_lookahead).min(input.len());
+        let idx = dictionary
+            .longest_match(&input[i..end])
+            .expect("No match found in dictionary");
+        let length = dictionary.length(idx);
+        observer.phrase(i, idx, length);
+        i += length;
+        output.push(idx);
+        if i < input.len()
+            && let Some(clear_code) = clear_code
+            && dictionary.is_full()
+        {
+            // the dictionary is full, so start over, and tell the decoder to do the same
+            output.push(clear_code);
+            dictionary.clear();
+        } else if i < input.len()
+            && let Some(index) = dictionary.add(Some(idx), input[i].clone())
+        {
             // if it is ok, add the next entry to the dictionary
-            if i < input.len() {
-                let next_char = input[i].clone();
-                let mut new_entry = dictionary[idx].clone();
-                new_entry.push(next_char);
-                // then add it to the dictionary
-                if !dictionary.contains(&new_entry) {
-                    dictionary.push(new_entry);
-                }
-            }
-        } else {
-            panic!("No match found in dictionary");
+            observer.added(index, length + 1);
         }
     }
-    return output;
+    (output, dictionary.stats())
+}
+
+/// The operations of a dictionary the LZW encoder and decoder rely on, so
+/// that the same loops run on a [Dictionary] and on a [DictionaryStore].
+trait Phrases<T> {
+    /// Returns the number of phrases.
+    fn len(&self) -> usize;
+
+    /// Returns whether the dictionary has to be cleared before adding
+    /// another phrase.
+    fn is_full(&self) -> bool;
+
+    /// Restores the initial dictionary, counting it as a reset.
+    fn clear(&mut self);
+
+    /// Finds the index of the longest phrase that is a prefix of the input.
+    fn longest_match(&self, input: &[T]) -> Option<usize>;
+
+    /// Returns the length of the phrase at the index.
+    fn length(&self, idx: usize) -> usize;
+
+    /// Returns the first symbol of the phrase at the index.
+    fn first(&self, idx: usize) -> T;
+
+    /// Appends the phrase at the index to the output.
+    fn write_phrase(&self, idx: usize, output: &mut Vec<T>);
+
+    /// Adds the phrase at the prefix index extended by the symbol, unless
+    /// it's already present. Returns the index of the added phrase, if it
+    /// was added.
+    fn add(&mut self, prefix: Option<usize>, symbol: T) -> Option<usize>;
+
+    /// Returns the statistics gathered so far.
+    fn stats(&self) -> DictionaryStats;
+}
+
+impl<T: Clone, C: Children<T>> Phrases<T> for Dictionary<'_, T, C> {
+    fn len(&self) -> usize {
+        Dictionary::len(self)
+    }
+
+    fn is_full(&self) -> bool {
+        Dictionary::len(self) >= self.max_size
+    }
+
+    fn clear(&mut self) {
+        self.reset();
+        self.stats.resets += 1;
+    }
+
+    fn longest_match(&self, input: &[T]) -> Option<usize> {
+        Dictionary::longest_match(self, input)
+    }
+
+    fn length(&self, idx: usize) -> usize {
+        Dictionary::length(self, idx)
+    }
+
+    fn first(&self, idx: usize) -> T {
+        Dictionary::first(self, idx).clone()
+    }
+
+    fn write_phrase(&self, idx: usize, output: &mut Vec<T>) {
+        Dictionary::write_phrase(self, idx, output)
+    }
+
+    fn add(&mut self, prefix: Option<usize>, symbol: T) -> Option<usize> {
+        Dictionary::add(self, prefix, symbol)
+    }
+
+    fn stats(&self) -> DictionaryStats {
+        self.stats
+    }
+}
+
+/// Creates the dictionary shared by the LZW encoder and decoder. It starts
+/// with the initial phrases, and grows without bound, or up to the memory
+/// limit, which must leave room for the initial phrases.
+fn new_dictionary<'a, T: Clone, C: Children<T>>(
+    initial: &'a [T],
+    memory_limit_bytes: Option<usize>,
+) -> Dictionary<'a, T, C> {
+    if let Some(limit) = memory_limit_bytes {
+        assert!(
+            size_of_val(initial) <= limit,
+            "The initial dictionary takes {} bytes, over the memory limit of {}",
+            size_of_val(initial),
+            limit
+        );
+    }
+    Dictionary::new(initial, usize::MAX, Eviction::Freeze, memory_limit_bytes)
+}
+
+/// Creates a dictionary bounded to the codes below `2^max_bits - 1`, which
+/// is reserved as the clear code.
+fn new_bounded_dictionary<T: Clone, C: Children<T>>(
+    initial: &[T],
+    max_bits: u32,
+) -> (Dictionary<'_, T, C>, usize) {
+    assert!(max_bits < usize::BITS, "Too many bits for a code");
+    let clear_code = (1 << max_bits) - 1;
+    assert!(
+        initial.len() < clear_code,
+        "No room for phrases in a dictionary of {} bit codes",
+        max_bits
+    );
+    let dictionary = Dictionary::new(initial, clear_code, Eviction::Freeze, None);
+    (dictionary, clear_code)
 }
 
 /// A function to decode a vector of indices using the LZW algorithm
 /// The function takes a vector of indices and an initial dictionary.
 /// It returns a vector of data.
+/// The lookahead used when encoding isn't needed. It only limits which
+/// phrases the encoder picks, while every new entry is still the previous
+/// phrase extended by the first symbol of the next one, which the decoder
+/// reconstructs the same way.
+///
+/// Data encoded with [lzw_encode_with_max_bits] contains clear codes, and
+/// must be decoded with [lzw_decode_with_max_bits] instead.
 ///
 /// ## Arguments
 ///
@@ -86,6 +462,11 @@ pub fn lzw_encode<T: Clone + PartialEq>(
 ///
 /// A vector of data.
 ///
+/// ## Panics
+///
+/// If a code points outside of the dictionary. Use [lzw_decode_checked] to
+/// get an error instead.
+///
 /// ## Example
 ///
 /// ```
@@ -97,61 +478,1121 @@ pub fn lzw_encode<T: Clone + PartialEq>(
 /// assert_eq!(input.to_vec(), decoded);
 /// ```
 pub fn lzw_decode<T: Clone + PartialEq>(input: &[usize], initial: &[T]) -> Vec<T> {
-    let mut dictionary: Vec<Vec<T>> = Vec::with_capacity(initial.len());
-    for i in initial {
-        dictionary.push(vec![i.clone()]);
-    }
-    let mut output: Vec<T> = Vec::new();
+    lzw_decode_with_memory_limit(input, initial, None)
+}
 
-    let mut i = 0;
-    while i < input.len() {
-        // we get the token
-        let idx = input[i];
-        let entry = dictionary[idx].clone();
-        output.extend(entry.clone()); // decode it
-        if i + 1 < input.len() {
-            let next_idx = input[i + 1];
-            if next_idx < dictionary.len() {
-                // if it's a simple token we just add it to the dictionary
-                let next_entry = dictionary[next_idx].clone();
-                let mut new_entry = entry.clone();
-                new_entry.push(next_entry[0].clone());
-                if !dictionary.contains(&new_entry) {
-                    dictionary.push(new_entry);
-                }
-            } else {
-                // well this is the unique case
-                let mut new_entry = entry.clone();
-                new_entry.push(entry[0].clone()); // instead of next_entry[0]
-                if !dictionary.contains(&new_entry) {
-                    dictionary.push(new_entry);
-                }
-            }
-        }
-        i += 1;
-    }
-    return output;
+/// A function to decode a vector of indices using the LZW algorithm, that
+/// validates the codes instead of panicking on invalid ones. A code may only
+/// refer to a phrase already in the dictionary, or to the one being added
+/// by it. Useful when the codes come from an untrusted source.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+///
+/// ## Returns
+///
+/// A vector of data, or an error describing the first invalid code.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{InvalidCodeError, lzw_decode_checked};
+/// assert_eq!(lzw_decode_checked(&[0, 1, 2], b"AB").unwrap(), b"ABAB");
+/// // the third code can refer to at most the phrase the second one adds
+/// assert_eq!(
+///     lzw_decode_checked(&[0, 1, 4], b"AB"),
+///     Err(InvalidCodeError { code: 4, dictionary_size: 4 })
+/// );
+/// ```
+pub fn lzw_decode_checked<T: Clone + PartialEq>(
+    input: &[usize],
+    initial: &[T],
+) -> Result<Vec<T>, InvalidCodeError> {
+    let dictionary: ScannedDictionary<_> = new_dictionary(initial, None);
+    decode(input, dictionary, None, usize::MAX)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// A function to decode a vector of indices encoded by
+/// [lzw_encode_with_memory_limit].
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+/// - `memory_limit_bytes`: The limit on the estimated dictionary memory usage used when encoding.
+///
+/// ## Returns
+///
+/// A vector of data.
+///
+/// ## Panics
+///
+/// If the initial dictionary alone exceeds the memory limit, or a code
+/// points outside of the dictionary.
+pub fn lzw_decode_with_memory_limit<T: Clone + PartialEq>(
+    input: &[usize],
+    initial: &[T],
+    memory_limit_bytes: Option<usize>,
+) -> Vec<T> {
+    let dictionary: ScannedDictionary<_> = new_dictionary(initial, memory_limit_bytes);
+    decode(input, dictionary, None, usize::MAX).expect("Invalid LZW code")
+}
 
-    #[test]
-    fn test_lzw() {
-        let input = b"ABABABABA";
-        let initial = b"AB";
-        let encoded = lzw_encode(input, initial, 4);
-        assert_eq!(encoded, vec![0, 1, 2, 4, 3]);
-    }
+/// A function to decode a vector of indices encoded by
+/// [lzw_encode_with_max_phrase_len].
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+/// - `max_phrase_len`: The maximum length of a dictionary phrase used when encoding.
+///
+/// ## Returns
+///
+/// A vector of data.
+pub fn lzw_decode_with_max_phrase_len<T: Clone + PartialEq>(
+    input: &[usize],
+    initial: &[T],
+    max_phrase_len: usize,
+) -> Vec<T> {
+    let dictionary: ScannedDictionary<_> =
+        new_dictionary(initial, None).with_max_phrase_len(max_phrase_len);
+    decode(input, dictionary, None, usize::MAX).expect("Invalid LZW code")
+}
 
-    #[test]
-    fn test_lzw_decode() {
-        let input = b"ABABABABA";
+/// A function to decode a vector of indices encoded by
+/// [lzw_encode_with_max_bits]. The dictionary is reset whenever the clear
+/// code is read, exactly where the encoder reset its own.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+/// - `max_bits`: The number of bits every code fits in, used when encoding.
+///
+/// ## Returns
+///
+/// A vector of data.
+///
+/// ## Panics
+///
+/// If the initial dictionary leaves no room for a phrase below the clear
+/// code, or `max_bits` is not smaller than the number of bits in a `usize`.
+pub fn lzw_decode_with_max_bits<T: Clone + PartialEq>(
+    input: &[usize],
+    initial: &[T],
+    max_bits: u32,
+) -> Vec<T> {
+    let (dictionary, clear_code): (ScannedDictionary<_>, _) =
+        new_bounded_dictionary(initial, max_bits);
+    decode(input, dictionary, Some(clear_code), usize::MAX).expect("Invalid LZW code")
+}
+
+/// A function to decode only the beginning of a vector of indices.
+/// Decoding stops as soon as `n` elements are produced.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+/// - `n`: The number of elements to decode.
+///
+/// ## Returns
+///
+/// The first `n` elements of the data, or all of it if it's shorter, or an
+/// error describing the first invalid code decoded.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_prefix, lzw_encode};
+/// let input = b"ABABABABA";
+/// let encoded = lzw_encode(input, b"AB", 4);
+/// assert_eq!(lzw_decode_prefix(&encoded, b"AB", 5).unwrap(), b"ABABA");
+/// ```
+pub fn lzw_decode_prefix<T: Clone + PartialEq>(
+    input: &[usize],
+    initial: &[T],
+    n: usize,
+) -> Result<Vec<T>, InvalidCodeError> {
+    let dictionary: ScannedDictionary<_> = new_dictionary(initial, None);
+    decode(input, dictionary, None, n)
+}
+
+/// A function to encode a slice of data using the LZW algorithm, that also
+/// builds an index of where every code ends in the data, for
+/// [lzw_seek_decode]. Produces the same codes as [lzw_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data, and the offset index.
+pub fn lzw_encode_indexed<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+) -> (Vec<usize>, OffsetIndex) {
+    let dictionary: Dictionary<_> = new_dictionary(initial, None);
+    let mut lengths = PhraseLengths(Vec::new());
+    let (output, _) = encode(input, dictionary, max_lookahead, None, &mut lengths);
+    (output, OffsetIndex::from_lengths(&lengths.0))
+}
+
+/// Collects the length of the phrase of every code, for the offset index.
+struct PhraseLengths(Vec<usize>);
+
+impl Observer for PhraseLengths {
+    fn phrase(&mut self, _position: usize, _index: usize, length: usize) {
+        self.0.push(length);
+    }
+}
+
+/// A function to decode a vector of indices starting at an offset in the
+/// decoded data. The dictionary is rebuilt from the codes before the one
+/// containing the offset, without decoding their phrases.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+/// - `index`: The offset index built by [lzw_encode_indexed].
+/// - `output_offset`: The offset in the decoded data to start at.
+///
+/// ## Returns
+///
+/// The decoded data from the offset onwards, which is empty if the offset is
+/// past the end.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_encode_indexed, lzw_seek_decode};
+/// let input = b"ABABABABA";
+/// let (encoded, index) = lzw_encode_indexed(input, b"AB", 4);
+/// assert_eq!(lzw_seek_decode(&encoded, b"AB", &index, 4), b"ABABA");
+/// ```
+pub fn lzw_seek_decode<T: Clone + PartialEq>(
+    input: &[usize],
+    initial: &[T],
+    index: &OffsetIndex,
+    output_offset: usize,
+) -> Vec<T> {
+    let Some((code, start)) = index.locate(output_offset) else {
+        return Vec::new();
+    };
+    let mut dictionary: ScannedDictionary<_> = new_dictionary(initial, None);
+    for i in 0..code {
+        let next_idx = input[i + 1];
+        let next_char = if next_idx < dictionary.len() {
+            dictionary.first(next_idx).clone()
+        } else {
+            dictionary.first(input[i]).clone()
+        };
+        dictionary.add(Some(input[i]), next_char);
+    }
+    let mut output =
+        decode(&input[code..], dictionary, None, usize::MAX).expect("Invalid LZW code");
+    output.drain(..output_offset - start);
+    output
+}
+
+/// Decodes the codes, validating every code against the dictionary at the
+/// time it's decoded. The phrase a code adds is added before the next code
+/// is decoded, so a code referring to it is valid too.
+fn decode<T: Clone, D: Phrases<T>>(
+    input: &[usize],
+    mut dictionary: D,
+    clear_code: Option<usize>,
+    n: usize,
+) -> Result<Vec<T>, InvalidCodeError> {
+    let mut output: Vec<T> = Vec::with_capacity(output_estimate(input.len()).min(n));
+
+    for (i, idx) in input.iter().enumerate() {
+        if output.len() >= n {
+            break;
+        }
+        if Some(*idx) == clear_code {
+            dictionary.clear();
+            continue;
+        }
+        if *idx >= dictionary.len() {
+            return Err(InvalidCodeError {
+                code: *idx,
+                dictionary_size: dictionary.len(),
+            });
+        }
+        dictionary.write_phrase(*idx, &mut output); // decode it
+        // the encoder adds nothing right before clearing the dictionary
+        if let Some(next_idx) = input.get(i + 1)
+            && Some(*next_idx) != clear_code
+        {
+            let next_char = if *next_idx < dictionary.len() {
+                // if it's a simple token we just add it to the dictionary
+                dictionary.first(*next_idx)
+            } else {
+                // well this is the unique case
+                dictionary.first(*idx) // instead of next_entry[0]
+            };
+            dictionary.add(Some(*idx), next_char);
+        }
+    }
+    output.truncate(n);
+    Ok(output)
+}
+
+/// An incremental LZW encoder. Data can be fed to it in chunks, and a code is
+/// produced as soon as its phrase can't be extended any further, so the
+/// phrase still being matched at the end of the data is only emitted by
+/// [LZWEncoder::finish]. Produces the same codes as [lzw_encode].
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{LZWEncoder, lzw_decode, lzw_encode};
+/// let mut encoder = LZWEncoder::new(b"AB", 4);
+/// let mut encoded = Vec::new();
+/// encoder.push(b"ABABA", &mut encoded);
+/// encoder.push(b"BABA", &mut encoded);
+/// encoder.finish(&mut encoded);
+/// assert_eq!(encoded, lzw_encode(b"ABABABABA", b"AB", 4));
+/// assert_eq!(lzw_decode(&encoded, b"AB"), b"ABABABABA");
+/// ```
+pub struct LZWEncoder<'a, T> {
+    dictionary: Dictionary<'a, T>,
+    max_lookahead: usize,
+    /// The node and the length of the phrase matched so far
+    current: Option<(usize, usize)>,
+}
+
+impl<'a, T: Clone + Eq + Hash> LZWEncoder<'a, T> {
+    /// Creates a new incremental LZW encoder
+    ///
+    /// ## Arguments
+    ///
+    /// - `initial`: An initial dictionary to start encoding.
+    /// - `max_lookahead`: The maximum lookahead size.
+    pub fn new(initial: &'a [T], max_lookahead: usize) -> Self {
+        LZWEncoder {
+            dictionary: new_dictionary(initial, None),
+            max_lookahead,
+            current: None,
+        }
+    }
+
+    /// Feeds more data into the encoder
+    ///
+    /// ## Arguments
+    ///
+    /// - `input`: The next chunk of data to be encoded.
+    /// - `out`: The output buffer to store finished codes in.
+    ///
+    /// ## Panics
+    ///
+    /// If a symbol of the input is missing from the initial dictionary.
+    pub fn push(&mut self, input: &[T], out: &mut Vec<usize>) {
+        for symbol in input {
+            if let Some((node, length)) = self.current
+                && length < self.max_lookahead
+                && let Some(child) = self.dictionary.find(Some(node), symbol)
+            {
+                // the phrase goes on
+                self.current = Some((child, length + 1));
+                continue;
+            }
+            if let Some(index) = self.current_index() {
+                out.push(index);
+                self.dictionary.add(Some(index), symbol.clone());
+            }
+            let root = self
+                .dictionary
+                .find(None, symbol)
+                .expect("No match found in dictionary");
+            self.current = Some((root, 1));
+        }
+    }
+
+    /// Emits the code of the phrase matched at the end of the data
+    ///
+    /// ## Arguments
+    ///
+    /// - `out`: The output buffer to store the last code in.
+    pub fn finish(self, out: &mut Vec<usize>) {
+        out.extend(self.current_index());
+    }
+
+    fn current_index(&self) -> Option<usize> {
+        self.current.map(|(node, _)| {
+            self.dictionary.nodes[node]
+                .index
+                .expect("Phrases are never evicted")
+        })
+    }
+}
+
+/// An entry of an LZW stream produced by [lzw_encode_escaped].
+/// Symbols that are not present in the dictionary are escaped, and emitted as
+/// literals. A serializer can represent the escape as a reserved code,
+/// followed by the raw symbol.
+#[derive(Debug, PartialEq)]
+pub enum LZWentry<T> {
+    /// An index into the dictionary
+    Code(usize),
+    /// An escaped symbol, that was not present in the dictionary
+    Literal(T),
+}
+
+/// A function to encode a slice of data using the LZW algorithm, escaping
+/// symbols missing from the dictionary instead of panicking. Escaped symbols
+/// are added to the dictionary, so the initial dictionary can be incomplete,
+/// or even empty.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+///
+/// ## Returns
+///
+/// A vector of LZW entries representing the encoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{LZWentry, lzw_encode_escaped};
+/// let input = b"ABABABABA";
+/// let encoded = lzw_encode_escaped(input, b"A", 4);
+/// assert_eq!(
+///     encoded,
+///     vec![
+///         LZWentry::Code(0),
+///         LZWentry::Literal(b'B'),
+///         LZWentry::Code(1),
+///         LZWentry::Code(4),
+///         LZWentry::Code(3),
+///     ]
+/// );
+/// ```
+pub fn lzw_encode_escaped<T: Clone + Eq + Hash>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+) -> Vec<LZWentry<T>> {
+    let mut dictionary: Dictionary<_> = new_dictionary(initial, None);
+    let mut output = Vec::new();
+
+    let mut i = 0;
+    while i < input.len() {
+        let end = i.saturating_add(max_lookahead).min(input.len());
+        let idx = match dictionary.longest_match(&input[i..end]) {
+            Some(idx) => {
+                output.push(LZWentry::Code(idx));
+                idx
+            }
+            None => {
+                // the symbol is unknown, so we escape it and learn it
+                output.push(LZWentry::Literal(input[i].clone()));
+                dictionary
+                    .add(None, input[i].clone())
+                    .expect("The dictionary is unbounded")
+            }
+        };
+        i += dictionary.length(idx);
+        // if it is ok, add the next entry to the dictionary
+        if i < input.len() {
+            dictionary.add(Some(idx), input[i].clone());
+        }
+    }
+    return output;
+}
+
+/// A function to decode a vector of LZW entries produced by
+/// [lzw_encode_escaped].
+///
+/// ## Arguments
+///
+/// - `input`: A vector of LZW entries to be decoded.
+/// - `initial`: An initial dictionary to start decoding.
+///
+/// ## Returns
+///
+/// A vector of data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_escaped, lzw_encode_escaped};
+/// let input = b"ABABABABA";
+/// let encoded = lzw_encode_escaped(input, b"", 4);
+/// let decoded = lzw_decode_escaped(&encoded, b"");
+/// assert_eq!(input.to_vec(), decoded);
+/// ```
+pub fn lzw_decode_escaped<T: Clone + PartialEq>(input: &[LZWentry<T>], initial: &[T]) -> Vec<T> {
+    let mut dictionary: ScannedDictionary<_> = new_dictionary(initial, None);
+    let mut output: Vec<T> = Vec::with_capacity(output_estimate(input.len()));
+
+    for (i, token) in input.iter().enumerate() {
+        let idx = match token {
+            LZWentry::Code(idx) => *idx,
+            // learn the escaped symbol, same as the encoder
+            LZWentry::Literal(symbol) => dictionary
+                .add(None, symbol.clone())
+                .expect("The dictionary is unbounded"),
+        };
+        dictionary.write_phrase(idx, &mut output); // decode it
+        let next_char = match input.get(i + 1) {
+            Some(LZWentry::Literal(symbol)) => symbol.clone(),
+            Some(LZWentry::Code(next_idx)) if *next_idx < dictionary.len() => {
+                dictionary.first(*next_idx).clone()
+            }
+            // the unique case, the next entry is the one we are about to add
+            Some(LZWentry::Code(_)) => dictionary.first(idx).clone(),
+            None => continue,
+        };
+        dictionary.add(Some(idx), next_char);
+    }
+    return output;
+}
+
+/// Storage for the phrases of an LZW dictionary, which never resets. Every
+/// phrase is stored as the index of its prefix, and its last symbol, so an
+/// implementation only has to append entries and look them up. The default
+/// is [MemoryStore], but an implementation can keep the entries on disk, for
+/// example in a memory mapped file, letting the dictionary of a very long
+/// stream grow beyond the available memory.
+///
+/// A disk backed implementation should index the entries by their prefix
+/// and symbol, as [DictionaryStore::find] is called for every symbol of the
+/// input while encoding, and for every code while decoding.
+pub trait DictionaryStore<T> {
+    /// Appends the phrase at the prefix index extended by the symbol, or just
+    /// the symbol if there is no prefix.
+    ///
+    /// ## Returns
+    ///
+    /// The index of the new phrase, which is the number of phrases before it.
+    fn push(&mut self, prefix: Option<usize>, symbol: T) -> usize;
+
+    /// Finds the phrase extending the phrase at the prefix index by the
+    /// symbol, or the phrase consisting of just the symbol if there is no
+    /// prefix. If there are several, the first one is returned.
+    fn find(&self, prefix: Option<usize>, symbol: &T) -> Option<usize>;
+
+    /// Returns the prefix index, and the last symbol of the phrase at the index.
+    fn entry(&self, index: usize) -> (Option<usize>, T);
+
+    /// Returns the number of phrases.
+    fn len(&self) -> usize;
+
+    /// Returns whether there are no phrases.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The in-memory [DictionaryStore], indexing the phrases by their prefix and
+/// last symbol.
+pub struct MemoryStore<T> {
+    entries: Vec<(Option<usize>, T)>,
+    /// The index of the first phrase with each prefix and last symbol
+    index: HashMap<(Option<usize>, T), usize>,
+}
+
+impl<T> MemoryStore<T> {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        MemoryStore {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Default for MemoryStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Eq + Hash> DictionaryStore<T> for MemoryStore<T> {
+    fn push(&mut self, prefix: Option<usize>, symbol: T) -> usize {
+        let index = self.entries.len();
+        self.index.entry((prefix, symbol.clone())).or_insert(index);
+        self.entries.push((prefix, symbol));
+        index
+    }
+
+    fn find(&self, prefix: Option<usize>, symbol: &T) -> Option<usize> {
+        // the key is owned, so the symbol has to be cloned for the lookup
+        self.index.get(&(prefix, symbol.clone())).copied()
+    }
+
+    fn entry(&self, index: usize) -> (Option<usize>, T) {
+        self.entries[index].clone()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// A function to encode a slice of data using the LZW algorithm, keeping the
+/// dictionary in the provided store. The dictionary is never reset, and
+/// grows for as long as the store allows. Produces the same codes as
+/// [lzw_encode].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `initial`: An initial dictionary to start encoding, added to the store.
+/// - `max_lookahead`: The maximum lookahead size.
+/// - `store`: The empty store to keep the dictionary in.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded data, or an error naming
+/// the first symbol missing from the initial dictionary.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{DictionaryStore, MemoryStore, lzw_encode, lzw_encode_with_store};
+/// let mut store = MemoryStore::new();
+/// let encoded = lzw_encode_with_store(b"ABABABABA", b"AB", 4, &mut store).unwrap();
+/// assert_eq!(encoded, lzw_encode(b"ABABABABA", b"AB", 4));
+/// assert_eq!(store.len(), 6);
+/// ```
+pub fn lzw_encode_with_store<T: Clone + PartialEq, S: DictionaryStore<T>>(
+    input: &[T],
+    initial: &[T],
+    max_lookahead: usize,
+    store: &mut S,
+) -> Result<Vec<usize>, UnknownSymbolError<T>> {
+    check_symbols(input, initial)?;
+    let dictionary = StoreDictionary::new(initial, store);
+    Ok(encode(input, dictionary, max_lookahead, None, &mut ()).0)
+}
+
+/// A function to decode a vector of indices encoded by
+/// [lzw_encode_with_store], keeping the dictionary in the provided store.
+///
+/// ## Arguments
+///
+/// - `input`: A vector of indices to be decoded.
+/// - `initial`: An initial dictionary to start decoding, added to the store.
+/// - `store`: The empty store to keep the dictionary in.
+///
+/// ## Returns
+///
+/// A vector of data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{MemoryStore, lzw_decode_with_store, lzw_encode};
+/// let encoded = lzw_encode(b"ABABABABA", b"AB", 4);
+/// let decoded = lzw_decode_with_store(&encoded, b"AB", &mut MemoryStore::new());
+/// assert_eq!(decoded, b"ABABABABA");
+/// ```
+pub fn lzw_decode_with_store<T: Clone, S: DictionaryStore<T>>(
+    input: &[usize],
+    initial: &[T],
+    store: &mut S,
+) -> Vec<T> {
+    let dictionary = StoreDictionary::new(initial, store);
+    decode(input, dictionary, None, usize::MAX).expect("Invalid LZW code")
+}
+
+/// A dictionary kept in a [DictionaryStore]. The store only ever grows, so
+/// it's never full, and never cleared.
+struct StoreDictionary<'s, S> {
+    store: &'s mut S,
+}
+
+impl<'s, S> StoreDictionary<'s, S> {
+    /// Adds the initial phrases to the empty store.
+    fn new<T: Clone>(initial: &[T], store: &'s mut S) -> Self
+    where
+        S: DictionaryStore<T>,
+    {
+        for symbol in initial {
+            store.push(None, symbol.clone());
+        }
+        StoreDictionary { store }
+    }
+}
+
+impl<T, S: DictionaryStore<T>> Phrases<T> for StoreDictionary<'_, S> {
+    fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    fn is_full(&self) -> bool {
+        false
+    }
+
+    fn clear(&mut self) {
+        unreachable!("A store is only used without a clear code");
+    }
+
+    fn longest_match(&self, input: &[T]) -> Option<usize> {
+        let mut longest = None;
+        for symbol in input {
+            match self.store.find(longest, symbol) {
+                Some(child) => longest = Some(child),
+                None => break,
+            }
+        }
+        longest
+    }
+
+    fn length(&self, idx: usize) -> usize {
+        let mut length = 1;
+        let mut current = self.store.entry(idx).0;
+        while let Some(prefix) = current {
+            length += 1;
+            current = self.store.entry(prefix).0;
+        }
+        length
+    }
+
+    fn first(&self, idx: usize) -> T {
+        let mut index = idx;
+        loop {
+            match self.store.entry(index) {
+                (Some(prefix), _) => index = prefix,
+                (None, symbol) => break symbol,
+            }
+        }
+    }
+
+    fn write_phrase(&self, idx: usize, output: &mut Vec<T>) {
+        let start = output.len();
+        let mut current = Some(idx);
+        while let Some(index) = current {
+            let (prefix, symbol) = self.store.entry(index);
+            output.push(symbol);
+            current = prefix;
+        }
+        output[start..].reverse();
+    }
+
+    fn add(&mut self, prefix: Option<usize>, symbol: T) -> Option<usize> {
+        match self.store.find(prefix, &symbol) {
+            Some(_) => None,
+            None => Some(self.store.push(prefix, symbol)),
+        }
+    }
+
+    fn stats(&self) -> DictionaryStats {
+        DictionaryStats::default()
+    }
+}
+
+/// The largest code width of the LZW flavours of GIF and TIFF.
+const IMAGE_MAX_BITS: u32 = 12;
+
+/// The conventions of the LZW flavour of an image format. The clear code
+/// follows the symbols, the end of information code follows the clear code,
+/// and the phrases follow both.
+struct ImageFormat {
+    /// The number of bits of a symbol
+    symbol_bits: u32,
+    /// Whether codes are packed starting from the least significant bit of a byte
+    lsb_first: bool,
+    /// Whether the code width grows one code before it has to, as in TIFF
+    early_change: bool,
+    /// The highest code assigned to a phrase, before the encoder clears the dictionary
+    max_code: usize,
+}
+
+impl ImageFormat {
+    const TIFF: ImageFormat = ImageFormat {
+        symbol_bits: 8,
+        lsb_first: false,
+        early_change: true,
+        max_code: (1 << IMAGE_MAX_BITS) - 3,
+    };
+
+    fn gif(min_code_size: u8) -> Self {
+        assert!(
+            (2..=8).contains(&min_code_size),
+            "Invalid GIF minimum code size {}",
+            min_code_size
+        );
+        ImageFormat {
+            symbol_bits: min_code_size as u32,
+            lsb_first: true,
+            early_change: false,
+            max_code: (1 << IMAGE_MAX_BITS) - 2,
+        }
+    }
+
+    fn clear_code(&self) -> usize {
+        1 << self.symbol_bits
+    }
+
+    fn end_code(&self) -> usize {
+        self.clear_code() + 1
+    }
+
+    /// The width of the next code, given the next code to be assigned to a
+    /// phrase by the encoder.
+    fn width(&self, next_code: usize) -> u32 {
+        let largest = if self.early_change {
+            next_code
+        } else {
+            next_code - 1
+        };
+        (usize::BITS - largest.leading_zeros()).clamp(self.symbol_bits + 1, IMAGE_MAX_BITS)
+    }
+
+    /// Converts an index into the dictionary into a code, skipping the clear
+    /// and end of information codes.
+    fn code(&self, index: usize) -> usize {
+        if index < self.clear_code() {
+            index
+        } else {
+            index + 2
+        }
+    }
+
+    fn index(&self, code: usize) -> usize {
+        if code < self.clear_code() {
+            code
+        } else {
+            code - 2
+        }
+    }
+
+    fn symbols(&self) -> Vec<u8> {
+        (0..self.clear_code()).map(|symbol| symbol as u8).collect()
+    }
+}
+
+/// Packs codes of varying width into bytes.
+struct CodeWriter {
+    output: Vec<u8>,
+    buffer: usize,
+    bits: u32,
+    lsb_first: bool,
+}
+
+impl CodeWriter {
+    fn new(lsb_first: bool) -> Self {
+        CodeWriter {
+            output: Vec::new(),
+            buffer: 0,
+            bits: 0,
+            lsb_first,
+        }
+    }
+
+    fn write(&mut self, code: usize, width: u32) {
+        if self.lsb_first {
+            self.buffer |= code << self.bits;
+            self.bits += width;
+            while self.bits >= 8 {
+                self.output.push(self.buffer as u8);
+                self.buffer >>= 8;
+                self.bits -= 8;
+            }
+        } else {
+            self.buffer = (self.buffer << width) | code;
+            self.bits += width;
+            while self.bits >= 8 {
+                self.bits -= 8;
+                self.output.push((self.buffer >> self.bits) as u8);
+            }
+            self.buffer &= (1 << self.bits) - 1;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            // pad the last byte with zeros
+            self.write(0, 8 - self.bits);
+        }
+        self.output
+    }
+}
+
+/// Unpacks codes of varying width from bytes.
+struct CodeReader<'a> {
+    input: &'a [u8],
+    buffer: usize,
+    bits: u32,
+    lsb_first: bool,
+}
+
+impl<'a> CodeReader<'a> {
+    fn new(input: &'a [u8], lsb_first: bool) -> Self {
+        CodeReader {
+            input,
+            buffer: 0,
+            bits: 0,
+            lsb_first,
+        }
+    }
+
+    /// Reads the next code, or returns None if the input ran out.
+    fn read(&mut self, width: u32) -> Option<usize> {
+        while self.bits < width {
+            let (byte, rest) = self.input.split_first()?;
+            self.input = rest;
+            if self.lsb_first {
+                self.buffer |= (*byte as usize) << self.bits;
+            } else {
+                self.buffer = (self.buffer << 8) | *byte as usize;
+            }
+            self.bits += 8;
+        }
+        let mask = (1 << width) - 1;
+        self.bits -= width;
+        if self.lsb_first {
+            let code = self.buffer & mask;
+            self.buffer >>= width;
+            Some(code)
+        } else {
+            let code = (self.buffer >> self.bits) & mask;
+            self.buffer &= (1 << self.bits) - 1;
+            Some(code)
+        }
+    }
+}
+
+fn encode_image(input: &[u8], format: &ImageFormat) -> Vec<u8> {
+    let symbols = format.symbols();
+    let dictionary: Dictionary<_> = Dictionary::new(
+        &symbols,
+        format.index(format.max_code) + 1,
+        Eviction::Freeze,
+        None,
+    );
+    let (codes, _) = encode(input, dictionary, usize::MAX, Some(usize::MAX), &mut ());
+
+    let first_code = format.end_code() + 1;
+    let mut writer = CodeWriter::new(format.lsb_first);
+    writer.write(format.clear_code(), format.width(first_code));
+    let mut next_code = first_code;
+    for code in codes {
+        let width = format.width(next_code);
+        if code == usize::MAX {
+            writer.write(format.clear_code(), width);
+            next_code = first_code;
+        } else {
+            writer.write(format.code(code), width);
+            // every code but the last is followed by a new phrase
+            next_code += 1;
+        }
+    }
+    writer.write(format.end_code(), format.width(next_code));
+    writer.finish()
+}
+
+fn decode_image(input: &[u8], format: &ImageFormat) -> Vec<u8> {
+    let first_code = format.end_code() + 1;
+    let mut reader = CodeReader::new(input, format.lsb_first);
+    let mut codes = Vec::new();
+    let mut next_code = first_code;
+    while let Some(code) = reader.read(format.width(next_code)) {
+        if code == format.clear_code() {
+            codes.push(usize::MAX);
+            next_code = first_code;
+        } else if code == format.end_code() {
+            break;
+        } else {
+            codes.push(format.index(code));
+            next_code += 1;
+        }
+    }
+
+    let symbols = format.symbols();
+    // other encoders may fill the dictionary up to the largest code before clearing it
+    let dictionary: Dictionary<_> = Dictionary::new(
+        &symbols,
+        format.index((1 << IMAGE_MAX_BITS) - 1) + 1,
+        Eviction::Freeze,
+        None,
+    );
+    decode(&codes, dictionary, Some(usize::MAX), usize::MAX).expect("Invalid LZW code")
+}
+
+/// A function to encode a slice of data into an LZW bit stream, as stored in
+/// the image data of a GIF file. Codes are packed starting from the least
+/// significant bit, grow from `min_code_size + 1` up to 12 bits, and the
+/// stream starts with the clear code, and ends with the end of information
+/// code. Splitting the stream into sub-blocks is left to the caller.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of symbols to be encoded, each below `2^min_code_size`.
+/// - `min_code_size`: The number of bits of a symbol, between 2 and 8.
+///
+/// ## Returns
+///
+/// The encoded bit stream.
+///
+/// ## Panics
+///
+/// If `min_code_size` is out of range, or a symbol doesn't fit in it.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_gif, lzw_encode_gif};
+/// let input = [1, 1, 1, 1, 2, 2, 2, 0];
+/// let encoded = lzw_encode_gif(&input, 2);
+/// assert_eq!(lzw_decode_gif(&encoded, 2), input);
+/// ```
+pub fn lzw_encode_gif(input: &[u8], min_code_size: u8) -> Vec<u8> {
+    encode_image(input, &ImageFormat::gif(min_code_size))
+}
+
+/// A function to decode an LZW bit stream, as stored in the image data of a
+/// GIF file, once joined from its sub-blocks. Decoding stops at the end of
+/// information code, or at the end of the input.
+///
+/// ## Arguments
+///
+/// - `input`: The encoded bit stream.
+/// - `min_code_size`: The number of bits of a symbol, between 2 and 8.
+///
+/// ## Returns
+///
+/// The decoded symbols.
+///
+/// ## Panics
+///
+/// If `min_code_size` is out of range, or the stream refers to a phrase
+/// that isn't in the dictionary.
+pub fn lzw_decode_gif(input: &[u8], min_code_size: u8) -> Vec<u8> {
+    decode_image(input, &ImageFormat::gif(min_code_size))
+}
+
+/// A function to encode a slice of bytes into an LZW bit stream, as stored
+/// in a strip of a TIFF file. Codes are packed starting from the most
+/// significant bit, grow from 9 up to 12 bits one code earlier than they
+/// have to, and the stream starts with the clear code, and ends with the end
+/// of information code.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of bytes to be encoded.
+///
+/// ## Returns
+///
+/// The encoded bit stream.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::lzw::{lzw_decode_tiff, lzw_encode_tiff};
+/// let input = b"ABABABABA";
+/// let encoded = lzw_encode_tiff(input);
+/// assert_eq!(lzw_decode_tiff(&encoded), input);
+/// ```
+pub fn lzw_encode_tiff(input: &[u8]) -> Vec<u8> {
+    encode_image(input, &ImageFormat::TIFF)
+}
+
+/// A function to decode an LZW bit stream, as stored in a strip of a TIFF
+/// file. Decoding stops at the end of information code, or at the end of
+/// the input.
+///
+/// ## Arguments
+///
+/// - `input`: The encoded bit stream.
+///
+/// ## Returns
+///
+/// The decoded bytes.
+///
+/// ## Panics
+///
+/// If the stream refers to a phrase that isn't in the dictionary.
+pub fn lzw_decode_tiff(input: &[u8]) -> Vec<u8> {
+    decode_image(input, &ImageFormat::TIFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_round_trip, gen_data};
+    use std::cell::Cell;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A symbol that counts how many times it was compared.
+    #[derive(Clone, Debug, Eq)]
+    struct Counted(u8);
+
+    impl PartialEq for Counted {
+        fn eq(&self, other: &Self) -> bool {
+            COMPARISONS.with(|c| c.set(c.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    impl Hash for Counted {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    /// The straightforward encoder, storing whole phrases.
+    fn reference_encode(input: &[u8], initial: &[u8], max_lookahead: usize) -> Vec<usize> {
+        let mut dictionary: Vec<Vec<u8>> = initial.iter().map(|i| vec![*i]).collect();
+        let mut output = Vec::new();
+        let mut i = 0;
+        while i < input.len() {
+            let mut longest: Option<usize> = None;
+            for (idx, entry) in dictionary.iter().enumerate() {
+                if entry.len() > max_lookahead || !input[i..].starts_with(entry) {
+                    continue;
+                }
+                if longest.is_none_or(|l| entry.len() > dictionary[l].len()) {
+                    longest = Some(idx);
+                }
+            }
+            let idx = longest.unwrap();
+            i += dictionary[idx].len();
+            output.push(idx);
+            if i < input.len() {
+                let mut new_entry = dictionary[idx].clone();
+                new_entry.push(input[i]);
+                if !dictionary.contains(&new_entry) {
+                    dictionary.push(new_entry);
+                }
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_lzw() {
+        let input = b"ABABABABA";
+        let initial = b"AB";
+        let encoded = lzw_encode(input, initial, 4);
+        assert_eq!(encoded, vec![0, 1, 2, 4, 3]);
+    }
+
+    #[test]
+    fn test_lzw_decode() {
+        let input = b"ABABABABA";
         let initial = b"AB";
         let encoded = lzw_encode(input, initial, 4);
         let decoded = lzw_decode(&encoded, initial);
-        assert_eq!(input.to_vec(), decoded);
+        assert_round_trip(&decoded, input);
     }
 
     #[test]
@@ -161,6 +1602,410 @@ mod tests {
         let encoded = lzw_encode(input, initial, 4);
         assert!(encoded.len() < input.len());
         let decoded = lzw_decode(&encoded, initial);
-        assert_eq!(input, decoded.as_slice());
+        assert_round_trip(&decoded, input);
+    }
+
+    #[test]
+    fn test_lzw_small_lookahead() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = gen_data(8, 5000, 0.9);
+        let unbounded = lzw_encode(&input, &initial, usize::MAX);
+        for max_lookahead in 1..=4 {
+            let encoded = lzw_encode(&input, &initial, max_lookahead);
+            assert!(encoded.len() >= unbounded.len());
+            assert_round_trip(&lzw_decode(&encoded, &initial), &input);
+        }
+    }
+
+    #[test]
+    fn test_lzw_max_phrase_len() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = vec![b'a'; 10000];
+        for max_phrase_len in [1, 2, 5] {
+            let encoded = lzw_encode_with_max_phrase_len(&input, &initial, 64, max_phrase_len);
+            // no code stands for more than the cap
+            assert!(encoded.len() >= input.len().div_ceil(max_phrase_len));
+            let decoded = lzw_decode_with_max_phrase_len(&encoded, &initial, max_phrase_len);
+            assert_round_trip(&decoded, &input);
+        }
+
+        let mut dictionary: Dictionary<_> = new_dictionary(b"ab", None).with_max_phrase_len(3);
+        let mut idx = 0;
+        while let Some(child) = dictionary.add(Some(idx), b'a') {
+            idx = child;
+        }
+        assert!((0..dictionary.len()).all(|idx| dictionary.length(idx) <= 3));
+        assert_eq!(dictionary.length(idx), 3);
+    }
+
+    /// A store keeping whole phrases, and scanning all of them, like the
+    /// simplest disk backend would.
+    struct PhraseStore(Vec<(Option<usize>, Vec<u8>)>);
+
+    impl DictionaryStore<u8> for PhraseStore {
+        fn push(&mut self, prefix: Option<usize>, symbol: u8) -> usize {
+            let mut phrase = prefix.map_or_else(Vec::new, |prefix| self.0[prefix].1.clone());
+            phrase.push(symbol);
+            self.0.push((prefix, phrase));
+            self.0.len() - 1
+        }
+
+        fn find(&self, prefix: Option<usize>, symbol: &u8) -> Option<usize> {
+            self.0
+                .iter()
+                .position(|(other, phrase)| *other == prefix && phrase.last() == Some(symbol))
+        }
+
+        fn entry(&self, index: usize) -> (Option<usize>, u8) {
+            let (prefix, phrase) = &self.0[index];
+            (*prefix, *phrase.last().unwrap())
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn test_lzw_store() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = gen_data(12, 5000, 0.8);
+        let encoded = lzw_encode(&input, &initial, 64);
+
+        let mut store = PhraseStore(Vec::new());
+        assert_eq!(
+            lzw_encode_with_store(&input, &initial, 64, &mut store),
+            Ok(encoded.clone())
+        );
+        let mut decode_store = PhraseStore(Vec::new());
+        let decoded = lzw_decode_with_store(&encoded, &initial, &mut decode_store);
+        assert_round_trip(&decoded, &input);
+        // both sides built the same dictionary
+        assert_eq!(store.0, decode_store.0);
+
+        let decoded = lzw_decode_with_store(&encoded, &initial, &mut MemoryStore::new());
+        assert_round_trip(&decoded, &input);
+
+        // a repeated phrase is found at its first index
+        let mut store = MemoryStore::new();
+        for symbol in b"ABA" {
+            store.push(None, *symbol);
+        }
+        store.push(Some(1), b'A');
+        assert_eq!(store.find(None, &b'A'), Some(0));
+        assert_eq!(store.find(Some(1), &b'A'), Some(3));
+        assert_eq!(store.find(Some(0), &b'A'), None);
+        assert_eq!(store.entry(3), (Some(1), b'A'));
+
+        assert_eq!(
+            lzw_encode_with_store(b"ABACABA", b"AB", 4, &mut MemoryStore::new()),
+            Err(UnknownSymbolError {
+                symbol: b'C',
+                position: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_lzw_encode_checked() {
+        let initial: Vec<u8> = (b'a'..=b'z').collect();
+        let input = b"the quick brown fox";
+        let err = lzw_encode_checked(input, &initial, 16).unwrap_err();
+        assert_eq!(
+            err,
+            UnknownSymbolError {
+                symbol: b' ',
+                position: 3
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Symbol 32 at position 3 is not in the initial dictionary"
+        );
+        let input = b"thequickbrownfox";
+        assert_eq!(
+            lzw_encode_checked(input, &initial, 16),
+            Ok(lzw_encode(input, &initial, 16))
+        );
+    }
+
+    #[test]
+    fn test_lzw_decode_checked() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = gen_data(5, 2000, 0.8);
+        let mut encoded = lzw_encode(&input, &initial, 64);
+        assert_eq!(lzw_decode_checked(&encoded, &initial), Ok(input.clone()));
+
+        // the last code may refer to at most the phrase added by the one before it
+        let last = encoded.len() - 1;
+        let dictionary_size = initial.len() + last;
+        encoded[last] = dictionary_size;
+        assert_eq!(
+            lzw_decode_checked(&encoded, &initial),
+            Err(InvalidCodeError {
+                code: dictionary_size,
+                dictionary_size
+            })
+        );
+        assert!(lzw_decode_prefix(&encoded, &initial, usize::MAX).is_err());
+        assert!(lzw_decode_prefix(&encoded, &initial, 10).is_ok());
+    }
+
+    #[test]
+    fn test_lzw_max_bits_clear_then_kwkwk() {
+        // with 3 bit codes, the initial dictionary leaves room for four
+        // phrases, which are full once ABABABA is encoded
+        let input = b"ABABABA BBBBBB";
+        let initial = b"AB ";
+        let encoded = lzw_encode_with_max_bits(input, initial, 255, 3);
+        let clear = encoded.iter().position(|code| *code == 7).unwrap();
+        // the first phrase after the clear is B, immediately followed by a
+        // reference to BB, the very phrase still being built
+        assert_eq!(encoded[clear + 1], 1);
+        assert_eq!(encoded[clear + 2], 3);
+        assert_round_trip(&lzw_decode_with_max_bits(&encoded, initial, 3), input);
+    }
+
+    #[test]
+    fn test_lzw_max_bits() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = gen_data(9, 50000, 0.8);
+        let unbounded = lzw_encode(&input, &initial, 255);
+        for max_bits in [9, 10, 12, 16] {
+            let encoded = lzw_encode_with_max_bits(&input, &initial, 255, max_bits);
+            assert!(encoded.iter().all(|code| *code < 1 << max_bits));
+            let clears = encoded.iter().filter(|code| **code == (1 << max_bits) - 1);
+            assert_eq!(
+                clears.count() > 0,
+                unbounded.iter().any(|code| *code >= 1 << max_bits)
+            );
+            assert_round_trip(
+                &lzw_decode_with_max_bits(&encoded, &initial, max_bits),
+                &input,
+            );
+        }
+    }
+
+    #[test]
+    fn test_lzw_gif_known_stream() {
+        // the image data of a 10x10 GIF, with a minimum code size of 2
+        let encoded = [
+            0x8C, 0x2D, 0x99, 0x87, 0x2A, 0x1C, 0xDC, 0x33, 0xA0, 0x02, 0x75, 0xEC, 0x95, 0xFA,
+            0xA8, 0xDE, 0x60, 0x8C, 0x04, 0x91, 0x4C, 0x01,
+        ];
+        let rows: [&[u8]; 10] = [
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 0, 0, 0, 0, 2, 2, 2],
+            &[1, 1, 1, 0, 0, 0, 0, 2, 2, 2],
+            &[2, 2, 2, 0, 0, 0, 0, 1, 1, 1],
+            &[2, 2, 2, 0, 0, 0, 0, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+        ];
+        let pixels = rows.concat();
+        assert_round_trip(&lzw_decode_gif(&encoded, 2), &pixels);
+        assert_eq!(lzw_encode_gif(&pixels, 2), encoded);
+    }
+
+    #[test]
+    fn test_lzw_tiff_known_stream() {
+        // clear, 0, 258, end of information, all 9 bits wide, most significant bit first
+        let encoded = lzw_encode_tiff(&[0, 0, 0]);
+        assert_eq!(encoded, [0x80, 0x00, 0x20, 0x50, 0x10]);
+        assert_eq!(lzw_decode_tiff(&encoded), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_lzw_image_round_trip() {
+        // long enough to fill the dictionary, and clear it a few times
+        let input = gen_data(11, 100000, 0.5);
+        assert_round_trip(&lzw_decode_tiff(&lzw_encode_tiff(&input)), &input);
+        assert_round_trip(&lzw_decode_gif(&lzw_encode_gif(&input, 8), 8), &input);
+        let pixels: Vec<u8> = input.iter().map(|byte| byte & 0b111).collect();
+        assert_round_trip(&lzw_decode_gif(&lzw_encode_gif(&pixels, 3), 3), &pixels);
+        assert!(lzw_decode_tiff(&lzw_encode_tiff(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_lzw_encoder_finish() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = gen_data(10, 5000, 0.8);
+        for max_lookahead in [4, 255] {
+            let batch = lzw_encode(&input, &initial, max_lookahead);
+            for chunk_size in [1, 7, 1000, input.len()] {
+                let mut encoder = LZWEncoder::new(&initial, max_lookahead);
+                let mut encoded = Vec::new();
+                for chunk in input.chunks(chunk_size) {
+                    encoder.push(chunk, &mut encoded);
+                }
+                // the stream ends in the middle of the last phrase
+                assert_eq!(encoded.len(), batch.len() - 1);
+                encoder.finish(&mut encoded);
+                assert_eq!(encoded, batch);
+            }
+        }
+
+        // the flushed code holds the final bytes
+        let input = b"ABABABABA";
+        let mut encoder = LZWEncoder::new(b"AB", 255);
+        let mut encoded = Vec::new();
+        encoder.push(input, &mut encoded);
+        let pending = lzw_decode(&encoded, b"AB");
+        encoder.finish(&mut encoded);
+        let decoded = lzw_decode(&encoded, b"AB");
+        assert_round_trip(&decoded, input);
+        assert_eq!(&decoded[pending.len()..], b"BA");
+
+        let mut encoded = Vec::new();
+        LZWEncoder::new(b"AB", 255).finish(&mut encoded);
+        assert!(encoded.is_empty());
+    }
+
+    #[test]
+    fn test_lzw_seek_decode() {
+        let initial: Vec<u8> = (0..=255).collect();
+        let input = gen_data(5, 5000, 0.8);
+        let (encoded, index) = lzw_encode_indexed(&input, &initial, 64);
+        assert_eq!(encoded, lzw_encode(&input, &initial, 64));
+        assert_eq!(index.decoded_len(), input.len());
+        let decoded = lzw_decode(&encoded, &initial);
+        for offset in [0, 1, 1234, 4321, input.len() - 1, input.len()] {
+            assert_eq!(
+                lzw_seek_decode(&encoded, &initial, &index, offset),
+                &decoded[offset..]
+            );
+        }
+    }
+
+    #[test]
+    fn test_lzw_escaped_novel_symbols() {
+        let input = b"the quick brown fox jumps over the lazy dog, the end";
+        for initial in [&b""[..], b"the", b"abcdefghijklmnopqrstuvwxyz"] {
+            let encoded = lzw_encode_escaped(input, initial, 8);
+            assert!(encoded.len() < input.len());
+            let decoded = lzw_decode_escaped(&encoded, initial);
+            assert_round_trip(&decoded, input);
+        }
+    }
+
+    #[test]
+    fn test_lzw_escaped_complete_dictionary() {
+        // with a complete dictionary nothing is escaped, and the codes match
+        let input = b"rabarbarbar";
+        let initial = b"rab";
+        let encoded = lzw_encode_escaped(input, initial, 4);
+        let plain = lzw_encode(input, initial, 4);
+        assert_eq!(
+            encoded,
+            plain.into_iter().map(LZWentry::Code).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_lzw_escaped_unique_case() {
+        // an escaped symbol, immediately followed by its own extension
+        let input = b"ccccxyxyxyx";
+        let encoded = lzw_encode_escaped(input, b"", 4);
+        let decoded = lzw_decode_escaped(&encoded, b"");
+        assert_round_trip(&decoded, input);
+    }
+
+    #[test]
+    fn test_lzw_memory_limit() {
+        let input = b"TAMTARAMTAMTAMRAMTAT".repeat(8);
+        let initial = b"AMRT";
+        let (_, unlimited) = lzw_encode_with_memory_limit(&input, initial, 8, None);
+        let limit = unlimited.peak_dictionary_bytes / 4;
+        let (encoded, stats) = lzw_encode_with_memory_limit(&input, initial, 8, Some(limit));
+        assert!(stats.peak_dictionary_bytes <= limit);
+        assert!(stats.resets > 0);
+        let decoded = lzw_decode_with_memory_limit(&encoded, initial, Some(limit));
+        assert_round_trip(&decoded, &input);
+
+        // a limit of exactly the initial dictionary leaves it as it is
+        let (encoded, stats) = lzw_encode_with_memory_limit(&input, initial, 8, Some(4));
+        assert_eq!(encoded.len(), input.len());
+        assert_eq!(stats.peak_dictionary_bytes, 4);
+        let decoded = lzw_decode_with_memory_limit(&encoded, initial, Some(4));
+        assert_round_trip(&decoded, &input);
+    }
+
+    #[test]
+    #[should_panic(expected = "The initial dictionary takes 4 bytes, over the memory limit of 3")]
+    fn test_lzw_memory_limit_below_initial() {
+        lzw_encode_with_memory_limit(b"TAMTAM", b"AMRT", 8, Some(3));
+    }
+
+    #[test]
+    fn test_lzw_matches_reference() {
+        let initial: Vec<u8> = (0..=255).collect();
+        for (seed, redundancy) in [(1, 0.0), (2, 0.5), (3, 0.9)] {
+            let input = gen_data(seed, 2000, redundancy);
+            for max_lookahead in [1, 4, 255] {
+                let encoded = lzw_encode(&input, &initial, max_lookahead);
+                assert_eq!(encoded, reference_encode(&input, &initial, max_lookahead));
+                assert_round_trip(&lzw_decode(&encoded, &initial), &input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lzw_decode_no_membership_scan() {
+        let input: Vec<Counted> = gen_data(4, 50000, 0.9)
+            .into_iter()
+            .map(|b| Counted(b % 4))
+            .collect();
+        let initial: Vec<Counted> = (0..4).map(Counted).collect();
+        let encoded = lzw_encode(&input, &initial, 255);
+        COMPARISONS.with(|c| c.set(0));
+        let decoded = lzw_decode(&encoded, &initial);
+        // every phrase has at most one child per symbol, so adding an entry
+        // takes at most that many comparisons, regardless of the dictionary size
+        let comparisons = COMPARISONS.with(|c| c.get());
+        assert!(comparisons <= encoded.len() * initial.len());
+        assert_round_trip(&decoded, &input);
+    }
+
+    #[test]
+    fn test_lzw_encode_no_membership_scan() {
+        let input: Vec<Counted> = gen_data(5, 50000, 0.9)
+            .into_iter()
+            .map(|b| Counted(b % 4))
+            .collect();
+        let initial: Vec<Counted> = (0..4).map(Counted).collect();
+        COMPARISONS.with(|c| c.set(0));
+        let encoded = lzw_encode(&input, &initial, 255);
+        // finding the single symbol, and each extension of the match, as well
+        // as adding an entry, takes at most one comparison
+        let comparisons = COMPARISONS.with(|c| c.get());
+        assert!(comparisons <= input.len() + encoded.len());
+
+        COMPARISONS.with(|c| c.set(0));
+        let escaped = lzw_encode_escaped(&input, &initial[..1], 255);
+        let comparisons = COMPARISONS.with(|c| c.get());
+        assert!(comparisons <= input.len() + escaped.len());
+        assert_round_trip(&lzw_decode_escaped(&escaped, &initial[..1]), &input);
+    }
+
+    #[test]
+    fn test_lzw_decode_large() {
+        let initial: Vec<u8> = (0..=255).collect();
+        for redundancy in [0.0, 0.5, 0.99] {
+            let input = gen_data(6, 100000, redundancy);
+            let encoded = lzw_encode(&input, &initial, 64);
+            let decoded = lzw_decode(&encoded, &initial);
+            assert!(decoded.capacity() >= output_estimate(encoded.len()));
+            assert_round_trip(&decoded, &input);
+        }
+        // every code decodes to a single byte, fewer than estimated, so the
+        // capacity can only come from reserving the estimate upfront
+        let encoded = lzw_encode(&initial, &initial, 64);
+        let decoded = lzw_decode(&encoded, &initial);
+        assert!(decoded.len() < output_estimate(encoded.len()));
+        assert!(decoded.capacity() >= output_estimate(encoded.len()));
+        assert_round_trip(&decoded, &initial);
     }
 }
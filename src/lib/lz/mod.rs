@@ -1,3 +1,5 @@
+use std::{collections::HashMap, hash::Hash};
+
 /// Module providing LZ77 compression and decompression functions. The lz77
 /// compression algorithm transforms a sequence of data into a sequence of
 /// triples, using the previous data as a dictionary.
@@ -12,3 +14,539 @@ pub mod lz78;
 /// compression algorithm is an iteration on the lz78 algorithm, removing the
 /// second value in the tuple, at the cost of requiring an initial dictionary.
 pub mod lzw;
+
+/// Module providing dictionary training. Given a set of samples similar to the
+/// data that will be compressed, a dictionary of common substrings can be
+/// built, and used as a preset window to improve the compression of small
+/// inputs.
+pub mod train;
+
+/// Module providing the events reported by the traced variants of the
+/// encoders, which expose every encoder decision for debugging poor ratios.
+/// Only available with the `trace` feature.
+#[cfg(feature = "trace")]
+pub mod trace;
+
+/// Module providing a sliding window over the most recent elements of a
+/// sequence, as used by the LZ77 encoder and decoder.
+pub mod window;
+
+/// The default maximum offset of an LZ77 match, as used by the command line
+/// utility.
+pub const DEFAULT_WINDOW_SIZE: usize = 255;
+/// The default maximum length of an LZ77 match, or of the lookahead of the
+/// LZ78 and LZW encoders, as used by the command line utility.
+pub const DEFAULT_LOOKAHEAD: usize = 255;
+/// The default maximum number of LZ78 dictionary phrases, as used by the
+/// command line utility.
+pub const DEFAULT_DICTIONARY_SIZE: usize = 255;
+
+/// Statistics about the dictionary of a dictionary based encoder, gathered
+/// during encoding.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DictionaryStats {
+    /// The highest estimated memory usage of the dictionary, in bytes. The
+    /// estimate is the sum of phrase lengths, times the size of an element.
+    pub peak_dictionary_bytes: usize,
+    /// How many times the dictionary was reset, to stay within the memory limit.
+    pub resets: usize,
+}
+
+/// An index of where the phrase of every code of an LZ78 or LZW stream ends
+/// in the decoded data. Since the dictionary doesn't depend on the decoded
+/// data itself, only on the codes, a decoder can rebuild it up to any code
+/// without writing out the phrases, and start decoding from there.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OffsetIndex {
+    ends: Vec<usize>,
+}
+
+impl OffsetIndex {
+    /// Creates an index from the length of the phrase of every code.
+    ///
+    /// ## Arguments
+    ///
+    /// - `lengths`: The length of the phrase of every code, in order.
+    ///
+    /// ## Returns
+    ///
+    /// A new OffsetIndex instance.
+    pub fn from_lengths(lengths: &[usize]) -> Self {
+        let mut end = 0;
+        OffsetIndex {
+            ends: lengths
+                .iter()
+                .map(|length| {
+                    end += length;
+                    end
+                })
+                .collect(),
+        }
+    }
+
+    /// Finds the code whose phrase contains the element at the offset.
+    ///
+    /// ## Arguments
+    ///
+    /// - `offset`: The offset in the decoded data.
+    ///
+    /// ## Returns
+    ///
+    /// The position of the code, and the offset its phrase starts at, or
+    /// None if the offset is past the end of the decoded data.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use generic_compression::lz::OffsetIndex;
+    /// let index = OffsetIndex::from_lengths(&[1, 3, 2]);
+    /// assert_eq!(index.locate(0), Some((0, 0)));
+    /// assert_eq!(index.locate(3), Some((1, 1)));
+    /// assert_eq!(index.locate(4), Some((2, 4)));
+    /// assert_eq!(index.locate(6), None);
+    /// ```
+    pub fn locate(&self, offset: usize) -> Option<(usize, usize)> {
+        let code = self.ends.partition_point(|end| *end <= offset);
+        if code == self.ends.len() {
+            return None;
+        }
+        let start = if code == 0 { 0 } else { self.ends[code - 1] };
+        Some((code, start))
+    }
+
+    /// Returns the length of the decoded data.
+    pub fn decoded_len(&self) -> usize {
+        self.ends.last().copied().unwrap_or(0)
+    }
+}
+
+/// How a [Dictionary] makes room for a new phrase once it's full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Eviction {
+    /// No more phrases are added.
+    Freeze,
+    /// The new phrase replaces the phrase at index 0.
+    ReplaceFirst,
+}
+
+/// The nodes extending a node of the phrase trie by one symbol, by that symbol.
+trait Children<T>: Default {
+    /// Finds the node extending by the symbol.
+    fn get(&self, symbol: &T) -> Option<usize>;
+
+    /// Adds the node extending by the symbol, unless there already is one.
+    fn insert(&mut self, symbol: T, node: usize);
+
+    /// Removes the node extending by the symbol, if it's the given node.
+    fn remove(&mut self, symbol: &T, node: usize);
+
+    fn is_empty(&self) -> bool;
+
+    fn clear(&mut self);
+}
+
+/// Children found by hashing their symbol, in constant time. The encoders
+/// look up a child for every symbol of the input, so they need this.
+impl<T: Eq + Hash> Children<T> for HashMap<T, usize> {
+    fn get(&self, symbol: &T) -> Option<usize> {
+        HashMap::get(self, symbol).copied()
+    }
+
+    fn insert(&mut self, symbol: T, node: usize) {
+        self.entry(symbol).or_insert(node);
+    }
+
+    fn remove(&mut self, symbol: &T, node: usize) {
+        if HashMap::get(self, symbol) == Some(&node) {
+            HashMap::remove(self, symbol);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        HashMap::is_empty(self)
+    }
+
+    fn clear(&mut self) {
+        HashMap::clear(self)
+    }
+}
+
+/// Children found by comparing their symbols, which only requires the
+/// symbols to be comparable. The decoders only look up a child when adding
+/// a phrase, once per code, so they use this.
+impl<T: PartialEq> Children<T> for Vec<(T, usize)> {
+    fn get(&self, symbol: &T) -> Option<usize> {
+        self.iter()
+            .find(|(other, _)| other == symbol)
+            .map(|(_, node)| *node)
+    }
+
+    fn insert(&mut self, symbol: T, node: usize) {
+        if Children::get(self, &symbol).is_none() {
+            self.push((symbol, node));
+        }
+    }
+
+    fn remove(&mut self, symbol: &T, node: usize) {
+        self.retain(|(other, other_node)| other != symbol || *other_node != node);
+    }
+
+    fn is_empty(&self) -> bool {
+        Vec::is_empty(self)
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+}
+
+/// A node of the phrase trie. The phrase is stored implicitly, as the phrase
+/// of the prefix node extended by the symbol.
+struct Node<T, C> {
+    prefix: Option<usize>,
+    symbol: T,
+    length: usize,
+    children: C,
+    /// The index of the phrase, unless it was evicted. Evicted phrases stay
+    /// in the trie for as long as longer phrases extend them.
+    index: Option<usize>,
+}
+
+/// The dictionary shared by the LZ78 and LZW encoders and decoders, which
+/// must evolve identically on both sides. Phrases are stored as a trie, so
+/// adding one costs a single node, and finding the longest phrase matching
+/// the input walks down from the first symbol, looking up each child by its
+/// symbol, in constant time by default. Phrases are added at most
+/// once, and the size of the dictionary is bounded, both by the number of
+/// phrases and by their estimated memory usage.
+struct Dictionary<'a, T, C = HashMap<T, usize>> {
+    initial: &'a [T],
+    nodes: Vec<Node<T, C>>,
+    /// Nodes that were pruned, which can be reused
+    free: Vec<usize>,
+    /// The nodes consisting of a single symbol
+    roots: C,
+    /// The node of each index
+    indices: Vec<usize>,
+    max_size: usize,
+    eviction: Eviction,
+    memory_limit_bytes: Option<usize>,
+    /// Phrases longer than this are never added
+    max_phrase_len: usize,
+    bytes: usize,
+    stats: DictionaryStats,
+}
+
+/// A [Dictionary] that only requires its symbols to be comparable, at the
+/// cost of scanning the children of a phrase to find one, as the decoders use.
+type ScannedDictionary<'a, T> = Dictionary<'a, T, Vec<(T, usize)>>;
+
+impl<'a, T: Clone, C: Children<T>> Dictionary<'a, T, C> {
+    fn new(
+        initial: &'a [T],
+        max_size: usize,
+        eviction: Eviction,
+        memory_limit_bytes: Option<usize>,
+    ) -> Self {
+        let mut dictionary = Dictionary {
+            initial,
+            nodes: Vec::with_capacity(initial.len()),
+            free: Vec::new(),
+            roots: C::default(),
+            indices: Vec::with_capacity(initial.len()),
+            max_size,
+            eviction,
+            memory_limit_bytes,
+            max_phrase_len: usize::MAX,
+            bytes: 0,
+            stats: DictionaryStats::default(),
+        };
+        dictionary.reset();
+        dictionary.stats.peak_dictionary_bytes = dictionary.bytes;
+        dictionary
+    }
+
+    fn with_max_phrase_len(mut self, max_phrase_len: usize) -> Self {
+        self.max_phrase_len = max_phrase_len;
+        self
+    }
+
+    /// Restores the initial dictionary.
+    fn reset(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.roots.clear();
+        self.indices.clear();
+        for symbol in self.initial {
+            let node = self.push_node(None, symbol.clone());
+            self.nodes[node].index = Some(self.indices.len());
+            self.indices.push(node);
+        }
+        self.bytes = size_of_val(self.initial);
+    }
+
+    /// Creates a node extending the prefix node by the symbol, reusing a
+    /// pruned node if there is one.
+    fn push_node(&mut self, prefix: Option<usize>, symbol: T) -> usize {
+        let node = Node {
+            prefix,
+            length: prefix.map_or(1, |prefix| self.nodes[prefix].length + 1),
+            symbol: symbol.clone(),
+            children: C::default(),
+            index: None,
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        let siblings = match prefix {
+            Some(prefix) => &mut self.nodes[prefix].children,
+            None => &mut self.roots,
+        };
+        // a repeated initial symbol keeps addressing its first node
+        siblings.insert(symbol, id);
+        id
+    }
+
+    /// Removes the node, and then its prefixes, for as long as they are
+    /// neither addressable nor extended by another node.
+    fn prune(&mut self, node: usize) {
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if self.nodes[node].index.is_some() || !self.nodes[node].children.is_empty() {
+                break;
+            }
+            current = self.nodes[node].prefix;
+            let symbol = self.nodes[node].symbol.clone();
+            let siblings = match current {
+                Some(prefix) => &mut self.nodes[prefix].children,
+                None => &mut self.roots,
+            };
+            siblings.remove(&symbol, node);
+            self.free.push(node);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Finds the node extending the prefix node by the symbol.
+    fn find(&self, prefix: Option<usize>, symbol: &T) -> Option<usize> {
+        let children = match prefix {
+            Some(prefix) => &self.nodes[prefix].children,
+            None => &self.roots,
+        };
+        children.get(symbol)
+    }
+
+    /// Finds the index of the longest phrase that is a prefix of the input.
+    fn longest_match(&self, input: &[T]) -> Option<usize> {
+        let mut longest = None;
+        let mut node = None;
+        for symbol in input {
+            node = match self.find(node, symbol) {
+                Some(child) => Some(child),
+                None => break,
+            };
+            if let Some(index) = node.and_then(|node| self.nodes[node].index) {
+                longest = Some(index);
+            }
+        }
+        longest
+    }
+
+    /// Returns the length of the phrase at the index.
+    fn length(&self, idx: usize) -> usize {
+        self.nodes[self.indices[idx]].length
+    }
+
+    /// Returns the first symbol of the phrase at the index.
+    fn first(&self, idx: usize) -> &T {
+        let mut node = self.indices[idx];
+        while let Some(prefix) = self.nodes[node].prefix {
+            node = prefix;
+        }
+        &self.nodes[node].symbol
+    }
+
+    /// Appends the phrase at the index to the output, by walking back the prefixes.
+    fn write_phrase(&self, idx: usize, output: &mut Vec<T>) {
+        let mut current = Some(self.indices[idx]);
+        output.reserve(self.length(idx));
+        let start = output.len();
+        while let Some(node) = current {
+            output.push(self.nodes[node].symbol.clone());
+            current = self.nodes[node].prefix;
+        }
+        output[start..].reverse();
+    }
+
+    /// Adds the phrase at the prefix index extended by the symbol, or just
+    /// the symbol if there is no prefix, unless it's already present, too
+    /// long, or the dictionary is full and frozen. If adding it would exceed
+    /// the memory limit, the dictionary is reset instead. Returns the index of
+    /// the added phrase, if it was added.
+    fn add(&mut self, prefix: Option<usize>, symbol: T) -> Option<usize> {
+        if self.max_size == 0 {
+            // there is no room for any phrase
+            return None;
+        }
+        let prefix = prefix.map(|idx| self.indices[idx]);
+        let existing = self.find(prefix, &symbol);
+        if existing.is_some_and(|node| self.nodes[node].index.is_some()) {
+            return None;
+        }
+        let length = prefix.map_or(1, |prefix| self.nodes[prefix].length + 1);
+        if length > self.max_phrase_len {
+            return None;
+        }
+        let full = self.indices.len() >= self.max_size;
+        if full && self.eviction == Eviction::Freeze {
+            return None;
+        }
+        let replaced_bytes = if full {
+            self.length(0) * size_of::<T>()
+        } else {
+            0
+        };
+        let phrase_bytes = length * size_of::<T>();
+        if self
+            .memory_limit_bytes
+            .is_some_and(|limit| self.bytes - replaced_bytes + phrase_bytes > limit)
+        {
+            self.reset();
+            self.stats.resets += 1;
+            return None;
+        }
+        let node = existing.unwrap_or_else(|| self.push_node(prefix, symbol));
+        let index = if full {
+            // the new phrase may extend the replaced one, so it's only pruned afterwards
+            let replaced = std::mem::replace(&mut self.indices[0], node);
+            self.nodes[replaced].index = None;
+            self.prune(replaced);
+            0
+        } else {
+            self.indices.push(node);
+            self.indices.len() - 1
+        };
+        self.nodes[node].index = Some(index);
+        self.bytes = self.bytes - replaced_bytes + phrase_bytes;
+        self.stats.peak_dictionary_bytes = self.stats.peak_dictionary_bytes.max(self.bytes);
+        Some(index)
+    }
+}
+
+/// Receives the decisions of the shared encoder loops, with one call for every
+/// emitted token and every phrase added to the dictionary. The calls do
+/// nothing by default, so `()` observes nothing, and only the traced encoders
+/// of the `trace` feature turn them into events.
+trait Observer {
+    /// An LZ77 token referring to earlier data, with the length of the match
+    fn matched(&mut self, _position: usize, _offset: usize, _length: usize) {}
+
+    /// A token carrying just a single element
+    fn literal(&mut self, _position: usize) {}
+
+    /// An LZ78 or LZW token referring to a phrase of the given length
+    fn phrase(&mut self, _position: usize, _index: usize, _length: usize) {}
+
+    /// A phrase of the given length was added to the dictionary
+    fn added(&mut self, _index: usize, _length: usize) {}
+}
+
+impl Observer for () {}
+
+/// Estimates the length of the data decoded by the dictionary based decoders,
+/// so that the output can be allocated upfront. Every code or entry decodes
+/// to at least one element, and on compressible data to a few.
+fn output_estimate(codes: usize) -> usize {
+    codes.saturating_mul(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_round_trip, gen_data};
+
+    #[test]
+    fn test_dictionary_replace_first() {
+        check_replace_first::<HashMap<u8, usize>>();
+        check_replace_first::<Vec<(u8, usize)>>();
+    }
+
+    fn check_replace_first<C: Children<u8>>() {
+        let mut dictionary: Dictionary<u8, C> =
+            Dictionary::new(&[], 2, Eviction::ReplaceFirst, None);
+        assert_eq!(dictionary.add(None, b'a'), Some(0));
+        assert_eq!(dictionary.add(Some(0), b'b'), Some(1));
+        assert_eq!(dictionary.add(Some(1), b'c'), Some(0));
+        // "a" was evicted, but still leads to "ab" and "abc"
+        assert_eq!(dictionary.longest_match(b"abcd"), Some(0));
+        assert_eq!(dictionary.longest_match(b"abd"), Some(1));
+        assert_eq!(dictionary.longest_match(b"ad"), None);
+        let mut phrase = Vec::new();
+        dictionary.write_phrase(0, &mut phrase);
+        assert_eq!(phrase, b"abc");
+        // a phrase is only added once
+        assert_eq!(dictionary.add(Some(1), b'c'), None);
+
+        // once nothing extends the evicted phrases, their nodes are reused
+        assert_eq!(dictionary.add(None, b'x'), Some(0));
+        assert_eq!(dictionary.add(None, b'y'), Some(0));
+        assert_eq!(dictionary.add(None, b'z'), Some(0));
+        assert!(dictionary.nodes.len() <= 4);
+        assert_eq!(dictionary.longest_match(b"abc"), Some(1));
+    }
+
+    #[test]
+    fn test_dictionary_freeze() {
+        let mut dictionary: Dictionary<u8> = Dictionary::new(b"ab", 3, Eviction::Freeze, None);
+        assert_eq!(dictionary.add(None, b'a'), None);
+        assert_eq!(dictionary.add(Some(0), b'b'), Some(2));
+        assert_eq!(dictionary.add(Some(1), b'a'), None);
+        assert_eq!(dictionary.len(), 3);
+        assert_eq!(dictionary.longest_match(b"aba"), Some(2));
+        assert_eq!(*dictionary.first(2), b'a');
+    }
+
+    #[test]
+    fn test_dictionary_decode_unhashable() {
+        // the decoders only compare symbols, so floats can be decoded
+        let input = gen_data(12, 2000, 0.8);
+        let floats: Vec<f32> = input.iter().map(|&b| b as f32 / 2.0).collect();
+        let initial: Vec<u8> = (0..=255).collect();
+        let initial_floats: Vec<f32> = initial.iter().map(|&b| b as f32 / 2.0).collect();
+        let encoded = lzw::lzw_encode(&input, &initial, 64);
+        assert_eq!(lzw::lzw_decode(&encoded, &initial_floats), floats);
+
+        let encoded: Vec<lz78::LZ78entry<f32>> = lz78::lz78_encode(&input, 64, 256)
+            .into_iter()
+            .map(|entry| {
+                let (index, next_char): lz78::LZ78tuple<u8> = entry.into();
+                lz78::LZ78entry::from((index, next_char as f32 / 2.0))
+            })
+            .collect();
+        assert_eq!(lz78::lz78_decode(&encoded, 256), floats);
+    }
+
+    #[test]
+    fn test_dictionary_round_trips() {
+        let initial: Vec<u8> = (0..=255).collect();
+        for redundancy in [0.0, 0.5, 0.99] {
+            let input = gen_data(11, 20000, redundancy);
+            for max_dictionary_size in [1, 16, 4096] {
+                let encoded = lz78::lz78_encode(&input, 64, max_dictionary_size);
+                assert_round_trip(&lz78::lz78_decode(&encoded, max_dictionary_size), &input);
+            }
+            let encoded = lzw::lzw_encode(&input, &initial, 64);
+            assert_round_trip(&lzw::lzw_decode(&encoded, &initial), &input);
+        }
+    }
+}
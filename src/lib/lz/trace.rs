@@ -0,0 +1,135 @@
+use super::Observer;
+
+/// A decision made by one of the LZ encoders, reported to the callback
+/// passed to the traced variants of the encoders, like
+/// [lz77_encode_traced](super::lz77::lz77_encode_traced). Every emitted
+/// token is reported by exactly one [TraceEvent::Match],
+/// [TraceEvent::Literal] or [TraceEvent::Phrase] event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    /// An LZ77 token referring to earlier data
+    Match {
+        /// The position in the input the token starts at
+        position: usize,
+        /// How far back the match starts
+        offset: usize,
+        /// The length of the match, not counting the next element
+        length: usize,
+    },
+    /// A token carrying just a single element, without a reference
+    Literal {
+        /// The position in the input of the element
+        position: usize,
+    },
+    /// An LZ78 or LZW token referring to a dictionary phrase
+    Phrase {
+        /// The position in the input the token starts at
+        position: usize,
+        /// The index of the phrase in the dictionary
+        index: usize,
+        /// The length of the phrase. LZ78 tokens cover one more element.
+        length: usize,
+    },
+    /// A phrase was added to the dictionary
+    DictionaryAddition {
+        /// The index of the new phrase
+        index: usize,
+        /// The length of the new phrase
+        length: usize,
+    },
+}
+
+impl TraceEvent {
+    /// Returns true if the event reports an emitted token.
+    pub fn is_token(&self) -> bool {
+        !matches!(self, TraceEvent::DictionaryAddition { .. })
+    }
+}
+
+/// Reports the decisions of an encoder loop to a callback, as [TraceEvent]s.
+pub(super) struct Traced<F>(pub(super) F);
+
+impl<F: FnMut(TraceEvent)> Observer for Traced<F> {
+    fn matched(&mut self, position: usize, offset: usize, length: usize) {
+        (self.0)(TraceEvent::Match {
+            position,
+            offset,
+            length,
+        });
+    }
+
+    fn literal(&mut self, position: usize) {
+        (self.0)(TraceEvent::Literal { position });
+    }
+
+    fn phrase(&mut self, position: usize, index: usize, length: usize) {
+        (self.0)(TraceEvent::Phrase {
+            position,
+            index,
+            length,
+        });
+    }
+
+    fn added(&mut self, index: usize, length: usize) {
+        (self.0)(TraceEvent::DictionaryAddition { index, length });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lz::{
+            lz77::lz77_encode_traced,
+            lz78::{lz78_encode_traced, lz78_encode_with_lengths},
+            lzw::lzw_encode_traced,
+        },
+        test_util::gen_data,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_trace_token_count() {
+        let input = gen_data(11, 3000, 0.7);
+
+        let mut events = Vec::new();
+        let encoded = lz77_encode_traced(&input, 64, 16, |event| events.push(event));
+        assert_eq!(events.len(), encoded.len());
+        assert!(events.iter().any(|e| matches!(e, TraceEvent::Match { .. })));
+
+        // a dictionary large enough that no phrase is evicted
+        let max_dictionary_size = 1 << 20;
+        let mut events = Vec::new();
+        let encoded =
+            lz78_encode_traced(&input, 64, max_dictionary_size, |event| events.push(event));
+        assert_eq!(
+            events.iter().filter(|e| e.is_token()).count(),
+            encoded.len()
+        );
+        // every LZ78 token adds its phrase, unless an earlier token already did
+        let (_, lengths) = lz78_encode_with_lengths(&input, 64, max_dictionary_size);
+        let mut start = 0;
+        let phrases: HashSet<&[u8]> = lengths
+            .iter()
+            .map(|length| {
+                start += length;
+                &input[start - length..start]
+            })
+            .collect();
+        assert!(phrases.len() < encoded.len());
+        assert_eq!(events.len(), encoded.len() + phrases.len());
+
+        let initial: Vec<u8> = (0..=255).collect();
+        let mut events = Vec::new();
+        let encoded = lzw_encode_traced(&input, &initial, 64, |event| events.push(event));
+        assert_eq!(
+            events.iter().filter(|e| e.is_token()).count(),
+            encoded.len()
+        );
+        assert!(events.iter().all(|e| match e {
+            TraceEvent::Phrase { index, .. } | TraceEvent::DictionaryAddition { index, .. } =>
+                *index < initial.len() + encoded.len(),
+            _ => false,
+        }));
+    }
+}
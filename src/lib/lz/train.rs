@@ -0,0 +1,121 @@
+use std::{cmp::Reverse, collections::HashMap, hash::Hash};
+
+/// The shortest substring considered while training a dictionary.
+const MIN_SEGMENT_LENGTH: usize = 4;
+/// The longest substring considered while training a dictionary.
+const MAX_SEGMENT_LENGTH: usize = 32;
+
+/// Trains a dictionary on a set of samples, to be used as a preset window in
+/// [lz77_encode_with_preset](crate::lz::lz77::lz77_encode_with_preset).
+/// Substrings are scored by how many samples contain them, times their
+/// length, and the best scoring ones are packed into the dictionary. Only
+/// substrings found in at least two samples are used. The best substrings end
+/// up at the end of the dictionary, closest to the data, so they are reached
+/// with the smallest offsets.
+///
+/// ## Arguments
+///
+/// - `samples`: A slice of samples, similar to the data to be compressed.
+/// - `dict_size`: The maximum size of the dictionary.
+///
+/// ## Returns
+///
+/// A dictionary, at most `dict_size` long.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::train::train_dictionary;
+/// let samples: [&[u8]; 3] = [b"id=1;name=foo", b"id=2;name=bar", b"id=3;name=qux"];
+/// let dictionary = train_dictionary(&samples, 16);
+/// assert_eq!(dictionary, b";name=");
+/// ```
+pub fn train_dictionary<T: Clone + Hash + Eq>(samples: &[&[T]], dict_size: usize) -> Vec<T> {
+    // how many samples contain a given substring, and where it's first found,
+    // as the index of the sample and the position in it
+    let mut counts: HashMap<&[T], (usize, (usize, usize))> = HashMap::new();
+    for (i, sample) in samples.iter().enumerate() {
+        let mut seen = HashMap::new();
+        for start in 0..sample.len() {
+            let max_end = sample.len().min(start + MAX_SEGMENT_LENGTH);
+            for end in start + MIN_SEGMENT_LENGTH..=max_end {
+                seen.entry(&sample[start..end]).or_insert(start);
+            }
+        }
+        for (segment, start) in seen {
+            counts.entry(segment).or_insert((0, (i, start))).0 += 1;
+        }
+    }
+
+    let mut candidates: Vec<(&[T], usize, (usize, usize))> = counts
+        .into_iter()
+        .filter(|(_, (count, _))| *count > 1)
+        .map(|(segment, (count, first_seen))| (segment, count * segment.len(), first_seen))
+        .collect();
+    // best first, with ties broken by length and position in the samples for
+    // deterministic output
+    candidates.sort_unstable_by_key(|(segment, score, first_seen)| {
+        (Reverse(*score), Reverse(segment.len()), *first_seen)
+    });
+
+    let mut chosen: Vec<&[T]> = Vec::new();
+    let mut size = 0;
+    for (segment, _, _) in candidates {
+        if size + segment.len() > dict_size {
+            continue;
+        }
+        // skip substrings of what we already picked
+        if chosen
+            .iter()
+            .any(|c| c.windows(segment.len()).any(|w| w == segment))
+        {
+            continue;
+        }
+        size += segment.len();
+        chosen.push(segment);
+    }
+
+    // the best segments go last, closest to the data
+    chosen.into_iter().rev().flatten().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lz::lz77::{lz77_decode_with_preset, lz77_encode, lz77_encode_with_preset};
+
+    #[test]
+    fn test_train_dictionary() {
+        let samples: Vec<Vec<u8>> = (0..8)
+            .map(|i| {
+                format!(
+                    "{{\"id\": {}, \"name\": \"user{}\", \"active\": true, \"role\": \"member\"}}",
+                    i,
+                    i * 7
+                )
+                .into_bytes()
+            })
+            .collect();
+        let samples: Vec<&[u8]> = samples.iter().map(|s| s.as_slice()).collect();
+        let dictionary = train_dictionary(&samples, 64);
+        assert!(!dictionary.is_empty());
+        assert!(dictionary.len() <= 64);
+
+        let held_out =
+            b"{\"id\": 42, \"name\": \"user99\", \"active\": false, \"role\": \"member\"}";
+        let window = dictionary.len() + held_out.len();
+        let without = lz77_encode(held_out, window, 255);
+        let with = lz77_encode_with_preset(&dictionary, held_out, window, 255);
+        assert!(with.len() < without.len());
+        assert_eq!(
+            lz77_decode_with_preset(&dictionary, &with).unwrap(),
+            held_out
+        );
+    }
+
+    #[test]
+    fn test_train_dictionary_nothing_common() {
+        let samples: [&[u8]; 2] = [b"abcdefgh", b"ijklmnop"];
+        assert!(train_dictionary(&samples, 64).is_empty());
+    }
+}
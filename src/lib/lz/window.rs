@@ -0,0 +1,117 @@
+/// A fixed capacity window over the most recent elements of a sequence,
+/// backed by a ring buffer. Once full, pushing an element drops the oldest
+/// one. Elements are addressed by how far back they are, same as LZ77
+/// offsets, so an offset of one is the most recently pushed element.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::lz::window::SlidingWindow;
+/// let mut window = SlidingWindow::new(2);
+/// window.push('a');
+/// window.push('b');
+/// window.push('c');
+/// assert_eq!(window.get(1), Some(&'c'));
+/// assert_eq!(window.get(2), Some(&'b'));
+/// assert_eq!(window.get(3), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SlidingWindow<T> {
+    buffer: Vec<T>,
+    /// The index of the oldest element, once the buffer is full
+    start: usize,
+    capacity: usize,
+}
+
+impl<T> SlidingWindow<T> {
+    /// Creates a new, empty window
+    ///
+    /// ## Arguments
+    ///
+    /// - `capacity`: The maximum number of elements kept in the window.
+    pub fn new(capacity: usize) -> Self {
+        SlidingWindow {
+            buffer: Vec::with_capacity(capacity),
+            start: 0,
+            capacity,
+        }
+    }
+
+    /// Pushes an element into the window, dropping the oldest element if the
+    /// window is full.
+    ///
+    /// ## Arguments
+    ///
+    /// - `value`: The element to push.
+    pub fn push(&mut self, value: T) {
+        if self.buffer.len() < self.capacity {
+            self.buffer.push(value);
+        } else if self.capacity > 0 {
+            self.buffer[self.start] = value;
+            self.start = (self.start + 1) % self.capacity;
+        }
+    }
+
+    /// Gets an element by how far back it is in the window
+    ///
+    /// ## Arguments
+    ///
+    /// - `offset_back`: How far back the element is, starting from one for the
+    ///   most recently pushed element.
+    ///
+    /// ## Returns
+    ///
+    /// The element, or None if the offset is zero or outside the window.
+    pub fn get(&self, offset_back: usize) -> Option<&T> {
+        if offset_back == 0 || offset_back > self.buffer.len() {
+            return None;
+        }
+        let index = (self.start + self.buffer.len() - offset_back) % self.capacity;
+        self.buffer.get(index)
+    }
+
+    /// Returns the number of elements currently in the window
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns true if nothing was pushed into the window yet
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the maximum number of elements kept in the window
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_wrap_around() {
+        let mut window = SlidingWindow::new(3);
+        assert!(window.is_empty());
+        assert_eq!(window.get(1), None);
+        for i in 0..10 {
+            window.push(i);
+            assert_eq!(window.len(), (i + 1).min(3));
+            for back in 1..=window.len() {
+                assert_eq!(window.get(back), Some(&(i + 1 - back)));
+            }
+            assert_eq!(window.get(0), None);
+            assert_eq!(window.get(window.len() + 1), None);
+        }
+        assert_eq!(window.capacity(), 3);
+    }
+
+    #[test]
+    fn test_window_zero_capacity() {
+        let mut window = SlidingWindow::new(0);
+        window.push(1);
+        assert!(window.is_empty());
+        assert_eq!(window.get(1), None);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    lz::lzw::{lzw_decode, lzw_encode},
+    transform::{
+        bwt::{decode_bwt, encode_bwt},
+        mtf::{
+            decode_move_to_front, decode_move_to_front_bytes, encode_move_to_front,
+            encode_move_to_front_bytes,
+        },
+    },
+};
+
+/// Every intermediate stage of the STACK pipeline: the transformed data and
+/// index of the BWT, the MTF indices, and the LZW codes.
+pub type StackStages = (Vec<u8>, usize, Vec<usize>, Vec<usize>);
+
+/// Encodes bytes using the STACK pipeline, the Burrows-Wheeler Transform
+/// followed by Move-to-Front and LZW, returning the output of every stage.
+/// Both MTF and LZW start from the ordered 256 byte alphabet. Useful for
+/// inspecting what each transform does to the data.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of bytes to be encoded.
+/// - `lookahead_max`: The maximum lookahead size of LZW.
+///
+/// ## Returns
+///
+/// The output of every stage, the last one being the encoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::pipeline::stack_encode_stages;
+/// let (bwt, index, mtf, lzw) = stack_encode_stages(b"banana", 255);
+/// assert_eq!(bwt, b"nnbaaa");
+/// assert_eq!(index, 3);
+/// assert_eq!(mtf, vec![110, 0, 99, 99, 0, 0]);
+/// assert!(lzw.len() <= mtf.len());
+/// ```
+pub fn stack_encode_stages(input: &[u8], lookahead_max: usize) -> StackStages {
+    let ordering: [u8; 256] = std::array::from_fn(|i| i as u8);
+    let (bwt, index) = encode_bwt(input);
+    let mtf = encode_move_to_front_bytes(&bwt, &mut ordering.clone());
+    let lzw = lzw_encode(&mtf, &ordering, lookahead_max);
+    (bwt, index, mtf.into_iter().map(usize::from).collect(), lzw)
+}
+
+/// Encodes data over any alphabet using the STACK pipeline. The MTF indices
+/// are kept as `usize` all the way through LZW, whose initial dictionary
+/// holds every index, so alphabets larger than 256 symbols work too.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be encoded.
+/// - `alphabet`: Every symbol that may occur in the input, the initial MTF ordering.
+/// - `lookahead_max`: The maximum lookahead size of LZW.
+///
+/// ## Returns
+///
+/// The index of the BWT, and the LZW codes.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::pipeline::{stack_decode, stack_encode};
+/// let alphabet: Vec<u16> = (0..1000).collect();
+/// let input: Vec<u16> = vec![999, 500, 999, 500, 999, 500];
+/// let (index, codes) = stack_encode(&input, &alphabet, 255);
+/// assert_eq!(stack_decode(index, &codes, &alphabet), input);
+/// ```
+pub fn stack_encode<T: Clone + Ord>(
+    input: &[T],
+    alphabet: &[T],
+    lookahead_max: usize,
+) -> (usize, Vec<usize>) {
+    let (bwt, index) = encode_bwt(input);
+    let mtf = encode_move_to_front(&bwt, &mut alphabet.to_vec());
+    let indices: Vec<usize> = (0..alphabet.len()).collect();
+    (index, lzw_encode(&mtf, &indices, lookahead_max))
+}
+
+/// Decodes data encoded by [stack_encode].
+///
+/// ## Arguments
+///
+/// - `index`: The index of the BWT.
+/// - `codes`: The LZW codes.
+/// - `alphabet`: The same alphabet used while encoding.
+///
+/// ## Returns
+///
+/// A vector of data.
+pub fn stack_decode<T: Clone + Ord>(index: usize, codes: &[usize], alphabet: &[T]) -> Vec<T> {
+    let indices: Vec<usize> = (0..alphabet.len()).collect();
+    let mtf = lzw_decode(codes, &indices);
+    let bwt = decode_move_to_front(&mtf, &mut alphabet.to_vec());
+    decode_bwt(&bwt, index)
+}
+
+/// A block of data produced by [transform_blocks], recording which path it
+/// took through the pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    /// A block too small to benefit from the transforms, stored as is.
+    Raw(Vec<u8>),
+    /// A block transformed by the BWT followed by MTF.
+    Transformed {
+        /// The index of the BWT
+        index: usize,
+        /// The MTF indices
+        data: Vec<u8>,
+    },
+}
+
+impl Block {
+    /// Reverses the transforms the block went through, if any.
+    ///
+    /// ## Returns
+    ///
+    /// The original data of the block.
+    pub fn decode(&self) -> Vec<u8> {
+        match self {
+            Block::Raw(data) => data.clone(),
+            Block::Transformed { index, data } => {
+                let mut ordering: [u8; 256] = std::array::from_fn(|i| i as u8);
+                decode_bwt(&decode_move_to_front_bytes(data, &mut ordering), *index)
+            }
+        }
+    }
+}
+
+/// Applies the BWT followed by MTF only to the blocks longer than a
+/// threshold. On tiny blocks the overhead of the transforms outweighs what
+/// they gain, so those are passed through raw.
+///
+/// ## Arguments
+///
+/// - `blocks`: The blocks of data to be transformed.
+/// - `threshold`: The length a block must exceed to be transformed.
+///
+/// ## Returns
+///
+/// A block for every input block, recording the path it took.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::pipeline::{Block, transform_blocks};
+/// let blocks = transform_blocks(&[b"banana", b"ab"], 4);
+/// assert!(matches!(blocks[0], Block::Transformed { index: 3, .. }));
+/// assert_eq!(blocks[1], Block::Raw(b"ab".to_vec()));
+/// assert_eq!(blocks[0].decode(), b"banana");
+/// ```
+pub fn transform_blocks(blocks: &[&[u8]], threshold: usize) -> Vec<Block> {
+    let ordering: [u8; 256] = std::array::from_fn(|i| i as u8);
+    blocks
+        .iter()
+        .map(|block| {
+            if block.len() > threshold {
+                let (bwt, index) = encode_bwt(block);
+                Block::Transformed {
+                    index,
+                    data: encode_move_to_front_bytes(&bwt, &mut ordering.clone()),
+                }
+            } else {
+                Block::Raw(block.to_vec())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lz::lzw::lzw_decode,
+        transform::{bwt::decode_bwt, mtf::encode_move_to_front},
+    };
+
+    #[test]
+    fn test_stack_stages_banana() {
+        let input = b"banana";
+        let (bwt, index, mtf, lzw) = stack_encode_stages(input, 255);
+        assert_eq!((bwt.clone(), index), encode_bwt(input));
+        let alphabet: Vec<u8> = (0..=255).collect();
+        assert_eq!(mtf, encode_move_to_front(&bwt, &mut alphabet.clone()));
+        let mtf_bytes: Vec<u8> = mtf.iter().map(|&i| i as u8).collect();
+        assert_eq!(lzw, lzw_encode(&mtf_bytes, &alphabet, 255));
+
+        // and every stage reverses
+        assert_eq!(lzw_decode(&lzw, &alphabet), mtf_bytes);
+        assert_eq!(decode_bwt(&bwt, index), input);
+    }
+
+    #[test]
+    fn test_stack_wide_alphabet() {
+        let alphabet: Vec<u16> = (0..1000).collect();
+        let input: Vec<u16> = (0..3000u32)
+            .map(|i| ((i % 40) * 24 + i / 1000) as u16)
+            .collect();
+        // the data really needs indices past a byte
+        let (bwt, _) = encode_bwt(&input);
+        let mtf = encode_move_to_front(&bwt, &mut alphabet.clone());
+        assert!(mtf.iter().any(|&i| i > 255));
+
+        let (index, codes) = stack_encode(&input, &alphabet, 255);
+        assert!(codes.len() < input.len());
+        assert_eq!(stack_decode(index, &codes, &alphabet), input);
+    }
+
+    #[test]
+    fn test_transform_blocks_threshold() {
+        let large = b"mississippi ".repeat(8);
+        let small = b"tiny";
+        let blocks: [&[u8]; 4] = [&large, small, b"", &large[..17]];
+        let transformed = transform_blocks(&blocks, 16);
+        assert!(matches!(transformed[0], Block::Transformed { .. }));
+        assert_eq!(transformed[1], Block::Raw(small.to_vec()));
+        assert_eq!(transformed[2], Block::Raw(Vec::new()));
+        assert!(matches!(transformed[3], Block::Transformed { .. }));
+        for (block, original) in transformed.iter().zip(blocks) {
+            assert_eq!(block.decode(), original);
+        }
+    }
+}
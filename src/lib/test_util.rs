@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// A small xorshift pseudo-random number generator, so that tests are
+/// reproducible without depending on an external crate.
+struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // the state must never be zero
+        XorShift {
+            state: seed ^ 0x9E3779B97F4A7C15,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns a number in the range `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Generates reproducible pseudo-random data, with tunable redundancy.
+///
+/// ## Arguments
+///
+/// - `seed`: The seed of the generator. The same seed always yields the same data.
+/// - `len`: The length of the generated data.
+/// - `redundancy`: The fraction of the data, between 0 and 1, that is copied
+///   from earlier in the output instead of being random. At 0 the data is
+///   incompressible noise, at 1 it's a single byte repeated.
+///
+/// ## Returns
+///
+/// The generated data.
+pub fn gen_data(seed: u64, len: usize, redundancy: f64) -> Vec<u8> {
+    let mut rng = XorShift::new(seed);
+    let mut output = Vec::with_capacity(len);
+    let threshold = (redundancy.clamp(0.0, 1.0) * u32::MAX as f64) as u64;
+    while output.len() < len {
+        if output.is_empty() || (rng.next() >> 32) >= threshold {
+            output.push(rng.next() as u8);
+            continue;
+        }
+        // copy a run from earlier in the output
+        let run = (8 + rng.below(25)).min(len - output.len());
+        let start = rng.below(output.len());
+        for i in 0..run {
+            output.push(output[start + i]);
+        }
+    }
+    output
+}
+
+/// Finds the first position where two sequences differ.
+///
+/// ## Arguments
+///
+/// - `a`: The first sequence.
+/// - `b`: The second sequence.
+///
+/// ## Returns
+///
+/// The index of the first differing element, the length of the shorter
+/// sequence if one is a prefix of the other, or None if they are equal.
+pub fn first_diff<T: PartialEq>(a: &[T], b: &[T]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(index) => Some(index),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+/// Asserts that a round trip recovered the original data. On failure, reports
+/// where the data diverged, rather than dumping both sequences.
+///
+/// ## Arguments
+///
+/// - `decoded`: The data produced by the round trip.
+/// - `original`: The original data.
+#[track_caller]
+pub fn assert_round_trip<T: PartialEq + fmt::Debug>(decoded: &[T], original: &[T]) {
+    if let Some(index) = first_diff(decoded, original) {
+        panic!(
+            "diverged at index {}: decoded {:?}, expected {:?} (lengths {} and {})",
+            index,
+            decoded.get(index),
+            original.get(index),
+            decoded.len(),
+            original.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Returns the length of the longest substring that occurs at least twice.
+    fn longest_repeat(data: &[u8]) -> usize {
+        let repeats = |length: usize| {
+            let mut seen = HashSet::new();
+            data.windows(length).any(|window| !seen.insert(window))
+        };
+        // binary search, a repeat of some length implies repeats of all shorter lengths
+        let (mut low, mut high) = (0, data.len());
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if repeats(mid) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+
+    #[test]
+    fn test_gen_data_deterministic() {
+        assert_eq!(gen_data(42, 1000, 0.5), gen_data(42, 1000, 0.5));
+        assert_ne!(gen_data(42, 1000, 0.5), gen_data(43, 1000, 0.5));
+        assert_eq!(gen_data(7, 1000, 0.3).len(), 1000);
+    }
+
+    #[test]
+    fn test_gen_data_redundancy() {
+        let noise = longest_repeat(&gen_data(1, 4096, 0.0));
+        let some = longest_repeat(&gen_data(1, 4096, 0.3));
+        let most = longest_repeat(&gen_data(1, 4096, 0.9));
+        assert!(noise < some);
+        assert!(some < most);
+        assert_eq!(gen_data(1, 100, 1.0), vec![gen_data(1, 1, 1.0)[0]; 100]);
+    }
+
+    #[test]
+    fn test_first_diff() {
+        let a = gen_data(3, 5000, 0.5);
+        let mut b = a.clone();
+        assert_eq!(first_diff(&a, &b), None);
+        b[4217] ^= 1;
+        assert_eq!(first_diff(&a, &b), Some(4217));
+        assert_eq!(first_diff(&a, &a[..100]), Some(100));
+        assert_eq!(first_diff::<u8>(&[], &[]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "diverged at index 4217")]
+    fn test_assert_round_trip() {
+        let a = gen_data(3, 5000, 0.5);
+        let mut b = a.clone();
+        b[4217] ^= 1;
+        assert_round_trip(&a, &b);
+    }
+}
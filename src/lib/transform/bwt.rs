@@ -1,3 +1,7 @@
+use super::Transform;
+
+use std::{collections::BTreeMap, error, fmt};
+
 /// Burrows-Wheeler Transform (BWT) implementation
 /// Transforms a slice of data, in a way that is useful for compression.
 ///
@@ -19,6 +23,37 @@
 /// assert_eq!(index, 3);
 /// ```
 pub fn encode_bwt<T: Clone + Ord>(input: &[T]) -> (Vec<T>, usize) {
+    let (result, original_index, _) = encode_bwt_with_order(input);
+    (result, original_index)
+}
+
+/// Burrows-Wheeler Transform (BWT) implementation, that also returns the
+/// sorted order of the rotations. Useful when the rotation ordering is needed
+/// for more than just the transform itself, for example to compute additional
+/// statistics. Equal rotations, of periodic data, are ordered by where they
+/// start, so the index of the original data is always the same for the same
+/// input.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be transformed.
+///
+/// ## Returns
+///
+/// A tuple containing the transformed data, the index of the original data
+/// and the start indices of the rotations in sorted order.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::bwt::encode_bwt_with_order;
+/// let input = b"banana";
+/// let (encoded, index, order) = encode_bwt_with_order(input);
+/// assert_eq!(encoded, vec![b'n', b'n', b'b', b'a', b'a', b'a']);
+/// assert_eq!(index, 3);
+/// assert_eq!(order, vec![5, 3, 1, 0, 4, 2]);
+/// ```
+pub fn encode_bwt_with_order<T: Clone + Ord>(input: &[T]) -> (Vec<T>, usize, Vec<usize>) {
     let n = input.len();
     let mut rotations: Vec<_> = (0..n).collect();
     rotations.sort_by(|&a, &b| {
@@ -31,8 +66,8 @@ pub fn encode_bwt<T: Clone + Ord>(input: &[T]) -> (Vec<T>, usize) {
         .iter()
         .map(|&i| input[(i + n - 1) % n].clone())
         .collect();
-    let original_index = rotations.iter().position(|&i| i == 0).unwrap();
-    (result, original_index)
+    let original_index = rotations.iter().position(|&i| i == 0).unwrap_or(0);
+    (result, original_index, rotations)
 }
 
 /// Decodes a Burrows-Wheeler Transform (BWT) encoded data.
@@ -56,6 +91,9 @@ pub fn encode_bwt<T: Clone + Ord>(input: &[T]) -> (Vec<T>, usize) {
 /// assert_eq!(decoded, vec![b'b', b'a', b'n', b'a', b'n', b'a']);
 /// ```
 pub fn decode_bwt<T: Clone + Ord>(input: &[T], index: usize) -> Vec<T> {
+    if input.is_empty() {
+        return Vec::new();
+    }
     let mut table = input.iter().enumerate().collect::<Vec<_>>();
     table.sort_by(|a, b| a.1.cmp(&b.1));
     let (mut i, el) = table[index];
@@ -65,7 +103,325 @@ pub fn decode_bwt<T: Clone + Ord>(input: &[T], index: usize) -> Vec<T> {
         result.push(el.clone());
         i = j;
     }
-    return result;
+    repeat_period(&mut result, input.len());
+    result
+}
+
+/// Error returned by [decode_bwt_checked] and [decode_bwt_bytes_checked] when
+/// the index of the original
+/// data doesn't point into the encoded data.
+#[derive(Debug, PartialEq)]
+pub struct InvalidPrimaryIndexError {
+    /// The offending index
+    pub index: usize,
+    /// The length of the encoded data
+    pub len: usize,
+}
+
+impl fmt::Display for InvalidPrimaryIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Primary index {} is out of range for {} elements",
+            self.index, self.len
+        )
+    }
+}
+
+impl error::Error for InvalidPrimaryIndexError {}
+
+/// Checks that the index of the original data points into the encoded data.
+/// The index of empty data is never used, so any index is accepted for it.
+fn check_primary_index(len: usize, index: usize) -> Result<(), InvalidPrimaryIndexError> {
+    if len != 0 && index >= len {
+        return Err(InvalidPrimaryIndexError { index, len });
+    }
+    Ok(())
+}
+
+/// Decodes a Burrows-Wheeler Transform (BWT) encoded data, validating the
+/// index first. Produces the same output as [decode_bwt], but returns an
+/// error instead of panicking on a corrupt index, which makes it suitable
+/// for data read from a file.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be decoded.
+/// - `index`: The index of the original data.
+///
+/// ## Returns
+///
+/// A vector of data, or an error if the index is out of range.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::bwt::{InvalidPrimaryIndexError, decode_bwt_checked, encode_bwt};
+/// let (encoded, index) = encode_bwt(b"banana");
+/// assert_eq!(decode_bwt_checked(&encoded, index).unwrap(), b"banana");
+/// assert_eq!(
+///     decode_bwt_checked(&encoded, 6),
+///     Err(InvalidPrimaryIndexError { index: 6, len: 6 })
+/// );
+/// ```
+pub fn decode_bwt_checked<T: Clone + Ord>(
+    input: &[T],
+    index: usize,
+) -> Result<Vec<T>, InvalidPrimaryIndexError> {
+    check_primary_index(input.len(), index)?;
+    Ok(decode_bwt(input, index))
+}
+
+/// Completes the decoding of a periodic input. The rotations of an input
+/// made of a repeated period are identical in groups, so following the
+/// rotations only visits a single period before returning to the start.
+fn repeat_period<T: Clone>(result: &mut Vec<T>, len: usize) {
+    let period = result.len();
+    while result.len() < len {
+        result.push(result[result.len() - period].clone());
+    }
+}
+
+/// Decodes Burrows-Wheeler Transform (BWT) encoded bytes.
+/// Produces the same output as [decode_bwt], but instead of sorting the input
+/// it counts the occurrences of each of the 256 possible bytes, which runs in
+/// linear time.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of bytes to be decoded.
+/// - `index`: The index of the original data.
+///
+/// ## Returns
+///
+/// A vector of bytes.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::bwt::{decode_bwt_bytes, encode_bwt};
+/// let input = b"banana";
+/// let (encoded, index) = encode_bwt(input);
+/// let decoded = decode_bwt_bytes(&encoded, index);
+/// assert_eq!(decoded, b"banana");
+/// ```
+pub fn decode_bwt_bytes(input: &[u8], index: usize) -> Vec<u8> {
+    if input.is_empty() {
+        return Vec::new();
+    }
+    // where each byte starts in the sorted first column
+    let mut starts = [0usize; 256];
+    for &byte in input {
+        starts[byte as usize] += 1;
+    }
+    let mut sum = 0;
+    for start in starts.iter_mut() {
+        let count = *start;
+        *start = sum;
+        sum += count;
+    }
+    // the same mapping a stable sort of the input would give us
+    let mut next = vec![0; input.len()];
+    for (j, &byte) in input.iter().enumerate() {
+        next[starts[byte as usize]] = j;
+        starts[byte as usize] += 1;
+    }
+    let mut i = next[index];
+    let mut result = Vec::with_capacity(input.len());
+    result.push(input[i]);
+    while i != index {
+        i = next[i];
+        result.push(input[i]);
+    }
+    repeat_period(&mut result, input.len());
+    result
+}
+
+/// Decodes Burrows-Wheeler Transform (BWT) encoded bytes, validating the
+/// index first. Produces the same output as [decode_bwt_bytes], but returns
+/// an error instead of panicking on a corrupt index.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of bytes to be decoded.
+/// - `index`: The index of the original data.
+///
+/// ## Returns
+///
+/// A vector of bytes, or an error if the index is out of range.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::bwt::{InvalidPrimaryIndexError, decode_bwt_bytes_checked, encode_bwt};
+/// let (encoded, index) = encode_bwt(b"banana");
+/// assert_eq!(decode_bwt_bytes_checked(&encoded, index).unwrap(), b"banana");
+/// assert_eq!(
+///     decode_bwt_bytes_checked(&encoded, 6),
+///     Err(InvalidPrimaryIndexError { index: 6, len: 6 })
+/// );
+/// ```
+pub fn decode_bwt_bytes_checked(
+    input: &[u8],
+    index: usize,
+) -> Result<Vec<u8>, InvalidPrimaryIndexError> {
+    check_primary_index(input.len(), index)?;
+    Ok(decode_bwt_bytes(input, index))
+}
+
+/// Counts the occurrences of each symbol in Burrows-Wheeler Transform (BWT)
+/// encoded data. These are also the lengths of the runs in the sorted first
+/// column, which [decode_bwt_with_counts] uses to avoid sorting.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of encoded data.
+///
+/// ## Returns
+///
+/// The distinct symbols and their counts, in ascending order of the symbols.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::bwt::{column_counts, encode_bwt};
+/// let (encoded, _) = encode_bwt(b"banana");
+/// assert_eq!(column_counts(&encoded), vec![(b'a', 3), (b'b', 1), (b'n', 2)]);
+/// ```
+pub fn column_counts<T: Clone + Ord>(input: &[T]) -> Vec<(T, usize)> {
+    let mut counts = BTreeMap::new();
+    for el in input {
+        *counts.entry(el).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(el, count)| (el.clone(), count))
+        .collect()
+}
+
+/// Error returned by [decode_bwt_with_counts] when the counts don't match
+/// the encoded data.
+#[derive(Debug, PartialEq)]
+pub enum InvalidCountsError {
+    /// The counts add up to a different length than that of the data
+    WrongTotal { total: usize, len: usize },
+    /// The element at this position is missing from the counts, or occurs
+    /// more often than counted
+    Miscounted { position: usize },
+}
+
+impl fmt::Display for InvalidCountsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidCountsError::WrongTotal { total, len } => {
+                write!(f, "Counts add up to {} for {} elements", total, len)
+            }
+            InvalidCountsError::Miscounted { position } => {
+                write!(f, "Element {} is not accounted for by the counts", position)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidCountsError {}
+
+/// Decodes Burrows-Wheeler Transform (BWT) encoded data, given the counts of
+/// its symbols. Produces the same output as [decode_bwt], but the first
+/// column is reconstructed from the counts instead of by sorting the input.
+/// The counts are validated against the data, so they may come from an
+/// untrusted source.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be decoded.
+/// - `index`: The index of the original data.
+/// - `counts`: The counts of the symbols, as returned by [column_counts].
+///
+/// ## Returns
+///
+/// A vector of data, or an error if the counts don't match the data.
+///
+/// ## Panics
+///
+/// If `index` is out of range for non-empty data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::bwt::{
+///     InvalidCountsError, column_counts, decode_bwt_with_counts, encode_bwt,
+/// };
+/// let (encoded, index) = encode_bwt(b"banana");
+/// let counts = column_counts(&encoded);
+/// let decoded = decode_bwt_with_counts(&encoded, index, &counts);
+/// assert_eq!(decoded.unwrap(), b"banana");
+/// assert_eq!(
+///     decode_bwt_with_counts(&encoded, index, &counts[1..]),
+///     Err(InvalidCountsError::WrongTotal { total: 3, len: 6 })
+/// );
+/// ```
+pub fn decode_bwt_with_counts<T: Clone + Ord>(
+    input: &[T],
+    index: usize,
+    counts: &[(T, usize)],
+) -> Result<Vec<T>, InvalidCountsError> {
+    // where each symbol starts in the sorted first column, and where the
+    // next one does
+    let mut starts = Vec::with_capacity(counts.len());
+    let mut ends = Vec::with_capacity(counts.len());
+    let mut total: usize = 0;
+    for (_, count) in counts {
+        starts.push(total);
+        total = total.saturating_add(*count);
+        ends.push(total);
+    }
+    if total != input.len() {
+        return Err(InvalidCountsError::WrongTotal {
+            total,
+            len: input.len(),
+        });
+    }
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
+    // with the right total, and no symbol overflowing its run, every
+    // position of the first column is filled exactly once
+    let mut next = vec![0; input.len()];
+    for (j, el) in input.iter().enumerate() {
+        let symbol = counts
+            .binary_search_by(|(other, _)| other.cmp(el))
+            .ok()
+            .filter(|&symbol| starts[symbol] < ends[symbol])
+            .ok_or(InvalidCountsError::Miscounted { position: j })?;
+        next[starts[symbol]] = j;
+        starts[symbol] += 1;
+    }
+    let mut i = next[index];
+    let mut result = Vec::with_capacity(input.len());
+    result.push(input[i].clone());
+    while i != index {
+        i = next[i];
+        result.push(input[i].clone());
+    }
+    repeat_period(&mut result, input.len());
+    Ok(result)
+}
+
+/// The Burrows-Wheeler Transform, as a [Transform]. The state is the index
+/// of the original data.
+pub struct BurrowsWheeler;
+
+impl<T: Clone + Ord> Transform<T> for BurrowsWheeler {
+    type Output = Vec<T>;
+    type State = usize;
+
+    fn forward(&self, input: &[T]) -> (Self::Output, Self::State) {
+        encode_bwt(input)
+    }
+
+    fn inverse(&self, output: &Self::Output, state: &Self::State) -> Vec<T> {
+        decode_bwt(output, *state)
+    }
 }
 
 #[cfg(test)]
@@ -87,4 +443,145 @@ mod tests {
         let decoded = decode_bwt(&input, index);
         assert_eq!(decoded, vec![b'h', b'e', b'l', b'l', b'o']);
     }
+
+    #[test]
+    fn test_bwt_decode_checked() {
+        let (encoded, index) = encode_bwt(b"hello");
+        assert_eq!(decode_bwt_checked(&encoded, index).unwrap(), b"hello");
+        assert_eq!(
+            decode_bwt_checked(&encoded, encoded.len()),
+            Err(InvalidPrimaryIndexError { index: 5, len: 5 })
+        );
+        assert!(decode_bwt_checked(&encoded, usize::MAX).is_err());
+        // the index of empty data is never used
+        assert_eq!(decode_bwt_checked::<u8>(&[], 0), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_bwt_decode_bytes_checked() {
+        let (encoded, index) = encode_bwt(b"hello");
+        assert_eq!(decode_bwt_bytes_checked(&encoded, index).unwrap(), b"hello");
+        assert_eq!(
+            decode_bwt_bytes_checked(&encoded, encoded.len()),
+            Err(InvalidPrimaryIndexError { index: 5, len: 5 })
+        );
+        assert!(decode_bwt_bytes_checked(&encoded, usize::MAX).is_err());
+        assert_eq!(decode_bwt_bytes_checked(&[], 0), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_bwt_order() {
+        let input = b"mississippi";
+        let n = input.len();
+        let (encoded, index, order) = encode_bwt_with_order(input);
+        assert_eq!((encoded.clone(), index), encode_bwt(input));
+
+        let rotations: Vec<Vec<u8>> = order
+            .iter()
+            .map(|&i| input[i..].iter().chain(&input[..i]).copied().collect())
+            .collect();
+        // the rotations are sorted
+        assert!(rotations.windows(2).all(|w| w[0] <= w[1]));
+        // and their last column is the transform
+        let last_column: Vec<u8> = rotations.iter().map(|r| r[n - 1]).collect();
+        assert_eq!(last_column, encoded);
+        assert_eq!(order[index], 0);
+    }
+
+    #[test]
+    fn test_bwt_decode_bytes() {
+        for input in [&b"abracadabra"[..], b"mississippi", b"a", b"hello"] {
+            let (encoded, index) = encode_bwt(input);
+            let decoded = decode_bwt_bytes(&encoded, index);
+            assert_eq!(decoded, decode_bwt(&encoded, index));
+            assert_eq!(decoded, input);
+        }
+    }
+
+    #[test]
+    fn test_bwt_periodic() {
+        for input in [
+            &b"aaaa"[..],
+            b"abab",
+            b"abcabcabc",
+            b"mississippimississippi",
+            b"",
+        ] {
+            let (encoded, index, order) = encode_bwt_with_order(input);
+            assert_eq!(decode_bwt(&encoded, index), input);
+            assert_eq!(decode_bwt_bytes(&encoded, index), input);
+            // equal rotations are ordered by where they start, so the original
+            // data is the first of the rotations equal to it
+            let rotation = |i: usize| input[i..].iter().chain(&input[..i]).cmp(input.iter());
+            let ties: Vec<usize> = order
+                .iter()
+                .copied()
+                .filter(|&i| rotation(i).is_eq())
+                .collect();
+            assert!(ties.is_sorted());
+            assert_eq!(
+                order.iter().position(|&i| rotation(i).is_eq()),
+                (!input.is_empty()).then_some(index)
+            );
+        }
+    }
+
+    #[test]
+    fn test_bwt_decode_with_counts() {
+        for input in [&b"abracadabra"[..], b"mississippi", b"abab", b"a", b""] {
+            let (encoded, index) = encode_bwt(input);
+            let counts = column_counts(&encoded);
+            assert_eq!(
+                counts.iter().map(|(_, count)| count).sum::<usize>(),
+                input.len()
+            );
+            let decoded = decode_bwt_with_counts(&encoded, index, &counts).unwrap();
+            assert_eq!(decoded, decode_bwt(&encoded, index));
+            assert_eq!(decoded, input);
+        }
+    }
+
+    #[test]
+    fn test_bwt_decode_with_invalid_counts() {
+        let (encoded, index) = encode_bwt(b"mississippi");
+        // i, m, p, s
+        let counts = column_counts(&encoded);
+        assert_eq!(counts.len(), 4);
+
+        let mut missing = counts.clone();
+        missing.remove(1);
+        missing[0].1 += 1;
+        let position = encoded.iter().position(|&el| el == b'm').unwrap();
+        assert_eq!(
+            decode_bwt_with_counts(&encoded, index, &missing),
+            Err(InvalidCountsError::Miscounted { position })
+        );
+
+        // the total is right, but one symbol overflows its run
+        let mut shifted = counts.clone();
+        shifted[0].1 -= 1;
+        shifted[3].1 += 1;
+        assert!(matches!(
+            decode_bwt_with_counts(&encoded, index, &shifted),
+            Err(InvalidCountsError::Miscounted { .. })
+        ));
+
+        let mut overflowing = counts.clone();
+        overflowing[0].1 = usize::MAX;
+        assert_eq!(
+            decode_bwt_with_counts(&encoded, index, &overflowing),
+            Err(InvalidCountsError::WrongTotal {
+                total: usize::MAX,
+                len: encoded.len()
+            })
+        );
+    }
+
+    #[test]
+    fn test_bwt_transform() {
+        for input in [&b"banana"[..], b"mississippi", b"abab", b""] {
+            let (output, state) = BurrowsWheeler.forward(input);
+            assert_eq!(BurrowsWheeler.inverse(&output, &state), input);
+        }
+    }
 }
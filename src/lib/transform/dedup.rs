@@ -0,0 +1,268 @@
+use std::{collections::HashMap, error, fmt};
+
+/// A chunk of the deduplicated input, as recorded in the [DedupTable].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DedupChunk {
+    /// A chunk seen for the first time, stored verbatim in the output.
+    /// Contains the length of the chunk.
+    Unique(usize),
+    /// A chunk identical to an earlier unique chunk.
+    /// Contains the index of that chunk among the unique chunks.
+    Repeat(usize),
+}
+
+/// The table needed to reconstruct deduplicated data, one entry per chunk
+/// of the original input, in order.
+pub type DedupTable = Vec<DedupChunk>;
+
+/// Table of pseudo-random values for the gear rolling hash, one per byte.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Splits the input into content-defined chunks. Boundaries depend only on
+/// the bytes preceding them, so identical regions are split identically
+/// regardless of where they are located in the input.
+fn chunk_boundaries(input: &[u8], avg_chunk: usize) -> Vec<usize> {
+    let mask = (avg_chunk.next_power_of_two() - 1) as u64;
+    let min_chunk = avg_chunk / 4;
+    let max_chunk = avg_chunk * 4;
+    let mut boundaries = Vec::new();
+    let mut start = 0;
+    let mut hash: u64 = 0;
+    for (i, byte) in input.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+        let length = i + 1 - start;
+        if (length >= min_chunk && hash & mask == 0) || length >= max_chunk {
+            boundaries.push(i + 1);
+            start = i + 1;
+            hash = 0;
+        }
+    }
+    if start < input.len() {
+        boundaries.push(input.len());
+    }
+    boundaries
+}
+
+/// Removes repeated blocks from the input, using content-defined chunking
+/// based on a rolling hash. Each chunk that was already seen is replaced
+/// with a reference to its first occurrence, no matter how far apart the
+/// two are.
+///
+/// ## Arguments
+///
+/// - `input`: The data to be deduplicated.
+/// - `avg_chunk`: The average chunk size to aim for. Smaller chunks find
+///   more duplicates, at the cost of a larger table.
+///
+/// ## Returns
+///
+/// The unique chunks concatenated, and the table needed to reconstruct the
+/// input.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::dedup::{dedup_blocks, reconstruct_blocks};
+/// let input = b"some data, some data, some data".repeat(8);
+/// let (data, table) = dedup_blocks(&input, 16);
+/// assert!(data.len() < input.len());
+/// assert_eq!(reconstruct_blocks(&data, &table).unwrap(), input);
+/// ```
+pub fn dedup_blocks(input: &[u8], avg_chunk: usize) -> (Vec<u8>, DedupTable) {
+    assert!(avg_chunk > 0, "Average chunk size must be positive");
+    let mut output = Vec::new();
+    let mut table = DedupTable::new();
+    let mut seen: HashMap<&[u8], usize> = HashMap::new();
+    let mut start = 0;
+    for end in chunk_boundaries(input, avg_chunk) {
+        let chunk = &input[start..end];
+        if let Some(idx) = seen.get(chunk) {
+            table.push(DedupChunk::Repeat(*idx));
+        } else {
+            seen.insert(chunk, seen.len());
+            output.extend_from_slice(chunk);
+            table.push(DedupChunk::Unique(chunk.len()));
+        }
+        start = end;
+    }
+    (output, table)
+}
+
+/// Error returned by [reconstruct_blocks] when the table doesn't describe the
+/// data.
+#[derive(Debug, PartialEq)]
+pub enum ReconstructError {
+    /// A unique chunk extending past the end of the data.
+    ChunkOutOfBounds {
+        /// The index of the offending table entry
+        entry: usize,
+        /// The length of the chunk
+        length: usize,
+        /// The amount of data left for it
+        available: usize,
+    },
+    /// A repeat of a unique chunk that doesn't precede it in the table.
+    UnknownChunk {
+        /// The index of the offending table entry
+        entry: usize,
+        /// The index of the repeated chunk among the unique chunks
+        index: usize,
+        /// The number of unique chunks before the entry
+        unique: usize,
+    },
+}
+
+impl fmt::Display for ReconstructError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconstructError::ChunkOutOfBounds {
+                entry,
+                length,
+                available,
+            } => write!(
+                f,
+                "Entry {} has a chunk of length {}, but only {} bytes are left",
+                entry, length, available
+            ),
+            ReconstructError::UnknownChunk {
+                entry,
+                index,
+                unique,
+            } => write!(
+                f,
+                "Entry {} repeats chunk {}, but only {} unique chunks precede it",
+                entry, index, unique
+            ),
+        }
+    }
+}
+
+impl error::Error for ReconstructError {}
+
+/// Reconstructs data deduplicated by [dedup_blocks].
+///
+/// ## Arguments
+///
+/// - `data`: The unique chunks, as returned by [dedup_blocks].
+/// - `table`: The table describing the chunks of the original input.
+///
+/// ## Returns
+///
+/// The original input, or an error if the table refers to data that isn't
+/// there.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::dedup::{DedupChunk, reconstruct_blocks};
+/// let table = vec![DedupChunk::Unique(3), DedupChunk::Unique(1), DedupChunk::Repeat(0)];
+/// assert_eq!(reconstruct_blocks(b"abc-", &table).unwrap(), b"abc-abc");
+/// assert!(reconstruct_blocks(b"abc", &table).is_err());
+/// ```
+pub fn reconstruct_blocks(data: &[u8], table: &[DedupChunk]) -> Result<Vec<u8>, ReconstructError> {
+    let mut output = Vec::with_capacity(data.len());
+    // the location of every unique chunk within data
+    let mut chunks: Vec<(usize, usize)> = Vec::new();
+    let mut position = 0;
+    for (entry, chunk) in table.iter().enumerate() {
+        match *chunk {
+            DedupChunk::Unique(length) => {
+                let available = data.len() - position;
+                if length > available {
+                    return Err(ReconstructError::ChunkOutOfBounds {
+                        entry,
+                        length,
+                        available,
+                    });
+                }
+                chunks.push((position, position + length));
+                output.extend_from_slice(&data[position..position + length]);
+                position += length;
+            }
+            DedupChunk::Repeat(index) => {
+                let &(start, end) = chunks.get(index).ok_or(ReconstructError::UnknownChunk {
+                    entry,
+                    index,
+                    unique: chunks.len(),
+                })?;
+                output.extend_from_slice(&data[start..end]);
+            }
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_data;
+
+    #[test]
+    fn test_dedup_distant_blocks() {
+        let block = gen_data(1, 16384, 0.0);
+        let mut input = block.clone();
+        input.extend(gen_data(2, 8192, 0.0));
+        input.extend(&block);
+        let (data, table) = dedup_blocks(&input, 256);
+        // the second copy of the block is almost entirely deduplicated
+        assert!(data.len() < block.len() + 8192 + 2048);
+        assert_eq!(reconstruct_blocks(&data, &table).unwrap(), input);
+    }
+
+    #[test]
+    fn test_dedup_empty() {
+        let (data, table) = dedup_blocks(b"", 64);
+        assert!(data.is_empty());
+        assert!(table.is_empty());
+        assert!(reconstruct_blocks(&data, &table).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reconstruct_malformed_table() {
+        let data = b"abcd";
+        assert_eq!(
+            reconstruct_blocks(data, &[DedupChunk::Unique(3), DedupChunk::Unique(2)]),
+            Err(ReconstructError::ChunkOutOfBounds {
+                entry: 1,
+                length: 2,
+                available: 1
+            })
+        );
+        assert_eq!(
+            reconstruct_blocks(data, &[DedupChunk::Unique(usize::MAX)]),
+            Err(ReconstructError::ChunkOutOfBounds {
+                entry: 0,
+                length: usize::MAX,
+                available: 4
+            })
+        );
+        let err =
+            reconstruct_blocks(data, &[DedupChunk::Unique(2), DedupChunk::Repeat(1)]).unwrap_err();
+        assert_eq!(
+            err,
+            ReconstructError::UnknownChunk {
+                entry: 1,
+                index: 1,
+                unique: 1
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Entry 1 repeats chunk 1, but only 1 unique chunks precede it"
+        );
+    }
+}
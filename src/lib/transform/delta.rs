@@ -0,0 +1,91 @@
+use num_traits::{WrappingAdd, WrappingSub, Zero};
+
+use super::Transform;
+
+/// Encodes a sequence of numbers as the differences between consecutive
+/// numbers. The first number is kept as is. Arithmetic wraps around, so no
+/// overflow can occur.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of numbers to be encoded.
+///
+/// ## Returns
+///
+/// A vector of differences.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::delta::encode_delta;
+/// let encoded = encode_delta(&[10u8, 11, 12, 12, 9]);
+/// assert_eq!(encoded, vec![10, 1, 1, 0, 253]);
+/// ```
+pub fn encode_delta<T: Copy + Zero + WrappingSub>(input: &[T]) -> Vec<T> {
+    let mut previous = T::zero();
+    input
+        .iter()
+        .map(|el| {
+            let diff = el.wrapping_sub(&previous);
+            previous = *el;
+            diff
+        })
+        .collect()
+}
+
+/// Decodes a sequence of differences produced by [encode_delta].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of differences to be decoded.
+///
+/// ## Returns
+///
+/// A vector of numbers representing the decoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::delta::decode_delta;
+/// let decoded = decode_delta(&[10u8, 1, 1, 0, 253]);
+/// assert_eq!(decoded, vec![10, 11, 12, 12, 9]);
+/// ```
+pub fn decode_delta<T: Copy + Zero + WrappingAdd>(input: &[T]) -> Vec<T> {
+    let mut previous = T::zero();
+    input
+        .iter()
+        .map(|diff| {
+            previous = previous.wrapping_add(diff);
+            previous
+        })
+        .collect()
+}
+
+/// The delta transform, as a [Transform].
+pub struct Delta;
+
+impl<T: Copy + Zero + WrappingAdd + WrappingSub> Transform<T> for Delta {
+    type Output = Vec<T>;
+    type State = ();
+
+    fn forward(&self, input: &[T]) -> (Self::Output, Self::State) {
+        (encode_delta(input), ())
+    }
+
+    fn inverse(&self, output: &Self::Output, _state: &Self::State) -> Vec<T> {
+        decode_delta(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta() {
+        let input: Vec<i32> = vec![100, 101, 103, 103, 90, i32::MIN, i32::MAX];
+        let (output, state) = Delta.forward(&input);
+        assert_eq!(&output[..4], &[100, 1, 2, 0]);
+        assert_eq!(Delta.inverse(&output, &state), input);
+    }
+}
@@ -7,3 +7,58 @@ pub mod bwt;
 /// transform that is used to improve the compression ratio of the data,
 /// usually in combination with other transforms.
 pub mod mtf;
+
+/// Module providing content-defined deduplication of repeated blocks. A
+/// pre-pass that removes repetitions too far apart for a sliding window to
+/// catch.
+pub mod dedup;
+
+/// Module providing run-length encoding (RLE). Replaces runs of repeated
+/// elements with the element and the length of the run.
+pub mod rle;
+
+/// Module providing the delta transform. Replaces each number with its
+/// difference from the previous one, which makes slowly changing sequences
+/// consist of small numbers.
+pub mod delta;
+
+/// A reversible transform of a sequence of elements. Implemented by all
+/// transforms in this module, so that they can be used and composed
+/// uniformly.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::{Transform, bwt::BurrowsWheeler};
+/// let (output, state) = BurrowsWheeler.forward(b"banana");
+/// assert_eq!(BurrowsWheeler.inverse(&output, &state), b"banana");
+/// ```
+pub trait Transform<T> {
+    /// The transformed data.
+    type Output;
+    /// Additional information needed to reverse the transform.
+    type State;
+
+    /// Applies the transform.
+    ///
+    /// ## Arguments
+    ///
+    /// - `input`: A slice of data to be transformed.
+    ///
+    /// ## Returns
+    ///
+    /// The transformed data, and the state needed to reverse the transform.
+    fn forward(&self, input: &[T]) -> (Self::Output, Self::State);
+
+    /// Reverses the transform.
+    ///
+    /// ## Arguments
+    ///
+    /// - `output`: The transformed data.
+    /// - `state`: The state returned along with the transformed data.
+    ///
+    /// ## Returns
+    ///
+    /// The original data.
+    fn inverse(&self, output: &Self::Output, state: &Self::State) -> Vec<T>;
+}
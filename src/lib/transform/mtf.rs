@@ -1,3 +1,7 @@
+use super::Transform;
+
+use std::{error, fmt};
+
 /// Encodes a sequence of elements using the Move-to-Front (MTF) algorithm.
 ///
 /// ## Arguments
@@ -32,6 +36,133 @@ pub fn encode_move_to_front<T: Eq + Clone>(input: &[T], ordering: &mut Vec<T>) -
     return result;
 }
 
+/// Encodes a stream of elements using the Move-to-Front (MTF) algorithm,
+/// lazily. Every index is produced as the element is pulled from the input,
+/// so MTF can sit between streaming stages without collecting the data.
+///
+/// ## Arguments
+///
+/// - `input`: An iterator over the elements to be encoded.
+/// - `ordering`: A mutable reference to a vector representing the current ordering of elements.
+///
+/// ## Returns
+///
+/// An iterator over the indices representing the encoded elements.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::mtf::encode_move_to_front_iter;
+/// let mut ordering = vec!['e', 'h', 'l', 'o'];
+/// let encoded: Vec<usize> = encode_move_to_front_iter("hello".chars(), &mut ordering).collect();
+/// assert_eq!(encoded, vec![1, 1, 2, 0, 3]);
+/// ```
+pub fn encode_move_to_front_iter<T: Eq, I: IntoIterator<Item = T>>(
+    input: I,
+    ordering: &mut Vec<T>,
+) -> impl Iterator<Item = usize> {
+    input.into_iter().map(move |el| {
+        let idx = ordering
+            .iter()
+            .position(|x| *x == el)
+            .expect("Element not found in ordering");
+        ordering.remove(idx);
+        ordering.insert(0, el);
+        idx
+    })
+}
+
+/// Error returned by [encode_move_to_front_u8] when an index doesn't fit in
+/// a byte.
+#[derive(Debug, PartialEq)]
+pub struct IndexOverflowError {
+    /// The offending index
+    pub index: usize,
+}
+
+impl fmt::Display for IndexOverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Index {} doesn't fit in a byte", self.index)
+    }
+}
+
+impl error::Error for IndexOverflowError {}
+
+/// Encodes a sequence of elements using the Move-to-Front (MTF) algorithm,
+/// producing bytes. Useful for byte pipelines, where the ordering has at
+/// most 256 elements.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of elements to be encoded.
+/// - `ordering`: A mutable reference to a vector representing the current ordering of elements.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded elements, or an error naming
+/// the first index greater than 255. The ordering is updated by the whole
+/// input either way.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::mtf::encode_move_to_front_u8;
+/// let input = vec!['h', 'e', 'l', 'l', 'o'];
+/// let mut ordering = vec!['e', 'h', 'l', 'o'];
+/// let encoded = encode_move_to_front_u8(&input, &mut ordering).unwrap();
+/// assert_eq!(encoded, vec![1u8, 1, 2, 0, 3]);
+/// ```
+pub fn encode_move_to_front_u8<T: Eq + Clone>(
+    input: &[T],
+    ordering: &mut Vec<T>,
+) -> Result<Vec<u8>, IndexOverflowError> {
+    encode_move_to_front(input, ordering)
+        .into_iter()
+        .map(|index| u8::try_from(index).map_err(|_| IndexOverflowError { index }))
+        .collect()
+}
+
+/// Encodes a sequence of bytes using the Move-to-Front (MTF) algorithm.
+/// Produces the same output as [encode_move_to_front], but the ordering is a
+/// fixed array of all 256 bytes, so moving a byte to the front is a single
+/// copy of the bytes ahead of it, and every index fits in a byte.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of bytes to be encoded.
+/// - `ordering`: A mutable reference to the current ordering of the bytes.
+///
+/// ## Returns
+///
+/// A vector of indices representing the encoded bytes.
+///
+/// ## Panics
+///
+/// If a byte of the input is missing from the ordering, which can only happen
+/// if the ordering is not a permutation of all 256 bytes.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::mtf::encode_move_to_front_bytes;
+/// let mut ordering: [u8; 256] = std::array::from_fn(|i| i as u8);
+/// let encoded = encode_move_to_front_bytes(b"aab", &mut ordering);
+/// assert_eq!(encoded, vec![b'a', 0, b'b']);
+/// ```
+pub fn encode_move_to_front_bytes(input: &[u8], ordering: &mut [u8; 256]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(input.len());
+    for &el in input {
+        let idx = ordering
+            .iter()
+            .position(|&x| x == el)
+            .expect("Byte not found in ordering, it must hold all 256 bytes");
+        result.push(idx as u8);
+        ordering.copy_within(..idx, 1);
+        ordering[0] = el;
+    }
+    result
+}
+
 /// Decodes a sequence of indices using the Move-to-Front (MTF) algorithm.
 ///
 /// ## Arguments
@@ -64,6 +195,61 @@ pub fn decode_move_to_front<T: Eq + Clone>(input: &[usize], ordering: &mut Vec<T
     return result;
 }
 
+/// Decodes a sequence of indices encoded by [encode_move_to_front_bytes].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of indices to be decoded.
+/// - `ordering`: A mutable reference to the current ordering of the bytes.
+///
+/// ## Returns
+///
+/// A vector of the decoded bytes.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::mtf::{decode_move_to_front_bytes, encode_move_to_front_bytes};
+/// let ordering: [u8; 256] = std::array::from_fn(|i| i as u8);
+/// let encoded = encode_move_to_front_bytes(b"hello", &mut ordering.clone());
+/// assert_eq!(decode_move_to_front_bytes(&encoded, &mut ordering.clone()), b"hello");
+/// ```
+pub fn decode_move_to_front_bytes(input: &[u8], ordering: &mut [u8; 256]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(input.len());
+    for &idx in input {
+        let idx = idx as usize;
+        let el = ordering[idx];
+        result.push(el);
+        ordering.copy_within(..idx, 1);
+        ordering[0] = el;
+    }
+    result
+}
+
+/// The Move-to-Front transform, as a [Transform]. Holds the initial
+/// ordering, which is also the state needed to reverse the transform.
+pub struct MoveToFront<T> {
+    /// The initial ordering of the elements
+    pub ordering: Vec<T>,
+}
+
+impl<T: Eq + Clone> Transform<T> for MoveToFront<T> {
+    type Output = Vec<usize>;
+    type State = Vec<T>;
+
+    fn forward(&self, input: &[T]) -> (Self::Output, Self::State) {
+        let mut ordering = self.ordering.clone();
+        (
+            encode_move_to_front(input, &mut ordering),
+            self.ordering.clone(),
+        )
+    }
+
+    fn inverse(&self, output: &Self::Output, state: &Self::State) -> Vec<T> {
+        decode_move_to_front(output, &mut state.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +269,82 @@ mod tests {
         let decoded = decode_move_to_front(&input, &mut ordering);
         assert_eq!(decoded, vec!['h', 'e', 'l', 'l', 'o']);
     }
+
+    #[test]
+    fn test_mtf_transform() {
+        let mtf = MoveToFront {
+            ordering: vec!['e', 'h', 'l', 'o'],
+        };
+        let input = vec!['h', 'e', 'l', 'l', 'o', 'h', 'e', 'l', 'l', 'o'];
+        let (output, state) = mtf.forward(&input);
+        assert_eq!(&output[..5], &[1, 1, 2, 0, 3]);
+        assert_eq!(mtf.inverse(&output, &state), input);
+    }
+
+    #[test]
+    fn test_mtf_u8() {
+        let input: Vec<u16> = vec![3, 3, 200, 7, 3, 255, 0, 200];
+        let ordering: Vec<u16> = (0..256).collect();
+        let wide = encode_move_to_front(&input, &mut ordering.clone());
+        let narrow = encode_move_to_front_u8(&input, &mut ordering.clone()).unwrap();
+        assert_eq!(
+            narrow,
+            wide.into_iter().map(|x| x as u8).collect::<Vec<_>>()
+        );
+
+        let ordering: Vec<u16> = (0..300).collect();
+        assert_eq!(
+            encode_move_to_front_u8(&[299], &mut ordering.clone()),
+            Err(IndexOverflowError { index: 299 })
+        );
+    }
+
+    #[test]
+    fn test_mtf_bytes() {
+        let ordering: [u8; 256] = std::array::from_fn(|i| (255 - i) as u8);
+        let mut input: Vec<u8> = b"bananas and mississippi".repeat(20);
+        input.extend((0..=255).rev());
+        let generic = encode_move_to_front(&input, &mut ordering.to_vec());
+        let mut fast_ordering = ordering;
+        let fast = encode_move_to_front_bytes(&input, &mut fast_ordering);
+        assert_eq!(
+            fast,
+            generic.into_iter().map(|x| x as u8).collect::<Vec<_>>()
+        );
+        // the orderings end up the same too
+        let mut generic_ordering = ordering.to_vec();
+        encode_move_to_front(&input, &mut generic_ordering);
+        assert_eq!(fast_ordering.to_vec(), generic_ordering);
+        assert_eq!(
+            decode_move_to_front_bytes(&fast, &mut ordering.clone()),
+            input
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Byte not found in ordering")]
+    fn test_mtf_bytes_missing_byte() {
+        let mut ordering = [0u8; 256];
+        encode_move_to_front_bytes(b"a", &mut ordering);
+    }
+
+    #[test]
+    fn test_mtf_iter() {
+        let input = b"bananas and mississippi".repeat(20);
+        let ordering: Vec<u8> = (0..=255).collect();
+        let mut slice_ordering = ordering.clone();
+        let expected = encode_move_to_front(&input, &mut slice_ordering);
+        let mut iter_ordering = ordering;
+        let encoded: Vec<usize> =
+            encode_move_to_front_iter(input.iter().copied(), &mut iter_ordering).collect();
+        assert_eq!(encoded, expected);
+        assert_eq!(iter_ordering, slice_ordering);
+
+        // only the pulled elements move to the front
+        let mut ordering = vec!['e', 'h', 'l', 'o'];
+        let mut encoded = encode_move_to_front_iter("hello".chars(), &mut ordering);
+        assert_eq!(encoded.next(), Some(1));
+        drop(encoded);
+        assert_eq!(ordering, vec!['h', 'e', 'l', 'o']);
+    }
 }
@@ -0,0 +1,84 @@
+use super::Transform;
+
+/// Encodes a sequence of elements using run-length encoding.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of elements to be encoded.
+///
+/// ## Returns
+///
+/// A vector of elements, each paired with the length of its run.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::rle::encode_run_length;
+/// let encoded = encode_run_length(b"aaabcc");
+/// assert_eq!(encoded, vec![(b'a', 3), (b'b', 1), (b'c', 2)]);
+/// ```
+pub fn encode_run_length<T: Clone + PartialEq>(input: &[T]) -> Vec<(T, usize)> {
+    let mut result: Vec<(T, usize)> = Vec::new();
+    for el in input {
+        match result.last_mut() {
+            Some((last, count)) if last == el => *count += 1,
+            _ => result.push((el.clone(), 1)),
+        }
+    }
+    result
+}
+
+/// Decodes a sequence of runs produced by [encode_run_length].
+///
+/// ## Arguments
+///
+/// - `input`: A slice of elements paired with the lengths of their runs.
+///
+/// ## Returns
+///
+/// A vector of elements representing the decoded data.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::transform::rle::decode_run_length;
+/// let decoded = decode_run_length(&[(b'a', 3), (b'b', 1), (b'c', 2)]);
+/// assert_eq!(decoded, b"aaabcc");
+/// ```
+pub fn decode_run_length<T: Clone>(input: &[(T, usize)]) -> Vec<T> {
+    let mut result = Vec::with_capacity(input.iter().map(|(_, count)| count).sum());
+    for (el, count) in input {
+        result.extend(std::iter::repeat_n(el.clone(), *count));
+    }
+    result
+}
+
+/// Run-length encoding, as a [Transform].
+pub struct RunLength;
+
+impl<T: Clone + PartialEq> Transform<T> for RunLength {
+    type Output = Vec<(T, usize)>;
+    type State = ();
+
+    fn forward(&self, input: &[T]) -> (Self::Output, Self::State) {
+        (encode_run_length(input), ())
+    }
+
+    fn inverse(&self, output: &Self::Output, _state: &Self::State) -> Vec<T> {
+        decode_run_length(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_length() {
+        for input in [&b""[..], b"a", b"aaaa", b"abcabc", b"aabbbcccc"] {
+            let (output, state) = RunLength.forward(input);
+            assert_eq!(output.len(), input.chunk_by(|a, b| a == b).count());
+            assert_eq!(RunLength.inverse(&output, &state), input);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::lz::{
+    lz77::{lz77_decode, lz77_encode},
+    lz78::{lz78_decode, lz78_encode},
+    lzw::{lzw_decode, lzw_encode},
+};
+
+use std::hash::Hash;
+
+/// Checks whether a decoded sequence matches the original sequence.
+///
+/// ## Arguments
+///
+/// - `original`: The data before encoding.
+/// - `decoded`: The data after encoding and decoding.
+///
+/// ## Returns
+///
+/// `true` if both sequences are equal.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::verify::roundtrips;
+/// assert!(roundtrips(b"abc", b"abc"));
+/// assert!(!roundtrips(b"abc", b"ab"));
+/// ```
+pub fn roundtrips<T: PartialEq>(original: &[T], decoded: &[T]) -> bool {
+    original == decoded
+}
+
+/// Encodes and decodes the input using the LZ77 algorithm, and checks whether
+/// the result matches the input.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be verified.
+/// - `max_offset`: The maximum offset to search for matches.
+/// - `max_length`: The maximum length of matches.
+///
+/// ## Returns
+///
+/// `true` if the input survives the round trip.
+///
+/// ## Example
+///
+/// ```
+/// use generic_compression::verify::verify_lz77;
+/// assert!(verify_lz77(b"ABABABABA", 4, 4));
+/// ```
+pub fn verify_lz77<T: PartialEq + Clone>(
+    input: &[T],
+    max_offset: usize,
+    max_length: usize,
+) -> bool {
+    let encoded = lz77_encode(input, max_offset, max_length);
+    lz77_decode(&encoded).is_ok_and(|decoded| roundtrips(input, &decoded))
+}
+
+/// Encodes and decodes the input using the LZ78 algorithm, and checks whether
+/// the result matches the input.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be verified.
+/// - `lookahead_max`: The maximum lookahead size.
+/// - `max_dictionary_size`: The maximum size of the dictionary.
+///
+/// ## Returns
+///
+/// `true` if the input survives the round trip.
+pub fn verify_lz78<T: Eq + Hash + Clone>(
+    input: &[T],
+    lookahead_max: usize,
+    max_dictionary_size: usize,
+) -> bool {
+    let encoded = lz78_encode(input, lookahead_max, max_dictionary_size);
+    roundtrips(input, &lz78_decode(&encoded, max_dictionary_size))
+}
+
+/// Encodes and decodes the input using the LZW algorithm, and checks whether
+/// the result matches the input.
+///
+/// ## Arguments
+///
+/// - `input`: A slice of data to be verified.
+/// - `initial`: An initial dictionary to start encoding.
+/// - `max_lookahead`: The maximum lookahead size.
+///
+/// ## Returns
+///
+/// `true` if the input survives the round trip.
+pub fn verify_lzw<T: Eq + Hash + Clone>(input: &[T], initial: &[T], max_lookahead: usize) -> bool {
+    let encoded = lzw_encode(input, initial, max_lookahead);
+    roundtrips(input, &lzw_decode(&encoded, initial))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let input = b"RATABARBARATABARBARAT";
+        assert!(verify_lz77(input, 4, 4));
+        assert!(verify_lz77(&input[..0], 4, 4));
+        assert!(verify_lz78(input, 4, 4));
+        assert!(verify_lzw(input, b"ABRT", 4));
+    }
+
+    #[test]
+    fn test_roundtrips() {
+        assert!(roundtrips::<u8>(&[], &[]));
+        assert!(!roundtrips(&[1, 2, 3], &[1, 2, 4]));
+    }
+}